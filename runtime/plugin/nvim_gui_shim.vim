@@ -3,17 +3,19 @@ if !has('nvim') || exists('g:GuiLoaded')
 	finish
 endif
 let g:GuiLoaded = 1
+" channel of gui, embedded nvim is connected to gui by channel 1
+let g:GtkGuiChannel = get(g:, 'GtkGuiChannel', 1)
 
 function s:GuiClipboard()
     let g:clipboard = {
             \ 'name': 'gtk',
             \ 'copy': {
-            \     '+': {lines, regtype -> rpcnotify(1, 'Gui', 'Clipboard', 'Set', '+', join(lines, "\n"))},
-            \     '*': {lines, regtype -> rpcnotify(1, 'Gui', 'Clipboard', 'Set', '*', join(lines, "\n"))},
+            \     '+': {lines, regtype -> rpcnotify(g:GtkGuiChannel, 'Gui', 'Clipboard', 'Set', '+', join(lines, "\n"))},
+            \     '*': {lines, regtype -> rpcnotify(g:GtkGuiChannel, 'Gui', 'Clipboard', 'Set', '*', join(lines, "\n"))},
             \ },
             \ 'paste': {
-            \     '+': { -> rpcrequest(1, 'Gui', 'Clipboard', 'Get', '+') },
-            \     '*': { -> rpcrequest(1, 'Gui', 'Clipboard', 'Get', '*') },
+            \     '+': { -> rpcrequest(g:GtkGuiChannel, 'Gui', 'Clipboard', 'Get', '+') },
+            \     '*': { -> rpcrequest(g:GtkGuiChannel, 'Gui', 'Clipboard', 'Get', '*') },
             \ },
             \ 'cache_enabled': 0,
         \ }
@@ -24,7 +26,7 @@ endfunction
 
 " Set GUI font
 function! GuiFont(fname, ...) abort
-	call rpcnotify(1, 'Gui', 'Font', s:NvimQtToPangoFont(a:fname))
+	call rpcnotify(g:GtkGuiChannel, 'Gui', 'Font', s:NvimQtToPangoFont(a:fname))
 endfunction
 
 " Some subset of parse command from neovim-qt
@@ -61,34 +63,68 @@ endfunction
 command! -nargs=1 -bang Guifont call s:GuiFontCommand("<args>", "<bang>")
 command! -nargs=1 -bang GuiFont call s:GuiFontCommand("<args>", "<bang>")
 
-command! -nargs=? GuiFontFeatures call rpcnotify(1, 'Gui', 'FontFeatures', <q-args>)
-command! -nargs=1 GuiLinespace call rpcnotify(1, 'Gui', 'Linespace', <q-args>)
+command! -nargs=? GuiFontFeatures call rpcnotify(g:GtkGuiChannel, 'Gui', 'FontFeatures', <q-args>)
+command! -nargs=? GuiFontBold call rpcnotify(g:GtkGuiChannel, 'Gui', 'FontFace', 'bold', <q-args>)
+command! -nargs=? GuiFontItalic call rpcnotify(g:GtkGuiChannel, 'Gui', 'FontFace', 'italic', <q-args>)
+command! -nargs=? GuiFontBoldItalic call rpcnotify(g:GtkGuiChannel, 'Gui', 'FontFace', 'bold_italic', <q-args>)
+command! -nargs=? GuiFontVariations call rpcnotify(g:GtkGuiChannel, 'Gui', 'FontVariations', <q-args>)
+command! -nargs=? GuiFontWide call rpcnotify(g:GtkGuiChannel, 'Gui', 'FontWide', <q-args>)
+command! -nargs=? GuiFontFallback call rpcnotify(g:GtkGuiChannel, 'Gui', 'FontFallback', <q-args>)
+command! -nargs=1 GuiLinespace call rpcnotify(g:GtkGuiChannel, 'Gui', 'Linespace', <q-args>)
+command! -nargs=1 GuiLetterSpace call rpcnotify(g:GtkGuiChannel, 'Gui', 'LetterSpace', <q-args>)
+command! -nargs=+ GuiPadding call rpcnotify(g:GtkGuiChannel, 'Gui', 'Padding', <q-args>)
 
 command! NGClipboard call s:GuiClipboard()
 
-command! NGToggleSidebar call rpcnotify(1, 'Gui', 'Command', 'ToggleSidebar')
-command! NGOpenSidebar call rpcnotify(1, 'Gui', 'Command', 'OpenSidebar')
-command! NGCloseSidebar call rpcnotify(1, 'Gui', 'Command', 'CloseSidebar')
-command! NGShowProjectView call rpcnotify(1, 'Gui', 'Command', 'ShowProjectView')
-command! -nargs=+ NGTransparency call rpcnotify(1, 'Gui', 'Command', 'Transparency', <f-args>)
-command! -nargs=1 NGPreferDarkTheme call rpcnotify(1, 'Gui', 'Command', 'PreferDarkTheme', <q-args>)
-command! -nargs=1 NGSetCursorBlink call rpcnotify(1, 'Gui', 'Command', 'SetCursorBlink', <q-args>)
-command! NGToggleFullscreen call rpcnotify(1, 'Gui', 'Command', 'ToggleFullscreen')
-command! NGFullscreen call rpcnotify(1, 'Gui', 'Command', 'Fullscreen')
-command! NGUnfullscreen call rpcnotify(1, 'Gui', 'Command', 'Unfullscreen')
-command! NGHideExtTabline call rpcnotify(1, 'Gui', 'Command', 'HideExtTabline')
-command! NGUnhideExtTabline call rpcnotify(1, 'Gui', 'Command', 'UnhideExtTabline')
-command! NGSidebarShowLines call rpcnotify(1, 'Gui', 'Command', 'SidebarShowLines')
-command! NGSidebarHideLines call rpcnotify(1, 'Gui', 'Command', 'SidebarHideLines')
-command! NGSidebarToggleLines call rpcnotify(1, 'Gui', 'Command', 'SidebarToggleLines')
-command! NGSidebarShowHidden call rpcnotify(1, 'Gui', 'Command', 'SidebarShowHidden')
-command! NGSidebarHideHidden call rpcnotify(1, 'Gui', 'Command', 'SidebarHideHidden')
-command! NGSidebarToggleHidden call rpcnotify(1, 'Gui', 'Command', 'SidebarToggleHidden')
-command! NGTogglePreview call rpcnotify(1, 'Gui', 'Command', 'TogglePreview')
-command! NGShowPreview call rpcnotify(1, 'Gui', 'Command', 'ShowPreview')
-command! NGHidePreview call rpcnotify(1, 'Gui', 'Command', 'HidePreview')
-command! -nargs=1 NGSetPreviewType call rpcnotify(1, 'Gui', 'Command', 'SetPreviewType', <q-args>)
-command! -nargs=1 NGSetPreviewWidth call rpcnotify(1, 'Gui', 'Command', 'SetPreviewWidth', <q-args>)
+command! NGToggleSidebar call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'ToggleSidebar')
+command! NGOpenSidebar call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'OpenSidebar')
+command! NGCloseSidebar call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'CloseSidebar')
+command! NGShowProjectView call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'ShowProjectView')
+command! -nargs=+ NGTransparency call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'Transparency', <f-args>)
+command! -nargs=1 NGPreferDarkTheme call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'PreferDarkTheme', <q-args>)
+command! -nargs=1 NGSetCursorBlink call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SetCursorBlink', <q-args>)
+command! -nargs=+ NGCursorAnimation call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'CursorAnimation', <f-args>)
+" NGBackgroundImage [path [scaled|tiled|centered [opacity]]], without path image is removed
+function s:NGBackgroundImage(...) abort
+	let path = a:0 > 0 ? expand(a:1) : ''
+	call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'BackgroundImage', path, get(a:000, 1, 'scaled'), get(a:000, 2, '1.0'))
+endfunction
+command! -nargs=* -complete=file NGBackgroundImage call s:NGBackgroundImage(<f-args>)
+" NGUiEvent bell|visual_bell|suspend|chdir on|off
+command! -nargs=+ NGUiEvent call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'UiEvent', <f-args>)
+command! -nargs=? NGColorSwatches call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'ColorSwatches', <q-args>)
+command! NGColorPicker call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'ColorPicker', bufnr('%'), line('.') - 1, col('.') - 1, getline('.'))
+command! NGToggleFullscreen call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'ToggleFullscreen')
+command! NGFullscreen call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'Fullscreen')
+command! NGUnfullscreen call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'Unfullscreen')
+command! NGHideExtTabline call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'HideExtTabline')
+command! NGUnhideExtTabline call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'UnhideExtTabline')
+command! NGSidebarShowLines call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SidebarShowLines')
+command! NGSidebarHideLines call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SidebarHideLines')
+command! NGSidebarToggleLines call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SidebarToggleLines')
+command! NGSidebarShowHidden call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SidebarShowHidden')
+command! NGSidebarHideHidden call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SidebarHideHidden')
+command! NGSidebarToggleHidden call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SidebarToggleHidden')
+command! NGTogglePreview call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'TogglePreview')
+command! NGShowPreview call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'ShowPreview')
+command! NGHidePreview call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'HidePreview')
+command! -nargs=1 NGSetPreviewType call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SetPreviewType', <q-args>)
+command! -nargs=1 NGSetPreviewWidth call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SetPreviewWidth', <q-args>)
+command! NGRedrawStats echo rpcrequest(g:GtkGuiChannel, 'RedrawStats')
+
+" Called by gui before ui detach, attached server keeps running
+" after gui is closed and gui channel is not valid anymore
+function! GtkGuiDetach() abort
+	if get(get(g:, 'clipboard', {}), 'name', '') ==# 'gtk'
+		unlet g:clipboard
+		unlet! g:loaded_clipboard_provider
+		runtime autoload/provider/clipboard.vim
+	endif
+	unlet! g:GtkGuiChannel
+endfunction
 
 " autocmds
-autocmd BufEnter * call rpcnotify(1, 'Gui', 'Command', 'SetPreviewType', &filetype)
+augroup NvimGtkShim
+	autocmd!
+	autocmd BufEnter * if exists('g:GtkGuiChannel') | call rpcnotify(g:GtkGuiChannel, 'Gui', 'Command', 'SetPreviewType', &filetype) | endif
+augroup END
@@ -0,0 +1,92 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::prelude::*;
+
+// microseconds, same units as gdk::FrameClock::get_frame_time
+const FLASH_DURATION: i64 = 200_000;
+// overlay alpha at flash start
+const FLASH_ALPHA: f64 = 0.3;
+
+fn flash_alpha(elapsed: i64) -> f64 {
+    if elapsed >= FLASH_DURATION {
+        0.0
+    } else {
+        let t = elapsed.max(0) as f64 / FLASH_DURATION as f64;
+        FLASH_ALPHA * (1.0 - t).powi(2)
+    }
+}
+
+struct State {
+    start_time: Option<i64>,
+    alpha: f64,
+    ticking: bool,
+}
+
+/// Overlay over grid that fades out on each frame clock tick
+pub struct VisualBell {
+    state: Rc<RefCell<State>>,
+    drawing_area: gtk::DrawingArea,
+}
+
+impl VisualBell {
+    pub fn new(drawing_area: &gtk::DrawingArea) -> Self {
+        VisualBell {
+            state: Rc::new(RefCell::new(State {
+                start_time: None,
+                alpha: 0.0,
+                ticking: false,
+            })),
+            drawing_area: drawing_area.clone(),
+        }
+    }
+
+    /// Start flash, flash that is already running starts again
+    pub fn flash(&self) {
+        let mut state = self.state.borrow_mut();
+        state.start_time = None;
+        state.alpha = FLASH_ALPHA;
+
+        if !state.ticking {
+            state.ticking = true;
+            self.start_tick();
+        }
+    }
+
+    /// Current alpha of overlay, zero when there is no flash
+    pub fn alpha(&self) -> f64 {
+        self.state.borrow().alpha
+    }
+
+    fn start_tick(&self) {
+        let state = self.state.clone();
+        self.drawing_area.add_tick_callback(move |drawing_area, clock| {
+            let mut state = state.borrow_mut();
+
+            let frame_time = clock.get_frame_time();
+            let start_time = *state.start_time.get_or_insert(frame_time);
+            state.alpha = flash_alpha(frame_time - start_time);
+
+            let running = state.alpha > 0.0;
+            if !running {
+                state.ticking = false;
+            }
+
+            drawing_area.queue_draw();
+            Continue(running)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flash_alpha() {
+        assert_eq!(FLASH_ALPHA, flash_alpha(0));
+        assert!(flash_alpha(FLASH_DURATION / 2) < FLASH_ALPHA);
+        assert!(flash_alpha(FLASH_DURATION / 2) > 0.0);
+        assert_eq!(0.0, flash_alpha(FLASH_DURATION));
+    }
+}
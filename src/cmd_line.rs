@@ -212,9 +212,33 @@ impl State {
         // queue old cursor position
         self.queue_redraw_cursor();
 
+        let prev_cursor = self.cursor_pos();
         if let Some(l) = self.levels.get_mut(level - 1) {
             l.set_cursor(render_state, pos)
         }
+        self.move_cursor_from(prev_cursor, render_state);
+    }
+
+    fn cursor_pos(&self) -> Option<(usize, usize)> {
+        self.levels
+            .last()
+            .map(|l| l.model_layout.model.get_cursor())
+    }
+
+    fn move_cursor_from(
+        &mut self,
+        prev_cursor: Option<(usize, usize)>,
+        render_state: &shell::RenderState,
+    ) {
+        if let (Some((prev_row, prev_col)), Some((row, col))) = (prev_cursor, self.cursor_pos()) {
+            let cell_metrics = render_state.font_ctx.cell_metrics();
+            if let Some(cursor) = self.cursor.as_mut() {
+                cursor.move_from(
+                    (prev_col as f64 - col as f64) * cell_metrics.char_width,
+                    (prev_row as f64 - row as f64) * cell_metrics.line_height,
+                );
+            }
+        }
     }
 
     fn queue_redraw_cursor(&mut self) {
@@ -252,6 +276,11 @@ impl cursor::CursorRedrawCb for State {
     fn queue_redraw_cursor(&mut self) {
         self.queue_redraw_cursor();
     }
+
+    fn queue_redraw_move(&mut self, _start_offset: (f64, f64)) {
+        // command line is small, whole line is redrawn
+        self.drawing_area.queue_draw();
+    }
 }
 
 pub struct CmdLine {
@@ -360,9 +389,13 @@ impl CmdLine {
         let render_state = render_state.borrow();
 
         if ctx.level_idx as usize == state.levels.len() {
-            let level = state.levels.last_mut().unwrap();
-            level.replace_from_ctx(ctx, &*render_state);
-            level.update_cache(&*render_state);
+            let prev_cursor = state.cursor_pos();
+            {
+                let level = state.levels.last_mut().unwrap();
+                level.replace_from_ctx(ctx, &render_state);
+                level.update_cache(&render_state);
+            }
+            state.move_cursor_from(prev_cursor, &render_state);
         } else {
             let mut level = Level::from_ctx(ctx, &*render_state);
             level.update_cache(&*render_state);
@@ -454,6 +487,15 @@ impl CmdLine {
             .set_cursor(render_state, pos as usize, level as usize);
     }
 
+    pub fn set_cursor_animation(&self, duration: u32, trail: bool) {
+        self.state
+            .borrow_mut()
+            .cursor
+            .as_mut()
+            .unwrap()
+            .set_animation(duration, trail);
+    }
+
     pub fn set_mode_info(&self, mode_info: Option<mode::ModeInfo>) {
         self.state
             .borrow_mut()
@@ -481,7 +523,7 @@ impl CmdLine {
         self.wild_renderer
             .set_property_foreground_rgba(Some(&render_state.hl.pmenu_fg().into()));
 
-        popup_menu::update_css(&self.wild_css_provider, &render_state.hl);
+        popup_menu::update_css(&self.wild_css_provider, &render_state.hl, 0);
 
         // set width
         // this calculation produce width more then needed, but this is looks ok :)
@@ -1,5 +1,6 @@
 use std;
 use std::borrow::Cow;
+use std::ops::Range;
 
 use gdk;
 
@@ -54,7 +55,26 @@ impl Color {
             (self.2 * 255.0) as u8
         )
     }
+
+    /// Parse `#rgb`, `#rrggbb`, `rgb(r, g, b)` or `rgba(r, g, b, a)`
+    pub fn from_literal(literal: &str) -> Option<Color> {
+        match ColorLiteral::parse(literal) {
+            Some((len, color, _, _)) if len == literal.len() => Some(color),
+            _ => None,
+        }
+    }
     
+    /// Css color with alpha channel
+    pub fn to_rgba(&self, alpha: f64) -> String {
+        format!(
+            "rgba({}, {}, {}, {})",
+            (self.0 * 255.0) as u8,
+            (self.1 * 255.0) as u8,
+            (self.2 * 255.0) as u8,
+            alpha
+        )
+    }
+
     pub fn hsp(&self) -> f64 {
         ((0.299 * self.0.powi(2)) + (0.587 * self.1.powi(2)) + (0.114 * self.2.powi(2))).sqrt()
     }
@@ -117,6 +137,150 @@ impl Color {
 }
 
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorNotation {
+    Hex { lowercase: bool },
+    Rgb,
+    Rgba,
+}
+
+/// Color literal found in text
+#[derive(Clone, PartialEq, Debug)]
+pub struct ColorLiteral {
+    /// byte range in text
+    pub range: Range<usize>,
+    pub color: Color,
+    pub alpha: f64,
+    pub notation: ColorNotation,
+}
+
+impl ColorLiteral {
+    /// All color literals in text, literal must not be part of word
+    pub fn find_all(text: &str) -> Vec<ColorLiteral> {
+        let mut literals = Vec::new();
+        let mut prev_word_char = false;
+        let mut skip_to = 0;
+
+        for (idx, ch) in text.char_indices() {
+            if idx >= skip_to && !prev_word_char && (ch == '#' || ch == 'r') {
+                if let Some((len, color, alpha, notation)) = ColorLiteral::parse(&text[idx..]) {
+                    let followed_by_word = text[idx + len..]
+                        .chars()
+                        .next()
+                        .map_or(false, is_word_char);
+
+                    if !followed_by_word {
+                        literals.push(ColorLiteral {
+                            range: idx..idx + len,
+                            color,
+                            alpha,
+                            notation,
+                        });
+                        skip_to = idx + len;
+                    }
+                }
+            }
+
+            prev_word_char = is_word_char(ch);
+        }
+
+        literals
+    }
+
+    /// Literal that contains given byte offset
+    pub fn find_at(text: &str, byte_idx: usize) -> Option<ColorLiteral> {
+        ColorLiteral::find_all(text)
+            .into_iter()
+            .find(|literal| literal.range.start <= byte_idx && byte_idx < literal.range.end)
+    }
+
+    /// Format color in notation of this literal
+    pub fn format(&self, color: &Color, alpha: f64) -> String {
+        let (r, g, b) = (
+            to_channel(color.0),
+            to_channel(color.1),
+            to_channel(color.2),
+        );
+
+        match self.notation {
+            ColorNotation::Hex { lowercase: true } => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            ColorNotation::Hex { lowercase: false } => format!("#{:02X}{:02X}{:02X}", r, g, b),
+            ColorNotation::Rgb => format!("rgb({}, {}, {})", r, g, b),
+            ColorNotation::Rgba => format!(
+                "rgba({}, {}, {}, {})",
+                r,
+                g,
+                b,
+                (alpha.clamp(0.0, 1.0) * 100.0).round() / 100.0
+            ),
+        }
+    }
+
+    /// Parse literal at start of text, returns length in bytes
+    fn parse(text: &str) -> Option<(usize, Color, f64, ColorNotation)> {
+        if let Some(rest) = text.strip_prefix('#') {
+            let digits = rest.bytes().take_while(u8::is_ascii_hexdigit).count();
+            let hex = &rest[..digits];
+
+            let color = match digits {
+                3 => {
+                    let v = u32::from_str_radix(hex, 16).ok()?;
+                    Color(
+                        f64::from((v >> 8) & 0xf) / 15.0,
+                        f64::from((v >> 4) & 0xf) / 15.0,
+                        f64::from(v & 0xf) / 15.0,
+                    )
+                }
+                6 => Color::from_indexed_color(u64::from_str_radix(hex, 16).ok()?),
+                _ => return None,
+            };
+
+            let lowercase = !hex.bytes().any(|b| b.is_ascii_uppercase());
+            Some((digits + 1, color, 1.0, ColorNotation::Hex { lowercase }))
+        } else {
+            let (notation, prefix) = if text.starts_with("rgba(") {
+                (ColorNotation::Rgba, "rgba(")
+            } else if text.starts_with("rgb(") {
+                (ColorNotation::Rgb, "rgb(")
+            } else {
+                return None;
+            };
+
+            let end = text.find(')')?;
+            let args: Vec<&str> = text[prefix.len()..end].split(',').map(str::trim).collect();
+            let channels = args
+                .iter()
+                .take(3)
+                .map(|arg| arg.parse::<u8>().ok())
+                .collect::<Option<Vec<u8>>>()?;
+
+            let alpha = match (notation, args.len()) {
+                (ColorNotation::Rgb, 3) => 1.0,
+                (ColorNotation::Rgba, 4) => args[3]
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|alpha| *alpha >= 0.0 && *alpha <= 1.0)?,
+                _ => return None,
+            };
+
+            let color = Color(
+                f64::from(channels[0]) / 255.0,
+                f64::from(channels[1]) / 255.0,
+                f64::from(channels[2]) / 255.0,
+            );
+            Some((end + 1, color, alpha, notation))
+        }
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+fn to_channel(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
 /// From https://jonasjacek.github.io/colors/ 
 const TERMINAL_COLORS: [(u8, u8, u8); 256] = [
     (0, 0, 0),
@@ -386,4 +550,46 @@ mod tests {
         let col = Color(0.0, 1.0, 0.0);
         assert_eq!("#00FF00", &col.to_hex());
     }
+
+    #[test]
+    fn test_from_literal() {
+        assert_eq!(Some(Color(1.0, 0.0, 0.0)), Color::from_literal("#f00"));
+        assert_eq!(Some(Color(0.0, 1.0, 0.0)), Color::from_literal("#00FF00"));
+        assert_eq!(Some(Color(0.0, 0.0, 1.0)), Color::from_literal("rgb(0, 0,255)"));
+        assert_eq!(Some(Color(1.0, 1.0, 1.0)), Color::from_literal("rgba(255, 255, 255, 0.5)"));
+        assert_eq!(None, Color::from_literal("#ff00"));
+        assert_eq!(None, Color::from_literal("rgb(256, 0, 0)"));
+        assert_eq!(None, Color::from_literal("rgb(0, 0, 0, 1)"));
+        assert_eq!(None, Color::from_literal("#f00 "));
+    }
+
+    #[test]
+    fn test_find_literals() {
+        let text = "a { color: #fff; border: rgba(0, 0, 0, 0.5) } #issue12 x#abc #ABCDEF";
+        let literals = ColorLiteral::find_all(text);
+
+        let ranges: Vec<&str> = literals.iter().map(|l| &text[l.range.clone()]).collect();
+        assert_eq!(vec!["#fff", "rgba(0, 0, 0, 0.5)", "#ABCDEF"], ranges);
+        assert_eq!(0.5, literals[1].alpha);
+
+        assert_eq!(Some(11..15), ColorLiteral::find_at(text, 14).map(|l| l.range));
+        assert_eq!(None, ColorLiteral::find_at(text, 15));
+    }
+
+    #[test]
+    fn test_format_literal() {
+        let literals = ColorLiteral::find_all("#abc #ABCDEF rgb(1, 2, 3) rgba(1, 2, 3, 1)");
+        let color = Color(1.0, 0.5, 0.0);
+
+        assert_eq!("#ff8000", literals[0].format(&color, 1.0));
+        assert_eq!("#FF8000", literals[1].format(&color, 1.0));
+        assert_eq!("rgb(255, 128, 0)", literals[2].format(&color, 1.0));
+        assert_eq!("rgba(255, 128, 0, 0.33)", literals[3].format(&color, 0.333));
+    }
+
+    #[test]
+    fn test_to_rgba() {
+        let col = Color(1.0, 0.0, 0.0);
+        assert_eq!("rgba(255, 0, 0, 0.8)", &col.to_rgba(0.8));
+    }
 }
@@ -2,8 +2,10 @@ use cairo;
 use crate::mode;
 use crate::render;
 use crate::render::CellMetrics;
+use crate::color::Color;
 use crate::highlight::HighlightMap;
 use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 use crate::ui::UiMutex;
 
 use glib;
@@ -52,6 +54,58 @@ impl BlinkCount {
     }
 }
 
+// blink fade in/out takes this number of steps, each changes alpha by 0.3
+const FADE_STEPS: u32 = 4;
+
+/// Duration of one fade step in ms, fade must fit into blinkon/blinkoff
+fn fade_step(on: u32, off: u32) -> u32 {
+    (on.min(off) / (FADE_STEPS * 2)).clamp(1, 60)
+}
+
+// number of ghost rectangles drawn in cursor trail
+const TRAIL_STEPS: usize = 4;
+
+fn ease_out(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+/// Cursor movement from previous position to current one
+struct MoveAnim {
+    // previous position relative to current, in pixels
+    start_offset: (f64, f64),
+    start: Instant,
+    duration: Duration,
+}
+
+impl MoveAnim {
+    fn progress(&self, now: Instant) -> Option<f64> {
+        let elapsed = now.duration_since(self.start);
+        if elapsed >= self.duration {
+            None
+        } else {
+            let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9;
+            let duration =
+                self.duration.as_secs() as f64 + f64::from(self.duration.subsec_nanos()) * 1e-9;
+            Some(elapsed / duration)
+        }
+    }
+
+    fn offset_at(&self, t: f64) -> (f64, f64) {
+        let k = 1.0 - t;
+        (self.start_offset.0 * k, self.start_offset.1 * k)
+    }
+
+    /// Cursor head offset, None when animation finished
+    fn offset(&self, now: Instant) -> Option<(f64, f64)> {
+        self.progress(now).map(|t| self.offset_at(ease_out(t)))
+    }
+
+    /// Trail end lags behind head
+    fn trail_offset(&self, now: Instant) -> Option<(f64, f64)> {
+        self.progress(now).map(|t| self.offset_at(t * t))
+    }
+}
+
 struct State<CB: CursorRedrawCb> {
     alpha: Alpha,
     anim_phase: AnimPhase,
@@ -59,6 +113,13 @@ struct State<CB: CursorRedrawCb> {
 
     timer: Option<glib::SourceId>,
     counter: Option<BlinkCount>,
+    // blinkon, blinkoff in ms
+    blink: (u32, u32),
+
+    move_duration: u32,
+    move_trail: bool,
+    move_anim: Option<MoveAnim>,
+    move_timer: Option<glib::SourceId>,
 }
 
 impl<CB: CursorRedrawCb> State<CB> {
@@ -69,6 +130,12 @@ impl<CB: CursorRedrawCb> State<CB> {
             redraw_cb,
             timer: None,
             counter: None,
+            blink: (mode::DEFAULT_BLINK.1, mode::DEFAULT_BLINK.2),
+
+            move_duration: 0,
+            move_trail: false,
+            move_anim: None,
+            move_timer: None,
         }
     }
 
@@ -92,6 +159,20 @@ pub trait Cursor {
         hl: &HighlightMap,
     ) -> f64;
 
+    /// Draw cursor that moves to current position,
+    /// return false in case cursor is not moving now
+    fn draw_moving(
+        &self,
+        _ctx: &cairo::Context,
+        _font_ctx: &render::Context,
+        _line_x: f64,
+        _line_y: f64,
+        _double_width: bool,
+        _hl: &HighlightMap,
+    ) -> bool {
+        false
+    }
+
     fn is_visible(&self) -> bool;
 
     fn mode_info(&self) -> Option<&mode::ModeInfo>;
@@ -126,9 +207,59 @@ impl Cursor for EmptyCursor {
     }
 }
 
+/// Always visible cursor without blinking, used for offscreen rendering
+#[cfg_attr(not(test), allow(dead_code))]
+pub struct StaticCursor {
+    mode_info: Option<mode::ModeInfo>,
+}
+
+impl StaticCursor {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn new(mode_info: Option<mode::ModeInfo>) -> Self {
+        StaticCursor { mode_info }
+    }
+}
+
+impl Cursor for StaticCursor {
+    fn draw(
+        &self,
+        ctx: &cairo::Context,
+        font_ctx: &render::Context,
+        line_y: f64,
+        double_width: bool,
+        hl: &HighlightMap,
+    ) -> f64 {
+        let current_point = ctx.get_current_point();
+
+        let bg = hl.cursor_color(self.mode_info().and_then(|mi| mi.attr_id(false)));
+        ctx.set_source_rgb(bg.0, bg.1, bg.2);
+
+        let (y, width, height) = cursor_rect(
+            self.mode_info(),
+            font_ctx.cell_metrics(),
+            line_y,
+            double_width,
+        );
+
+        ctx.rectangle(current_point.0, y, width, height);
+        ctx.fill();
+
+        1.0
+    }
+
+    fn is_visible(&self) -> bool {
+        true
+    }
+
+    fn mode_info(&self) -> Option<&mode::ModeInfo> {
+        self.mode_info.as_ref()
+    }
+}
+
 pub struct BlinkCursor<CB: CursorRedrawCb> {
     state: Arc<UiMutex<State<CB>>>,
     mode_info: Option<mode::ModeInfo>,
+    langmap: bool,
 }
 
 impl<CB: CursorRedrawCb + 'static> BlinkCursor<CB> {
@@ -136,6 +267,7 @@ impl<CB: CursorRedrawCb + 'static> BlinkCursor<CB> {
         BlinkCursor {
             state: Arc::new(UiMutex::new(State::new(redraw_cb))),
             mode_info: None,
+            langmap: false,
         }
     }
 
@@ -143,6 +275,11 @@ impl<CB: CursorRedrawCb + 'static> BlinkCursor<CB> {
         self.mode_info = mode_info;
     }
 
+    /// Language mapping is on (iminsert), cursor uses `attr_id_lm` highlight
+    pub fn set_langmap(&mut self, langmap: bool) {
+        self.langmap = langmap;
+    }
+
     pub fn set_cursor_blink(&mut self, val: i32) {
         let mut mut_state = self.state.borrow_mut();
         mut_state.counter = if val < 0 {
@@ -153,11 +290,10 @@ impl<CB: CursorRedrawCb + 'static> BlinkCursor<CB> {
     }
 
     pub fn start(&mut self) {
-        let blinkwait = self
+        let blink = self
             .mode_info
             .as_ref()
-            .and_then(|mi| mi.blinkwait)
-            .unwrap_or(500);
+            .map_or(Some(mode::DEFAULT_BLINK), mode::ModeInfo::blink);
 
         let state = self.state.clone();
         let mut mut_state = self.state.borrow_mut();
@@ -168,10 +304,14 @@ impl<CB: CursorRedrawCb + 'static> BlinkCursor<CB> {
             counter.count = 0;
         }
 
-        mut_state.timer = Some(glib::timeout_add(
-            if blinkwait > 0 { blinkwait } else { 500 },
-            move || anim_step(&state),
-        ));
+        // guicursor without blinking
+        let (blinkwait, blinkon, blinkoff) = match blink {
+            Some(blink) => blink,
+            None => return,
+        };
+
+        mut_state.blink = (blinkon, blinkoff);
+        mut_state.timer = Some(glib::timeout_add(blinkwait, move || anim_step(&state)));
     }
 
     pub fn reset_state(&mut self) {
@@ -199,6 +339,53 @@ impl<CB: CursorRedrawCb + 'static> BlinkCursor<CB> {
     pub fn busy_off(&mut self) {
         self.start();
     }
+
+    /// Set cursor movement animation duration in ms, 0 - disable animation
+    pub fn set_animation(&mut self, duration: u32, trail: bool) {
+        let mut mut_state = self.state.borrow_mut();
+        mut_state.move_duration = duration;
+        mut_state.move_trail = trail;
+        if duration == 0 {
+            mut_state.move_anim = None;
+        }
+    }
+
+    /// Cursor moved, (dx, dy) is previous position relative to new one in pixels
+    pub fn move_from(&mut self, dx: f64, dy: f64) {
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
+
+        let state = self.state.clone();
+        let mut mut_state = self.state.borrow_mut();
+        if mut_state.move_duration == 0 {
+            return;
+        }
+
+        // continue from position where cursor is drawn now
+        let now = Instant::now();
+        let (cur_dx, cur_dy) = mut_state
+            .move_anim
+            .as_ref()
+            .and_then(|anim| anim.offset(now))
+            .unwrap_or((0.0, 0.0));
+
+        mut_state.move_anim = Some(MoveAnim {
+            start_offset: (cur_dx + dx, cur_dy + dy),
+            start: now,
+            duration: Duration::from_millis(u64::from(mut_state.move_duration)),
+        });
+
+        if mut_state.move_timer.is_none() {
+            mut_state.move_timer = Some(glib::timeout_add(16, move || move_step(&state)));
+        }
+    }
+}
+
+impl<CB: CursorRedrawCb> BlinkCursor<CB> {
+    fn color(&self, hl: &HighlightMap) -> Color {
+        hl.cursor_color(self.mode_info.as_ref().and_then(|mi| mi.attr_id(self.langmap)))
+    }
 }
 
 impl<CB: CursorRedrawCb> Cursor for BlinkCursor<CB> {
@@ -214,7 +401,7 @@ impl<CB: CursorRedrawCb> Cursor for BlinkCursor<CB> {
 
         let current_point = ctx.get_current_point();
 
-        let bg = hl.cursor_bg();
+        let bg = self.color(hl);
         ctx.set_source_rgba(bg.0, bg.1, bg.2, state.alpha.0);
 
         let (y, width, height) = cursor_rect(
@@ -234,6 +421,70 @@ impl<CB: CursorRedrawCb> Cursor for BlinkCursor<CB> {
         state.alpha.0
     }
 
+    fn draw_moving(
+        &self,
+        ctx: &cairo::Context,
+        font_ctx: &render::Context,
+        line_x: f64,
+        line_y: f64,
+        double_width: bool,
+        hl: &HighlightMap,
+    ) -> bool {
+        let state = self.state.borrow();
+
+        // busy cursor is hidden
+        if state.anim_phase == AnimPhase::Busy {
+            return false;
+        }
+
+        let now = Instant::now();
+        let anim = match state.move_anim {
+            Some(ref anim) => anim,
+            None => return false,
+        };
+        let (dx, dy) = match anim.offset(now) {
+            Some(offset) => offset,
+            None => return false,
+        };
+
+        let bg = self.color(hl);
+        let (y, width, height) = cursor_rect(
+            self.mode_info(),
+            font_ctx.cell_metrics(),
+            line_y,
+            double_width,
+        );
+
+        // moving cursor blinks too
+        let alpha = state.alpha.0;
+
+        if state.move_trail {
+            if let Some((trail_dx, trail_dy)) = anim.trail_offset(now) {
+                for step in 0..TRAIL_STEPS {
+                    let k = step as f64 / TRAIL_STEPS as f64;
+                    ctx.set_source_rgba(bg.0, bg.1, bg.2, (0.1 + 0.3 * k) * alpha);
+                    ctx.rectangle(
+                        line_x + trail_dx + (dx - trail_dx) * k,
+                        y + trail_dy + (dy - trail_dy) * k,
+                        width,
+                        height,
+                    );
+                    ctx.fill();
+                }
+            }
+        }
+
+        ctx.set_source_rgba(bg.0, bg.1, bg.2, alpha);
+        ctx.rectangle(line_x + dx, y + dy, width, height);
+        if state.anim_phase == AnimPhase::NoFocus {
+            ctx.stroke();
+        } else {
+            ctx.fill();
+        }
+
+        true
+    }
+
     fn is_visible(&self) -> bool {
         let state = self.state.borrow();
 
@@ -314,26 +565,30 @@ pub fn cursor_rect(
 fn anim_step<CB: CursorRedrawCb + 'static>(state: &Arc<UiMutex<State<CB>>>) -> glib::Continue {
     let mut mut_state = state.borrow_mut();
 
+    // fade is counted into blinkon/blinkoff time
+    let (blinkon, blinkoff) = mut_state.blink;
+    let fade = fade_step(blinkon, blinkoff);
+
     let next_event = match mut_state.anim_phase {
         AnimPhase::Shown => {
             if let Some(counter) = &mut mut_state.counter {
                 if counter.count < counter.max {
                     counter.count += 1;
                     mut_state.anim_phase = AnimPhase::Hide;
-                    Some(60)
+                    Some(fade)
                 } else {
                     None
                 }
             } else {
                 mut_state.anim_phase = AnimPhase::Hide;
-                Some(60)
+                Some(fade)
             }
         }
         AnimPhase::Hide => {
             if !mut_state.alpha.hide(0.3) {
                 mut_state.anim_phase = AnimPhase::Hidden;
 
-                Some(300)
+                Some(blinkoff.saturating_sub(FADE_STEPS * fade).max(1))
             } else {
                 None
             }
@@ -341,13 +596,13 @@ fn anim_step<CB: CursorRedrawCb + 'static>(state: &Arc<UiMutex<State<CB>>>) -> g
         AnimPhase::Hidden => {
             mut_state.anim_phase = AnimPhase::Show;
 
-            Some(60)
+            Some(fade)
         }
         AnimPhase::Show => {
             if !mut_state.alpha.show(0.3) {
                 mut_state.anim_phase = AnimPhase::Shown;
 
-                Some(500)
+                Some(blinkon.saturating_sub(FADE_STEPS * fade).max(1))
             } else {
                 None
             }
@@ -370,9 +625,32 @@ fn anim_step<CB: CursorRedrawCb + 'static>(state: &Arc<UiMutex<State<CB>>>) -> g
     }
 }
 
+fn move_step<CB: CursorRedrawCb + 'static>(state: &Arc<UiMutex<State<CB>>>) -> glib::Continue {
+    let mut mut_state = state.borrow_mut();
+
+    let (running, start_offset) = match mut_state.move_anim {
+        Some(ref anim) => (anim.offset(Instant::now()).is_some(), anim.start_offset),
+        None => (false, (0.0, 0.0)),
+    };
+    if !running {
+        mut_state.move_anim = None;
+        mut_state.move_timer = None;
+    }
+
+    if let Some(redraw_cb) = mut_state.redraw_cb.upgrade() {
+        redraw_cb.borrow_mut().queue_redraw_move(start_offset);
+    }
+
+    glib::Continue(running)
+}
+
 impl<CB: CursorRedrawCb> Drop for BlinkCursor<CB> {
     fn drop(&mut self) {
-        if let Some(timer_id) = self.state.borrow_mut().timer.take() {
+        let mut mut_state = self.state.borrow_mut();
+        if let Some(timer_id) = mut_state.timer.take() {
+            glib::source_remove(timer_id);
+        }
+        if let Some(timer_id) = mut_state.move_timer.take() {
             glib::source_remove(timer_id);
         }
     }
@@ -380,6 +658,10 @@ impl<CB: CursorRedrawCb> Drop for BlinkCursor<CB> {
 
 pub trait CursorRedrawCb {
     fn queue_redraw_cursor(&mut self);
+
+    /// Moving cursor can be anywhere between old and new positions,
+    /// `start_offset` is old position relative to new one in pixels
+    fn queue_redraw_move(&mut self, start_offset: (f64, f64));
 }
 
 #[cfg(test)]
@@ -387,6 +669,37 @@ mod tests {
     use super::*;
     use std::collections::HashMap;
 
+    #[test]
+    fn test_fade_step() {
+        assert_eq!(60, fade_step(700, 500));
+        assert_eq!(31, fade_step(400, 250));
+        assert_eq!(1, fade_step(1, 1));
+
+        // fade in and out fits into blink time
+        let fade = fade_step(100, 250);
+        assert!(FADE_STEPS * fade <= 100);
+    }
+
+    #[test]
+    fn test_move_anim() {
+        let anim = MoveAnim {
+            start_offset: (100.0, -20.0),
+            start: Instant::now(),
+            duration: Duration::from_millis(100),
+        };
+
+        assert_eq!((100.0, -20.0), anim.offset_at(0.0));
+        assert_eq!((50.0, -10.0), anim.offset_at(0.5));
+        assert_eq!((0.0, 0.0), anim.offset_at(1.0));
+
+        assert_eq!(None, anim.offset(anim.start + Duration::from_millis(100)));
+        assert_eq!(Some((100.0, -20.0)), anim.offset(anim.start));
+
+        // trail lags behind cursor head
+        let now = anim.start + Duration::from_millis(50);
+        assert!(anim.trail_offset(now).unwrap().0 > anim.offset(now).unwrap().0);
+    }
+
     #[test]
     fn test_cursor_rect_horizontal() {
         let mut mode_data = HashMap::new();
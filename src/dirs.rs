@@ -31,3 +31,31 @@ fn get_xdg_config_dir() -> Result<PathBuf, String> {
     home_dir.push(".config");
     Ok(home_dir)
 }
+
+/// Directory for sockets, it is accessible only by user
+pub fn get_app_runtime_dir_create() -> Result<PathBuf, String> {
+    let runtime_dir = match env_dirs::runtime_dir() {
+        Some(runtime_dir) => runtime_dir.join("nvim-gtk"),
+        None => std::env::temp_dir().join(format!("nvim-gtk-{}", whoami::username())),
+    };
+
+    create_private_dir(&runtime_dir)?;
+
+    Ok(runtime_dir)
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &PathBuf) -> Result<(), String> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|e| format!("{}", e))
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &PathBuf) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}", e))
+}
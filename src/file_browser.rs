@@ -1,8 +1,7 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
 use std::io;
 use std::fs;
-use std::fs::DirEntry;
 use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
 use std::ops::Deref;
@@ -12,11 +11,11 @@ use gio::prelude::*;
 use gtk;
 use gtk::prelude::*;
 
-use neovim_lib::{NeovimApi, NeovimApiAsync};
+use neovim_lib::{CallError, NeovimApiAsync, Value};
 use htmlescape::encode_minimal;
 
 use crate::misc::{escape_filename, substitute_home_for_tilde};
-use crate::nvim::{ErrorReport, NeovimClient, NeovimRef};
+use crate::nvim::{on_ui_thread, ErrorReport, NeovimClient, NeovimRef};
 use crate::shell;
 use crate::subscriptions::SubscriptionKey;
 
@@ -38,6 +37,9 @@ struct State {
     current_dir: String,
     show_hidden: bool,
     selected_path: Option<String>,
+    dir_source: DirSource,
+    // changed on every tree reload, so late answers of previous tree are dropped
+    tree_version: Rc<Cell<u32>>,
 }
 
 pub struct FileBrowserWidget {
@@ -57,7 +59,7 @@ impl Deref for FileBrowserWidget {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 enum FileType {
     File,
     Dir,
@@ -104,6 +106,8 @@ impl FileBrowserWidget {
                 current_dir: "".to_owned(),
                 show_hidden: false,
                 selected_path: None,
+                dir_source: DirSource::Local,
+                tree_version: Rc::new(Cell::new(0)),
             })),
         };
         file_browser
@@ -113,30 +117,59 @@ impl FileBrowserWidget {
         self.nvim.as_ref().unwrap().nvim()
     }
 
+    /// Show new working directory of nvim as tree root
+    pub fn set_current_dir(&self, dir: &str) {
+        if self.nvim.is_none() {
+            // not initialized yet, directory is requested on init
+            return;
+        }
+
+        update_current_dir(
+            dir,
+            &self.store,
+            &self.state,
+            &self.comps.dir_list_model,
+            &self.comps.dir_list,
+        );
+        build_buf_list(&self.comps.buf_list, self.nvim.as_ref().unwrap());
+    }
+
     pub fn init(&mut self, shell_state: &shell::State) {
         // Initialize values.
         let nvim = shell_state.nvim_clone();
-        self.nvim = Some(nvim);
-        if let Some(dir) = get_current_dir(&mut self.nvim().unwrap()) {
-            update_dir_list(&dir, &self.comps.dir_list_model, &self.comps.dir_list);
-            self.state.borrow_mut().current_dir = dir;
+        if shell_state.remote_host().is_some() {
+            self.state.borrow_mut().dir_source = DirSource::Nvim(nvim.clone());
         }
+        self.nvim = Some(nvim);
 
         // Populate tree.
-        tree_reload(&self.store, &self.state.borrow());
-
         let store = &self.store;
         let state_ref = &self.state;
+        let dir_list_model = &self.comps.dir_list_model;
+        let dir_list = &self.comps.dir_list;
+        if let Some(mut nvim) = self.nvim() {
+            let (store, state_ref) = (store.clone(), state_ref.clone());
+            let (dir_list_model, dir_list) = (dir_list_model.clone(), dir_list.clone());
+            nvim.eval_async("getcwd()")
+                .cb(on_ui_thread(move |res: Result<Value, CallError>| {
+                    let dir = res.ok_and_report();
+                    if let Some(dir) = dir.as_ref().and_then(Value::as_str) {
+                        update_current_dir(dir, &store, &state_ref, &dir_list_model, &dir_list);
+                    }
+                }))
+                .call();
+        }
+
         self.tree.connect_test_expand_row(clone!(store, state_ref => move |_, iter, _| {
             store.set(&iter, &[Column::IconName as u32], &[&ICON_FOLDER_OPEN]);
             // We cannot recursively populate all directories. Instead, we have prepared a single
             // empty child entry for all non-empty directories, so the row will be expandable. Now,
-            // when a directory is expanded, populate its children.
+            // when a directory is expanded, populate its children. The empty entry is removed
+            // when children are added, so the row stays expanded while they are read.
             let state = state_ref.borrow();
             if let Some(child) = store.iter_children(Some(iter)) {
                 let filename = store.get_value(&child, Column::Filename as i32);
                 if filename.get::<&str>().is_none() {
-                    store.remove(&child);
                     let dir_value = store.get_value(&iter, Column::Path as i32);
                     if let Some(dir) = dir_value.get() {
                         populate_tree_nodes(&store, &state, dir, Some(iter));
@@ -173,24 +206,31 @@ impl FileBrowserWidget {
             let mut nvim = nvim_ref.nvim().unwrap();
             if col.get_title().unwrap().as_str() == "close" {
                 if ! (buf_list.get_value(&buf_list.get_iter(path).unwrap(), 3).get::<&str>().unwrap() == "edit-delete-symbolic") {
-                    nvim.session.call("nvim_buf_delete", vec![neovim_lib::Value::from(buf_num), neovim_lib::Value::Map(vec![])]).unwrap();
+                    nvim.session
+                        .call_async::<Value>("nvim_buf_delete", vec![Value::from(buf_num), Value::Map(vec![])])
+                        .cb(|r| r.report_err())
+                        .call();
                 }
             } else {
-               if let Err(_) = nvim.set_current_buf(&neovim_lib::neovim_api::Buffer::new(neovim_lib::Value::Integer(neovim_lib::Integer::from(buf_num)))) {
-                    if let Ok(new_buf) = nvim.get_current_buf() {
-                        if let Ok(new_buf_num) = new_buf.get_number(&mut nvim) {
-                           let mut tree_path = gtk::TreePath::new();
-                            tree_path.down();
-                            while let Some(iter) = buf_list.get_iter(&tree_path) {
-                                if new_buf_num as u32 == buf_list.get_value(&iter, 1).get::<u32>().unwrap() {
-                                    buf_tree.set_cursor(&tree_path, Option::<&gtk::TreeViewColumn>::None, false);
-                                    break;
-                                }
-                                tree_path.next();
-                            }
+                let (nvim_ref, buf_list, buf_tree) = (nvim_ref.clone(), buf_list.clone(), buf_tree.clone());
+                nvim.session
+                    .call_async::<Value>("nvim_set_current_buf", vec![Value::from(buf_num)])
+                    .cb(on_ui_thread(move |res: Result<Value, CallError>| {
+                        if res.is_ok() {
+                            return;
                         }
-                    }
-                }
+                        // buffer is not changed, select current buffer again
+                        if let Some(mut nvim) = nvim_ref.nvim() {
+                            nvim.eval_async("bufnr('%')")
+                                .cb(on_ui_thread(move |res: Result<Value, CallError>| {
+                                    if let Some(num) = res.ok_and_report().and_then(|num| num.as_u64()) {
+                                        update_buf_list_selected(&buf_list, &buf_tree, num as u32);
+                                    }
+                                }))
+                                .call();
+                        }
+                    }))
+                    .call();
             }
         }));
 
@@ -230,25 +270,10 @@ impl FileBrowserWidget {
     }
 
     fn init_subscriptions(&self, shell_state: &shell::State) {
-        // Always set the current working directory as the root of the file browser.
-        let store = &self.store;
-        let state_ref = &self.state;
-        let dir_list_model = &self.comps.dir_list_model;
-        let dir_list = &self.comps.dir_list;
-        shell_state.subscribe(
-            SubscriptionKey::from("DirChanged"),
-            &["getcwd()"],
-            clone!(store, state_ref, dir_list_model, dir_list => move |args| {
-                let dir = args.into_iter().next().unwrap();
-                if dir != state_ref.borrow().current_dir {
-                    state_ref.borrow_mut().current_dir = dir.to_owned();
-                    update_dir_list(&dir, &dir_list_model, &dir_list);
-                    tree_reload(&store, &state_ref.borrow());
-                }
-            }),
-        );
+        // Current working directory is set by chdir ui event or DirChanged, see set_current_dir
 
         // Reveal the file of an entered buffer in the file browser and select the entry.
+        let store = &self.store;
         let tree = &self.tree;
         let subscription = shell_state.subscribe(
             SubscriptionKey::from("BufEnter"),
@@ -273,16 +298,16 @@ impl FileBrowserWidget {
         let buf_list = &self.comps.buf_list;
         let nvim_ref = self.nvim.as_ref().unwrap();
         shell_state.subscribe(SubscriptionKey::from("BufAdd"), &["expand('<abuf>')", "expand('<afile>')"], clone!(buf_list, nvim_ref => move |args| {
-            let mut nvim = nvim_ref.nvim().unwrap();
             let mut args = args.into_iter();
             if let Some(buf_num) = args.next() {
                 if let Ok(num) = buf_num.parse::<u32>() {
                     if let Some(name) = args.next() {
-                        if let Ok(listed) = neovim_lib::neovim_api::Buffer::new(neovim_lib::Value::Integer(neovim_lib::Integer::from(num))).get_option(&mut nvim, "buflisted") {
+                        let buf_list = buf_list.clone();
+                        get_buf_option_async(&nvim_ref, num, "buflisted", move |listed| {
                             if listed.as_bool().unwrap_or(false) {
                                 update_buf_list_added(&buf_list, num, &name);
                             }
-                        }
+                        });
                     }
                 }
             }
@@ -313,22 +338,17 @@ impl FileBrowserWidget {
         let buf_list = &self.comps.buf_list;
         let nvim_ref = self.nvim.as_ref().unwrap();
         shell_state.subscribe(SubscriptionKey::from("BufModifiedSet"), &["expand('<abuf>')"], clone!(buf_list, nvim_ref => move |args| {
-            let mut nvim = nvim_ref.nvim().unwrap();
             let mut args = args.into_iter();
             if let Some(buf_num) = args.next() {
                 if let Ok(num) = buf_num.parse::<u32>() {
-                        let is_modified = if let Ok(neovim_lib::Value::Boolean(is_modified)) = neovim_lib::neovim_api::Buffer::new(neovim_lib::Value::Integer(neovim_lib::Integer::from(num))).get_option(&mut nvim, "modified") {is_modified} else {false};
-                        update_buf_list_modified(&buf_list, num, is_modified);
+                    let buf_list = buf_list.clone();
+                    get_buf_option_async(&nvim_ref, num, "modified", move |is_modified| {
+                        update_buf_list_modified(&buf_list, num, is_modified.as_bool().unwrap_or(false));
+                    });
                 }
             }
         }));
 
-        let buf_list = &self.comps.buf_list;
-        let nvim_ref = self.nvim.as_ref().unwrap();
-        shell_state.subscribe(SubscriptionKey::from("DirChanged"), &[], clone!(buf_list, nvim_ref => move |_| {
-            build_buf_list(&buf_list, &mut nvim_ref.nvim().unwrap());
-        }));
-
         let buf_tree = &self.comps.buf_tree_view;
         let buf_list = &self.comps.buf_list;
         shell_state.subscribe(SubscriptionKey::from("BufEnter,BufDelete,BufAdd,BufDelete,BufFilePost"), &["bufnr('%')"], clone!(buf_tree, buf_list => move |args| {
@@ -459,27 +479,18 @@ impl FileBrowserWidget {
     }
 
     pub fn update_buf_list(&self) {
-        build_buf_list(&self.comps.buf_list, &mut self.nvim.as_ref().unwrap().nvim().unwrap());
+        build_buf_list(&self.comps.buf_list, self.nvim.as_ref().unwrap());
     }
 }
 
 /// Compare function for dir entries.
 ///
 /// Sorts directories above files.
-fn cmp_dirs_first(lhs: &DirEntry, rhs: &DirEntry) -> io::Result<Ordering> {
-    let lhs_metadata = fs::metadata(lhs.path())?;
-    let rhs_metadata = fs::metadata(rhs.path())?;
-    if lhs_metadata.is_dir() == rhs_metadata.is_dir() {
-        Ok(lhs.path()
-            .to_string_lossy()
-            .to_lowercase()
-            .cmp(&rhs.path().to_string_lossy().to_lowercase()))
-    } else {
-        if lhs_metadata.is_dir() {
-            Ok(Ordering::Less)
-        } else {
-            Ok(Ordering::Greater)
-        }
+fn cmp_dirs_first(lhs: &DirItem, rhs: &DirItem) -> Ordering {
+    match (lhs.file_type, rhs.file_type) {
+        (FileType::Dir, FileType::File) => Ordering::Less,
+        (FileType::File, FileType::Dir) => Ordering::Greater,
+        _ => lhs.path.to_lowercase().cmp(&rhs.path.to_lowercase()),
     }
 }
 
@@ -487,9 +498,25 @@ fn cmp_dirs_first(lhs: &DirEntry, rhs: &DirEntry) -> io::Result<Ordering> {
 fn tree_reload(store: &gtk::TreeStore, state: &State) {
     let dir = &state.current_dir;
     store.clear();
+    state.tree_version.set(state.tree_version.get().wrapping_add(1));
     populate_tree_nodes(store, state, dir, None);
 }
 
+/// Show new working directory as tree root
+fn update_current_dir(
+    dir: &str,
+    store: &gtk::TreeStore,
+    state: &RefCell<State>,
+    dir_list_model: &gtk::TreeStore,
+    dir_list: &gtk::ComboBox,
+) {
+    if dir != state.borrow().current_dir {
+        state.borrow_mut().current_dir = dir.to_owned();
+        update_dir_list(dir, dir_list_model, dir_list);
+        tree_reload(store, &state.borrow());
+    }
+}
+
 /// Updates the dirctory list on top of the file browser.
 ///
 /// The list represents the path the the current working directory.  If the new cwd is a parent of
@@ -574,54 +601,84 @@ fn update_dir_list(dir: &str, dir_list_model: &gtk::TreeStore, dir_list: &gtk::C
 }
 
 /// Populates one level, i.e. one directory of the file browser tree.
+///
+/// Directory of nvim is read asynchronously, so items are added
+/// only if the tree still has the parent row when they are ready.
 fn populate_tree_nodes(
     store: &gtk::TreeStore,
     state: &State,
     dir: &str,
     parent: Option<&gtk::TreeIter>,
 ) {
-    let path = Path::new(dir);
-    let read_dir = match path.read_dir() {
-        Ok(read_dir) => read_dir,
-        Err(err) => {
-            error!("Couldn't populate tree: {}", err);
+    let parent_row = match parent {
+        Some(iter) => match store
+            .get_path(iter)
+            .and_then(|path| gtk::TreeRowReference::new(store, &path))
+        {
+            Some(row) => Some(row),
+            None => return,
+        },
+        None => None,
+    };
+    let store = store.clone();
+    let show_hidden = state.show_hidden;
+    let tree_version = state.tree_version.clone();
+    let version = tree_version.get();
+
+    state.dir_source.read_dir(dir, move |items| {
+        if tree_version.get() != version {
             return;
         }
-    };
-    let iter = read_dir.filter_map(Result::ok);
-    let mut entries: Vec<DirEntry> = if state.show_hidden {
-        iter.collect()
+        let parent = match parent_row {
+            Some(row) => match row.get_path().and_then(|path| store.get_iter(&path)) {
+                Some(iter) => Some(iter),
+                None => return,
+            },
+            None => None,
+        };
+
+        let items = items.unwrap_or_else(|err| {
+            error!("Couldn't populate tree: {}", err);
+            vec![]
+        });
+        append_tree_nodes(&store, items, show_hidden, parent.as_ref());
+    });
+}
+
+/// Append directory items to tree, empty entry of expandable parent is replaced
+fn append_tree_nodes(
+    store: &gtk::TreeStore,
+    items: Vec<DirItem>,
+    show_hidden: bool,
+    parent: Option<&gtk::TreeIter>,
+) {
+    let placeholder = store.iter_children(parent).filter(|child| {
+        parent.is_some()
+            && store
+                .get_value(child, Column::Filename as i32)
+                .get::<&str>()
+                .is_none()
+    });
+    // row expanded again before answer, so it is already populated
+    if parent.is_some() && placeholder.is_none() {
+        return;
+    }
+
+    let mut items: Vec<DirItem> = if show_hidden {
+        items
     } else {
-        iter.filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
-            .filter(|entry| !entry.file_name().to_string_lossy().ends_with('~'))
+        items
+            .into_iter()
+            .filter(|item| !item.name.starts_with('.'))
+            .filter(|item| !item.name.ends_with('~'))
             .collect()
     };
-    entries.sort_unstable_by(|lhs, rhs| cmp_dirs_first(lhs, rhs).unwrap_or(Ordering::Equal));
-    for entry in entries {
-        let path = if let Some(path) = entry.path().to_str() {
-            path.to_owned()
-        } else {
-            // Skip paths that contain invalid unicode.
-            continue;
-        };
-        let filename = entry.file_name().to_str().unwrap().to_owned();
-        let file_type = if let Ok(metadata) = fs::metadata(entry.path()) {
-            let file_type = metadata.file_type();
-            if file_type.is_dir() {
-                FileType::Dir
-            } else if file_type.is_file() {
-                FileType::File
-            } else {
-                continue;
-            }
-        } else {
-            // In case of invalid symlinks, we cannot obtain metadata.
-            continue;
-        };
-        let icon = match file_type {
+    items.sort_unstable_by(cmp_dirs_first);
+    for item in items {
+        let icon = match item.file_type {
             FileType::Dir => ICON_FOLDER_CLOSED.to_string(),
             FileType::File => {
-                let file_name = path.split("/").last().unwrap().trim();
+                let file_name = item.name.trim();
                 get_icon(vec![file_name, file_name.split(".").last().unwrap()])
             },
         };
@@ -630,35 +687,185 @@ fn populate_tree_nodes(
         store.set(
             &iter,
             &[0, 1, 2, 3],
-            &[&filename, &path, &(file_type as u8), &icon],
+            &[&item.name, &item.path, &(item.file_type as u8), &icon],
         );
         // For directories, check whether the directory is empty. If not, append a single empty
         // entry, so the expand arrow is shown. Its contents are dynamically populated when
         // expanded (see `init`).
-        if let FileType::Dir = file_type {
-            let not_empty = if let Ok(mut dir) = entry.path().read_dir() {
-                dir.next().is_some()
-            } else {
-                false
-            };
-            if not_empty {
-                let iter = store.append(Some(&iter));
-                store.set(&iter, &[], &[]);
-            }
+        if item.not_empty {
+            let iter = store.append(Some(&iter));
+            store.set(&iter, &[], &[]);
         }
     }
+
+    if let Some(placeholder) = placeholder {
+        store.remove(&placeholder);
+    }
 }
 
-fn get_current_dir(nvim: &mut NeovimRef) -> Option<String> {
-    match nvim.eval("getcwd()") {
-        Ok(cwd) => cwd.as_str().map(|s| s.to_owned()),
-        Err(err) => {
-            error!("Couldn't get cwd: {}", err);
-            None
+/// Shown entry of directory
+#[derive(Debug, PartialEq)]
+struct DirItem {
+    name: String,
+    path: String,
+    file_type: FileType,
+    // directory has entries, so it can be expanded
+    not_empty: bool,
+}
+
+/// Where directories are listed from
+enum DirSource {
+    Local,
+    // nvim on other host, directories are listed by luv in nvim
+    Nvim(Rc<NeovimClient>),
+}
+
+// returns list of [name, type, not_empty], links are resolved
+const READ_DIR_LUA: &str = r#"
+local dir = ...
+local uv = vim.loop
+local items = {}
+local handle = uv.fs_scandir(dir)
+if not handle then
+  return items
+end
+while true do
+  local name, type = uv.fs_scandir_next(handle)
+  if not name then
+    break
+  end
+  local path = dir:gsub('/$', '') .. '/' .. name
+  if type == 'link' then
+    local stat = uv.fs_stat(path)
+    type = stat and stat.type or ''
+  end
+  local not_empty = false
+  if type == 'directory' then
+    local sub = uv.fs_scandir(path)
+    not_empty = sub ~= nil and uv.fs_scandir_next(sub) ~= nil
+  end
+  table.insert(items, {name, type, not_empty})
+end
+return items
+"#;
+
+impl DirSource {
+    /// Read directory items, `cb` is called on ui thread
+    fn read_dir<F>(&self, dir: &str, cb: F)
+    where
+        F: FnOnce(Result<Vec<DirItem>, String>) + 'static,
+    {
+        match *self {
+            DirSource::Local => cb(read_local_dir(dir).map_err(|e| format!("{}", e))),
+            DirSource::Nvim(ref nvim) => {
+                let mut nvim = match nvim.nvim() {
+                    Some(nvim) => nvim,
+                    None => return cb(Err("Nvim not initialized".to_owned())),
+                };
+                let dir = dir.to_owned();
+                nvim.session
+                    .call_async::<Value>(
+                        "nvim_exec_lua",
+                        vec![
+                            Value::from(READ_DIR_LUA),
+                            Value::Array(vec![Value::from(dir.as_str())]),
+                        ],
+                    )
+                    .cb(on_ui_thread(move |res: Result<Value, CallError>| {
+                        cb(res
+                            .map(|items| parse_dir_items(&dir, &items))
+                            .map_err(|e| e.to_string()))
+                    }))
+                    .call();
+            }
         }
     }
 }
 
+fn read_local_dir(dir: &str) -> io::Result<Vec<DirItem>> {
+    let items = Path::new(dir)
+        .read_dir()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            // Skip paths that contain invalid unicode.
+            let path = entry.path().to_str()?.to_owned();
+            let name = entry.file_name().to_str()?.to_owned();
+            // In case of invalid symlinks, we cannot obtain metadata.
+            let file_type = fs::metadata(entry.path()).ok()?.file_type();
+            let (file_type, not_empty) = if file_type.is_dir() {
+                let not_empty = entry
+                    .path()
+                    .read_dir()
+                    .map(|mut dir| dir.next().is_some())
+                    .unwrap_or(false);
+                (FileType::Dir, not_empty)
+            } else if file_type.is_file() {
+                (FileType::File, false)
+            } else {
+                return None;
+            };
+
+            Some(DirItem {
+                name,
+                path,
+                file_type,
+                not_empty,
+            })
+        })
+        .collect();
+
+    Ok(items)
+}
+
+/// Items returned by `READ_DIR_LUA`, only files and directories are kept
+fn parse_dir_items(dir: &str, items: &Value) -> Vec<DirItem> {
+    let items = match items.as_array() {
+        Some(items) => items,
+        None => return vec![],
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let item = item.as_array()?;
+            let name = item.first()?.as_str()?;
+            let file_type = match item.get(1)?.as_str()? {
+                "directory" => FileType::Dir,
+                "file" => FileType::File,
+                _ => return None,
+            };
+            let not_empty = item.get(2).and_then(Value::as_bool).unwrap_or(false);
+
+            Some(DirItem {
+                name: name.to_owned(),
+                path: format!("{}/{}", dir.trim_end_matches('/'), name),
+                file_type,
+                not_empty,
+            })
+        })
+        .collect()
+}
+
+/// Get buffer option without blocking ui, `cb` is called on ui thread
+fn get_buf_option_async<F>(nvim: &NeovimClient, buf_num: u32, name: &str, cb: F)
+where
+    F: FnOnce(Value) + 'static,
+{
+    if let Some(mut nvim) = nvim.nvim() {
+        nvim.session
+            .call_async::<Value>(
+                "nvim_buf_get_option",
+                vec![Value::from(buf_num), Value::from(name)],
+            )
+            .cb(on_ui_thread(move |res: Result<Value, CallError>| {
+                if let Some(value) = res.ok_and_report() {
+                    cb(value);
+                }
+            }))
+            .call();
+    }
+}
+
 /// Reveals and selects the given file in the file browser.
 ///
 /// Returns `true` if the file could be successfully revealed.
@@ -688,18 +895,57 @@ fn reveal_path_in_tree(store: &gtk::TreeStore, tree: &gtk::TreeView, rel_file_pa
     true
 }
 
-fn build_buf_list(buf_list: &gtk::TreeStore, nvim: &mut NeovimRef) {
-    let buffers = nvim.list_bufs().unwrap();
+// listed buffers as [bufnr, name, changed]
+const BUF_LIST: &str =
+    "map(getbufinfo({'buflisted': 1}), '[v:val.bufnr, bufname(v:val.bufnr), v:val.changed]')";
+
+/// Listed buffer shown in buffer list
+#[derive(Debug, PartialEq)]
+struct BufItem {
+    num: u32,
+    name: String,
+    modified: bool,
+}
+
+/// Items of `BUF_LIST` result
+fn parse_buf_list(buffers: &Value) -> Vec<BufItem> {
+    let buffers = match buffers.as_array() {
+        Some(buffers) => buffers,
+        None => return vec![],
+    };
+
+    buffers
+        .iter()
+        .filter_map(|buf| {
+            let buf = buf.as_array()?;
+            Some(BufItem {
+                num: buf.first()?.as_u64()? as u32,
+                name: buf.get(1)?.as_str()?.to_owned(),
+                modified: buf.get(2).and_then(Value::as_u64).unwrap_or(0) != 0,
+            })
+        })
+        .collect()
+}
+
+/// Request buffers without blocking ui and fill buffer list
+fn build_buf_list(buf_list: &gtk::TreeStore, nvim: &NeovimClient) {
+    if let Some(mut nvim) = nvim.nvim() {
+        let buf_list = buf_list.clone();
+        nvim.eval_async(BUF_LIST)
+            .cb(on_ui_thread(move |res: Result<Value, CallError>| {
+                if let Some(buffers) = res.ok_and_report() {
+                    fill_buf_list(&buf_list, &parse_buf_list(&buffers));
+                }
+            }))
+            .call();
+    }
+}
+
+fn fill_buf_list(buf_list: &gtk::TreeStore, buffers: &[BufItem]) {
     buf_list.clear();
     for buf in buffers {
-        let buf_id = buf.get_number(nvim).unwrap();
-        let name = nvim.eval(&format!("buffer_name({})", buf_id)).unwrap_or(neovim_lib::Value::from(""));
-        let name = name.as_str().unwrap_or("");
-        if let Ok(neovim_lib::Value::Boolean(is_listed)) = buf.get_option(nvim, "buflisted") {
-            if ! is_listed {
-                continue;
-            }
-        }
+        let buf_id = buf.num;
+        let name = buf.name.as_str();
         let iter = buf_list.append(None);
         let file_name: String;
         let icon: String;
@@ -710,7 +956,7 @@ fn build_buf_list(buf_list: &gtk::TreeStore, nvim: &mut NeovimRef) {
             file_name = name.split("/").last().unwrap().to_string();
             icon = get_icon(vec![&name[..], name.split(".").last().unwrap()]);
         }
-        let is_modified = if let Ok(neovim_lib::Value::Boolean(is_modified)) = buf.get_option(nvim, "modified") {is_modified} else {false};
+        let is_modified = buf.modified;
 
         let close_icon_name = if is_modified {"edit-delete-symbolic"} else {"window-close-symbolic"};
 
@@ -816,3 +1062,71 @@ pub fn get_icon(names: Vec<&str>) -> String {
     }
     return ICON_FILE.to_string();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dir_items() {
+        let item = |name: &str, file_type: &str, not_empty| {
+            Value::Array(vec![
+                Value::from(name),
+                Value::from(file_type),
+                Value::from(not_empty),
+            ])
+        };
+        let items = Value::Array(vec![
+            item("src", "directory", true),
+            item("Cargo.toml", "file", false),
+            item("nvim.sock", "socket", false),
+        ]);
+
+        let mut items = parse_dir_items("/home/user/project/", &items);
+        items.sort_unstable_by(cmp_dirs_first);
+
+        assert_eq!(
+            vec![
+                DirItem {
+                    name: "src".to_owned(),
+                    path: "/home/user/project/src".to_owned(),
+                    file_type: FileType::Dir,
+                    not_empty: true,
+                },
+                DirItem {
+                    name: "Cargo.toml".to_owned(),
+                    path: "/home/user/project/Cargo.toml".to_owned(),
+                    file_type: FileType::File,
+                    not_empty: false,
+                },
+            ],
+            items
+        );
+    }
+
+    #[test]
+    fn test_parse_buf_list() {
+        let buffers = Value::Array(vec![
+            Value::Array(vec![Value::from(1), Value::from("src/main.rs"), Value::from(0)]),
+            Value::Array(vec![Value::from(3), Value::from(""), Value::from(1)]),
+            Value::from("broken"),
+        ]);
+
+        assert_eq!(
+            vec![
+                BufItem {
+                    num: 1,
+                    name: "src/main.rs".to_owned(),
+                    modified: false,
+                },
+                BufItem {
+                    num: 3,
+                    name: "".to_owned(),
+                    modified: true,
+                },
+            ],
+            parse_buf_list(&buffers)
+        );
+        assert!(parse_buf_list(&Value::Nil).is_empty());
+    }
+}
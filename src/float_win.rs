@@ -0,0 +1,174 @@
+use std::ops::Deref;
+use std::rc::Rc;
+
+use fnv::FnvHashMap;
+use gtk::prelude::*;
+
+// space around float frame, reserved for shadow
+const FLOAT_MARGIN: i32 = 8;
+const FLOAT_BORDER: i32 = 1;
+pub const FLOAT_RADIUS: f64 = 4.0;
+
+const FLOAT_CSS: &str = ".nvim-float {
+    margin: 8px;
+    border: 1px solid alpha(black, 0.25);
+    border-radius: 4px;
+    box-shadow: 0 2px 6px 1px alpha(black, 0.35);
+}
+.nvim-float.left-edge {
+    margin-left: 0;
+    border-left-width: 0;
+}
+.nvim-float.top-edge {
+    margin-top: 0;
+    border-top-width: 0;
+}";
+
+type DrawCb = dyn Fn(u64, &cairo::Context);
+
+struct FloatWindow {
+    frame: gtk::Box,
+    drawing_area: gtk::DrawingArea,
+}
+
+impl FloatWindow {
+    fn new(grid: u64, css_provider: &gtk::CssProvider, draw_cb: Rc<DrawCb>) -> Self {
+        let frame = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        frame.set_halign(gtk::Align::Start);
+        frame.set_valign(gtk::Align::Start);
+
+        let style_context = frame.get_style_context();
+        style_context.add_class("nvim-float");
+        style_context.add_provider(css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+
+        let drawing_area = gtk::DrawingArea::new();
+        drawing_area.connect_draw(move |_, ctx| {
+            draw_cb(grid, ctx);
+            Inhibit(false)
+        });
+
+        frame.pack_start(&drawing_area, true, true, 0);
+
+        FloatWindow {
+            frame,
+            drawing_area,
+        }
+    }
+
+    fn set_area(&self, x: i32, y: i32, width: i32, height: i32) {
+        let style_context = self.frame.get_style_context();
+        self.frame.set_margin_start(frame_offset(&style_context, "left-edge", x));
+        self.frame.set_margin_top(frame_offset(&style_context, "top-edge", y));
+        self.drawing_area.set_size_request(width, height);
+    }
+}
+
+/// Frame offset that places float content at `pos`
+///
+/// Near drawing area edge there is no space for margin and border,
+/// frame is drawn without them on that side, so cells stay aligned.
+fn frame_offset(style_context: &gtk::StyleContext, edge_class: &str, pos: i32) -> i32 {
+    let offset = pos - FLOAT_MARGIN - FLOAT_BORDER;
+    if offset < 0 {
+        style_context.add_class(edge_class);
+        pos
+    } else {
+        style_context.remove_class(edge_class);
+        offset
+    }
+}
+
+/// Neovim floating windows, hosted as frames over drawing area
+///
+/// Floats get theme border, rounded corners and shadow
+/// instead of painting to the main drawing area.
+pub struct FloatWindows {
+    overlay: gtk::Overlay,
+    css_provider: gtk::CssProvider,
+    windows: FnvHashMap<u64, FloatWindow>,
+    draw_cb: Option<Rc<DrawCb>>,
+}
+
+impl FloatWindows {
+    pub fn new(drawing_area: &gtk::DrawingArea) -> Self {
+        let overlay = gtk::Overlay::new();
+        overlay.add(drawing_area);
+
+        let css_provider = gtk::CssProvider::new();
+        if let Err(e) = gtk::CssProviderExt::load_from_data(&css_provider, FLOAT_CSS.as_bytes()) {
+            error!("Can't load float css {}", e);
+        }
+
+        FloatWindows {
+            overlay,
+            css_provider,
+            windows: FnvHashMap::default(),
+            draw_cb: None,
+        }
+    }
+
+    pub fn set_draw_cb<F>(&mut self, cb: F)
+    where
+        F: Fn(u64, &cairo::Context) + 'static,
+    {
+        self.draw_cb = Some(Rc::new(cb));
+    }
+
+    pub fn contains(&self, grid: u64) -> bool {
+        self.windows.contains_key(&grid)
+    }
+
+    /// Show float or move it to new position, area is in drawing area pixels
+    pub fn show(&mut self, grid: u64, x: i32, y: i32, width: i32, height: i32) {
+        if !self.windows.contains_key(&grid) {
+            let draw_cb = match self.draw_cb {
+                Some(ref draw_cb) => draw_cb.clone(),
+                None => return,
+            };
+
+            let window = FloatWindow::new(grid, &self.css_provider, draw_cb);
+            self.overlay.add_overlay(&window.frame);
+            // mouse events must go to the main drawing area
+            // as nvim expects coordinates in default grid
+            self.overlay.set_overlay_pass_through(&window.frame, true);
+            self.windows.insert(grid, window);
+        }
+
+        let window = &self.windows[&grid];
+        window.set_area(x, y, width, height);
+        window.frame.show_all();
+        window.drawing_area.queue_draw();
+    }
+
+    pub fn hide(&self, grid: u64) {
+        if let Some(window) = self.windows.get(&grid) {
+            window.frame.hide();
+        }
+    }
+
+    pub fn destroy(&mut self, grid: u64) {
+        if let Some(window) = self.windows.remove(&grid) {
+            self.overlay.remove(&window.frame);
+        }
+    }
+
+    pub fn queue_draw(&self, grid: u64) {
+        if let Some(window) = self.windows.get(&grid) {
+            window.drawing_area.queue_draw();
+        }
+    }
+
+    pub fn queue_draw_all(&self) {
+        for window in self.windows.values() {
+            window.drawing_area.queue_draw();
+        }
+    }
+}
+
+impl Deref for FloatWindows {
+    type Target = gtk::Overlay;
+
+    fn deref(&self) -> &gtk::Overlay {
+        &self.overlay
+    }
+}
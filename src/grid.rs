@@ -8,10 +8,14 @@ use neovim_lib::Value;
 use crate::highlight::{Highlight, HighlightMap};
 use crate::ui_model::{ModelRect, ModelRectVec, UiModel};
 
-const DEFAULT_GRID: u64 = 1;
+pub const DEFAULT_GRID: u64 = 1;
 
 pub struct GridMap {
     grids: FnvHashMap<u64, Grid>,
+    cursor_grid: u64,
+    // incremented every time grid position changes,
+    // so last positioned float is painted on top
+    position_seq: u64,
 }
 
 impl Index<u64> for GridMap {
@@ -32,27 +36,53 @@ impl GridMap {
     pub fn new() -> Self {
         GridMap {
             grids: FnvHashMap::default(),
+            cursor_grid: DEFAULT_GRID,
+            position_seq: 0,
         }
     }
 
+    /// Grid that currently holds cursor
     pub fn current(&self) -> Option<&Grid> {
-        self.grids.get(&DEFAULT_GRID)
+        self.grids
+            .get(&self.cursor_grid)
+            .or_else(|| self.grids.get(&DEFAULT_GRID))
     }
 
-    pub fn current_model_mut(&mut self) -> Option<&mut UiModel> {
-        self.grids.get_mut(&DEFAULT_GRID).map(|g| &mut g.model)
+    pub fn current_grid_id(&self) -> u64 {
+        if self.grids.contains_key(&self.cursor_grid) {
+            self.cursor_grid
+        } else {
+            DEFAULT_GRID
+        }
+    }
+
+    pub fn set_current_grid(&mut self, idx: u64) {
+        self.cursor_grid = idx;
     }
 
-    pub fn current_model(&self) -> Option<&UiModel> {
+    /// Model of default grid, it covers whole drawing area
+    pub fn default_model(&self) -> Option<&UiModel> {
         self.grids.get(&DEFAULT_GRID).map(|g| &g.model)
     }
 
+    pub fn get(&self, idx: u64) -> Option<&Grid> {
+        self.grids.get(&idx)
+    }
+
+    pub fn get_mut(&mut self, idx: u64) -> Option<&mut Grid> {
+        self.grids.get_mut(&idx)
+    }
+
     pub fn get_or_create(&mut self, idx: u64) -> &mut Grid {
         if self.grids.contains_key(&idx) {
             return self.grids.get_mut(&idx).unwrap();
         }
 
-        self.grids.insert(idx, Grid::new());
+        let mut grid = Grid::new();
+        // default grid is always visible, other grids
+        // became visible on win_pos/win_float_pos/msg_set_pos
+        grid.visible = idx == DEFAULT_GRID;
+        self.grids.insert(idx, grid);
         self.grids.get_mut(&idx).unwrap()
     }
 
@@ -60,30 +90,159 @@ impl GridMap {
         self.grids.remove(&idx);
     }
 
+    pub fn set_position(&mut self, idx: u64, row: usize, col: usize, kind: GridKind, zindex: u64) {
+        self.position_seq += 1;
+        let position_seq = self.position_seq;
+
+        let grid = self.get_or_create(idx);
+        grid.row = row;
+        grid.col = col;
+        grid.kind = kind;
+        grid.zindex = zindex;
+        grid.position_seq = position_seq;
+        grid.visible = true;
+    }
+
+    pub fn hide(&mut self, idx: u64) {
+        if let Some(grid) = self.grids.get_mut(&idx) {
+            grid.visible = false;
+        }
+    }
+
     pub fn clear_glyphs(&mut self) {
         for grid in self.grids.values_mut() {
             grid.model.clear_glyphs();
         }
     }
+
+    pub fn models_mut(&mut self) -> impl Iterator<Item = &mut UiModel> {
+        self.grids.values_mut().map(|g| &mut g.model)
+    }
+
+    /// Visible grids in paint order: default grid first, then windows,
+    /// floating windows and message grid last
+    pub fn visible_grids(&self) -> Vec<(u64, &Grid)> {
+        let mut grids: Vec<_> = self
+            .grids
+            .iter()
+            .filter(|(_, g)| g.visible)
+            .map(|(&idx, g)| (idx, g))
+            .collect();
+
+        grids.sort_by_key(|&(idx, g)| (idx != DEFAULT_GRID, g.kind, g.zindex, g.position_seq));
+
+        grids
+    }
+
+    /// Topmost window grid that contains given global cell, floats are not counted
+    pub fn grid_at(&self, row: usize, col: usize) -> Option<u64> {
+        self.find_grid_at(row, col, |kind| kind == GridKind::Window)
+    }
+
+    /// Topmost grid of any kind that contains given global cell,
+    /// mouse input goes to floats over windows
+    pub fn input_grid_at(&self, row: usize, col: usize) -> Option<u64> {
+        self.find_grid_at(row, col, |_| true)
+    }
+
+    fn find_grid_at<F>(&self, row: usize, col: usize, filter: F) -> Option<u64>
+    where
+        F: Fn(GridKind) -> bool,
+    {
+        self.visible_grids()
+            .into_iter()
+            .rev()
+            .filter(|&(_, g)| filter(g.kind))
+            .find(|&(_, g)| {
+                row >= g.row
+                    && col >= g.col
+                    && row < g.row + g.rows()
+                    && col < g.col + g.columns()
+            })
+            .map(|(idx, _)| idx)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum GridKind {
+    Window,
+    Float,
+    Message,
 }
 
 pub struct Grid {
     model: UiModel,
+
+    // position of grid in default grid coordinates
+    row: usize,
+    col: usize,
+    kind: GridKind,
+    zindex: u64,
+    position_seq: u64,
+    visible: bool,
 }
 
 impl Grid {
     pub fn new() -> Self {
         Grid {
             model: UiModel::empty(),
+            row: 0,
+            col: 0,
+            kind: GridKind::Window,
+            zindex: 0,
+            position_seq: 0,
+            visible: false,
         }
     }
 
+    pub fn model(&self) -> &UiModel {
+        &self.model
+    }
+
+    pub fn model_mut(&mut self) -> &mut UiModel {
+        &mut self.model
+    }
+
+    /// Grid position (row, col) in default grid coordinates
+    pub fn position(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    pub fn kind(&self) -> GridKind {
+        self.kind
+    }
+
+    pub fn columns(&self) -> usize {
+        self.model.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.model.rows
+    }
+
+    /// Cursor position (row, col) in default grid coordinates
     pub fn get_cursor(&self) -> (usize, usize) {
-        self.model.get_cursor()
+        let (row, col) = self.model.get_cursor();
+        (row + self.row, col + self.col)
     }
 
     pub fn cur_point(&self) -> ModelRect {
-        self.model.cur_point()
+        self.to_global(self.model.cur_point())
+    }
+
+    /// Translate grid local rect to default grid coordinates
+    pub fn to_global(&self, mut rect: ModelRect) -> ModelRect {
+        rect.translate(self.row, self.col);
+        rect
+    }
+
+    pub fn area(&self) -> ModelRect {
+        ModelRect::new(
+            self.row,
+            self.row + self.model.rows.max(1) - 1,
+            self.col,
+            self.col + self.model.columns.max(1) - 1,
+        )
     }
 
     pub fn resize(&mut self, columns: u64, rows: u64) {
@@ -93,7 +252,11 @@ impl Grid {
     }
 
     pub fn cursor_goto(&mut self, row: usize, col: usize) -> ModelRectVec {
-        self.model.set_cursor(row, col)
+        let mut rects = self.model.set_cursor(row, col);
+        for rect in &mut rects.list {
+            rect.translate(self.row, self.col);
+        }
+        rects
     }
 
     pub fn clear(&mut self, default_hl: &Rc<Highlight>) {
@@ -126,7 +289,9 @@ impl Grid {
             col_end += repeat;
         }
 
-        ModelRect::new(row, row, col_start, col_end - 1)
+        let mut rect = ModelRect::new(row, row, col_start, col_end - 1);
+        rect.extend_by_items(Some(&self.model));
+        self.to_global(rect)
     }
 
     pub fn scroll(
@@ -139,13 +304,72 @@ impl Grid {
         _: i64,
         default_hl: &Rc<Highlight>,
     ) -> ModelRect {
-        self.model.scroll(
+        let mut rect = self.model.scroll(
             top as i64,
             bot as i64 - 1,
             left as usize,
             right as usize - 1,
             rows,
             default_hl,
-        )
+        );
+        rect.extend_by_items(Some(&self.model));
+
+        self.to_global(rect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_grids_order() {
+        let mut grids = GridMap::new();
+        grids.get_or_create(DEFAULT_GRID).resize(80, 24);
+        grids.get_or_create(2).resize(40, 10);
+        grids.get_or_create(3).resize(10, 2);
+        grids.get_or_create(4).resize(20, 5);
+
+        grids.set_position(3, 1, 1, GridKind::Float, 50);
+        grids.set_position(2, 0, 0, GridKind::Window, 0);
+
+        let order: Vec<_> = grids.visible_grids().iter().map(|&(idx, _)| idx).collect();
+        assert_eq!(vec![DEFAULT_GRID, 2, 3], order);
+
+        grids.hide(3);
+        let order: Vec<_> = grids.visible_grids().iter().map(|&(idx, _)| idx).collect();
+        assert_eq!(vec![DEFAULT_GRID, 2], order);
+    }
+
+    #[test]
+    fn test_cursor_position() {
+        let mut grids = GridMap::new();
+        grids.get_or_create(DEFAULT_GRID).resize(80, 24);
+        grids.get_or_create(2).resize(40, 10);
+        grids.set_position(2, 5, 10, GridKind::Window, 0);
+
+        grids.set_current_grid(2);
+        let rects = grids[2].cursor_goto(1, 2);
+
+        assert_eq!((6, 12), grids.current().unwrap().get_cursor());
+        assert_eq!(ModelRect::point(12, 6), rects.list[1]);
+    }
+
+    #[test]
+    fn test_grid_at() {
+        let mut grids = GridMap::new();
+        grids.get_or_create(DEFAULT_GRID).resize(80, 24);
+        grids.get_or_create(2).resize(40, 10);
+        grids.set_position(2, 5, 10, GridKind::Window, 0);
+        grids.get_or_create(3).resize(10, 2);
+        grids.set_position(3, 6, 12, GridKind::Float, 50);
+
+        assert_eq!(Some(2), grids.grid_at(6, 12));
+        assert_eq!(Some(DEFAULT_GRID), grids.grid_at(0, 0));
+        assert_eq!(Some(DEFAULT_GRID), grids.grid_at(15, 10));
+        assert_eq!(None, grids.grid_at(30, 10));
+
+        assert_eq!(Some(3), grids.input_grid_at(6, 12));
+        assert_eq!(Some(2), grids.input_grid_at(8, 12));
     }
 }
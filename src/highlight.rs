@@ -138,6 +138,15 @@ impl HighlightMap {
         cell.hl.special.as_ref().unwrap_or(&self.sp_color)
     }
 
+    /// Underline uses special color only when it is set for highlight,
+    /// default special color is for undercurl
+    pub fn underline_color<'a>(&'a self, cell: &'a Cell) -> &'a Color {
+        cell.hl
+            .special
+            .as_ref()
+            .unwrap_or_else(|| self.actual_cell_fg(cell))
+    }
+
     pub fn pmenu_bg(&self) -> &Color {
         if !self.pmenu.reverse {
             self.pmenu.background.as_ref().unwrap_or_else(|| self.bg())
@@ -182,6 +191,21 @@ impl HighlightMap {
         }
     }
 
+    /// Cursor color from guicursor highlight of mode, `Cursor` highlight otherwise
+    pub fn cursor_color(&self, attr_id: Option<u64>) -> Color {
+        let hl = match attr_id.and_then(|id| self.highlights.get(&id)) {
+            Some(hl) => hl,
+            None => return self.cursor_bg().clone(),
+        };
+
+        let color = if !hl.reverse {
+            hl.background.as_ref()
+        } else {
+            hl.foreground.as_ref()
+        };
+        color.unwrap_or_else(|| self.cursor_bg()).clone()
+    }
+
     pub fn cursor_bg(&self) -> &Color {
         if !self.cursor.reverse {
             self.cursor.background.as_ref().unwrap_or_else(|| self.bg())
@@ -197,11 +221,16 @@ pub struct Highlight {
     pub bold: bool,
     pub underline: bool,
     pub undercurl: bool,
+    pub underdouble: bool,
+    pub underdotted: bool,
+    pub underdashed: bool,
     pub strikethrough: bool,
     pub foreground: Option<Color>,
     pub background: Option<Color>,
     pub special: Option<Color>,
     pub reverse: bool,
+    /// blend level 0..100, same as pumblend/winblend
+    pub blend: u8,
 }
 
 impl Highlight {
@@ -214,11 +243,29 @@ impl Highlight {
             bold: false,
             underline: false,
             undercurl: false,
+            underdouble: false,
+            underdotted: false,
+            underdashed: false,
             strikethrough: false,
             reverse: false,
+            blend: 0,
         }
     }
 
+    /// Alpha of background with blend applied, blend 100 is fully transparent
+    pub fn blend_alpha(&self, alpha: Option<f64>) -> Option<f64> {
+        if self.blend == 0 {
+            alpha
+        } else {
+            Some(alpha.unwrap_or(1.0) * (1.0 - f64::from(self.blend) / 100.0))
+        }
+    }
+
+    /// Any kind of line under text
+    pub fn has_underline(&self) -> bool {
+        self.underline || self.undercurl || self.underdouble || self.underdotted || self.underdashed
+    }
+
     pub fn from_value_map(attrs: &HashMap<String, Value>) -> Self {
         let mut model_attrs = Highlight::new();
 
@@ -244,7 +291,17 @@ impl Highlight {
                 "italic" => model_attrs.italic = true,
                 "underline" => model_attrs.underline = true,
                 "undercurl" => model_attrs.undercurl = true,
+                "underdouble" => model_attrs.underdouble = true,
+                "underdotted" => model_attrs.underdotted = true,
+                "underdashed" => model_attrs.underdashed = true,
                 "strikethrough" => model_attrs.strikethrough = true,
+                "blend" => {
+                    if let Some(blend) = val.as_u64() {
+                        model_attrs.blend = blend.min(100) as u8;
+                    }
+                }
+                // alternative font is not supported, no need to log it
+                "altfont" => (),
                 attr_key => error!("unknown attribute {}", attr_key),
             };
         }
@@ -252,3 +309,38 @@ impl Highlight {
         model_attrs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decorations() {
+        let mut attrs = HashMap::new();
+        attrs.insert("underdotted".to_owned(), Value::from(true));
+        attrs.insert("blend".to_owned(), Value::from(30));
+
+        let hl = Highlight::from_value_map(&attrs);
+        assert!(hl.underdotted);
+        assert!(hl.has_underline());
+        assert!(!hl.underdashed);
+        assert_eq!(30, hl.blend);
+
+        attrs.insert("blend".to_owned(), Value::from(250));
+        assert_eq!(100, Highlight::from_value_map(&attrs).blend);
+    }
+
+    #[test]
+    fn test_blend_alpha() {
+        let mut hl = Highlight::new();
+        assert_eq!(None, hl.blend_alpha(None));
+        assert_eq!(Some(0.5), hl.blend_alpha(Some(0.5)));
+
+        hl.blend = 20;
+        assert_eq!(Some(0.8), hl.blend_alpha(None));
+        assert_eq!(Some(0.4), hl.blend_alpha(Some(0.5)));
+
+        hl.blend = 100;
+        assert_eq!(Some(0.0), hl.blend_alpha(None));
+    }
+}
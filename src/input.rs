@@ -3,7 +3,9 @@ use gtk::prelude::*;
 use gdk;
 use gdk::EventKey;
 use phf;
-use neovim_lib::{Neovim, NeovimApi};
+use neovim_lib::{Neovim, NeovimApiAsync};
+
+use crate::nvim::ErrorReport;
 
 include!(concat!(env!("OUT_DIR"), "/key_map_table.rs"));
 
@@ -60,6 +62,23 @@ pub fn keyval_to_input_string(in_str: &str, in_state: gdk::ModifierType) -> Stri
     }
 }
 
+/// Modifiers of mouse event in `nvim_input_mouse` format
+pub fn mouse_modifiers(state: gdk::ModifierType) -> String {
+    let mut modifiers = String::new();
+
+    if state.contains(gdk::ModifierType::SHIFT_MASK) {
+        modifiers.push('S');
+    }
+    if state.contains(gdk::ModifierType::CONTROL_MASK) {
+        modifiers.push('C');
+    }
+    if state.contains(gdk::ModifierType::MOD1_MASK) {
+        modifiers.push('A');
+    }
+
+    modifiers
+}
+
 pub fn convert_key(ev: &EventKey) -> Option<String> {
     let keyval = ev.get_keyval();
     let state = ev.get_state();
@@ -85,13 +104,13 @@ pub fn im_input(nvim: &mut Neovim, input: &str) {
             keyval_to_input_string(&ch.to_string(), gdk::ModifierType::empty())
         })
         .collect();
-    nvim.input(&input).expect("Error run input command to nvim");
+    nvim.input_async(&input).cb(|r| r.report_err()).call();
 }
 
 pub fn gtk_key_press(nvim: &mut Neovim, ev: &EventKey) -> Inhibit {
     if let Some(input) = convert_key(ev) {
         debug!("nvim_input -> {}", input);
-        nvim.input(&input).expect("Error run input command to nvim");
+        nvim.input_async(&input).cb(|r| r.report_err()).call();
         Inhibit(true)
     } else {
         Inhibit(false)
@@ -18,22 +18,28 @@ mod ui_model;
 mod value;
 #[macro_use]
 mod ui;
+mod bell;
 mod cmd_line;
 mod cursor;
 mod error;
 mod file_browser;
+mod float_win;
 mod grid;
 mod highlight;
 mod input;
+mod messages;
 mod misc;
 mod nvim;
 mod plug_manager;
 mod popup_menu;
 mod project;
+mod remote;
 mod render;
+mod sessions;
 mod settings;
 mod shell;
 mod shell_dlg;
+mod smooth_scroll;
 mod subscriptions;
 mod tabline;
 mod theme_helper;
@@ -74,6 +80,25 @@ fn main() {
         .arg(Arg::with_name("cterm-colors")
              .long("cterm-colors")
              .help("Use ctermfg/ctermbg instead of guifg/guibg"))
+        .arg(Arg::with_name("no-ext-messages")
+             .long("no-ext-messages")
+             .help("Show messages and command line in nvim grid instead of notifications and popup"))
+        .arg(Arg::with_name("server")
+             .long("server")
+             .value_name("ADDRESS")
+             .help("Attach to running nvim started with --listen, host:port or socket path")
+             .takes_value(true))
+        .arg(Arg::with_name("persistent")
+             .long("persistent")
+             .conflicts_with("server")
+             .help("Run nvim as server of current project, closing window keeps server running \
+                    and next start in project attaches to it"))
+        .arg(Arg::with_name("remote")
+             .long("remote")
+             .value_name("PROFILE")
+             .conflicts_with_all(&["server", "persistent"])
+             .help("Run nvim on other host through ssh, profile name from remotes.toml or [user@]host")
+             .takes_value(true))
         .arg(Arg::with_name("files").help("Files to open").multiple(true))
         .arg(
             Arg::with_name("nvim-bin-path")
@@ -131,6 +156,17 @@ fn main() {
     new_window_action.connect_activate(move |_, _| activate(&app_ref, &matches_copy, None));
     app.add_action(&new_window_action);
 
+    let app_ref = app.clone();
+    let matches_copy = matches.clone();
+    let connect_server_action =
+        gio::SimpleAction::new("connect-server", Some(glib::VariantTy::new("s").unwrap()));
+    connect_server_action.connect_activate(move |_, server| {
+        if let Some(server) = server.and_then(|server| server.get_str()) {
+            connect_server(&app_ref, &matches_copy, server);
+        }
+    });
+    app.add_action(&connect_server_action);
+
     gtk::Window::set_default_icon_name("org.daa.NeovimGtk");
 
     let app_exe = std::env::args().next().unwrap_or_else(|| "nvim-gtk".to_owned());
@@ -167,6 +203,13 @@ fn activate(app: &gtk::Application, matches: &ArgMatches, input_data: Option<Str
     ui.init(app, !matches.is_present("disable-win-restore"));
 }
 
+fn connect_server(app: &gtk::Application, matches: &ArgMatches, server: &str) {
+    let options = ShellOptions::new(matches, None).with_server(server.to_owned());
+    let mut ui = Ui::new(options, Box::new([]));
+
+    ui.init(app, !matches.is_present("disable-win-restore"));
+}
+
 fn read_piped_input() -> Option<String> {
     if atty::isnt(atty::Stream::Stdin) {
         let mut buf = String::new();
@@ -0,0 +1,484 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use htmlescape::encode_minimal;
+
+use crate::highlight::HighlightMap;
+
+const MAX_TOASTS: usize = 5;
+const MAX_HISTORY: usize = 200;
+
+const TOAST_TIMEOUT_SEC: u32 = 4;
+const TOAST_ERROR_TIMEOUT_SEC: u32 = 8;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MessageKind {
+    Info,
+    Warning,
+    Error,
+    // stays visible until nvim sends msg_clear
+    Prompt,
+}
+
+impl MessageKind {
+    fn from_kind(kind: &str) -> Self {
+        match kind {
+            "emsg" | "echoerr" | "lua_error" | "rpc_error" => MessageKind::Error,
+            "wmsg" => MessageKind::Warning,
+            "return_prompt" | "confirm" | "confirm_sub" => MessageKind::Prompt,
+            _ => MessageKind::Info,
+        }
+    }
+
+    fn css_class(self) -> Option<&'static str> {
+        match self {
+            MessageKind::Error => Some("error"),
+            MessageKind::Warning => Some("warning"),
+            MessageKind::Prompt => Some("prompt"),
+            MessageKind::Info => None,
+        }
+    }
+
+    fn timeout(self) -> Option<u32> {
+        match self {
+            MessageKind::Info | MessageKind::Warning => Some(TOAST_TIMEOUT_SEC),
+            MessageKind::Error => Some(TOAST_ERROR_TIMEOUT_SEC),
+            MessageKind::Prompt => None,
+        }
+    }
+}
+
+/// Convert ext_messages content chunks `[attr_id, text]` to pango markup
+pub fn content_to_markup(content: &[(u64, String)], hl: &HighlightMap) -> String {
+    let mut markup = String::new();
+
+    for &(attr_id, ref text) in content {
+        let attr = hl.get(Some(attr_id));
+        let mut span = String::from("<span");
+
+        if let Some(ref fg) = attr.foreground {
+            span.push_str(&format!(" foreground=\"{}\"", fg.to_hex()));
+        }
+        if let Some(ref bg) = attr.background {
+            span.push_str(&format!(" background=\"{}\"", bg.to_hex()));
+        }
+        if attr.bold {
+            span.push_str(" weight=\"bold\"");
+        }
+        if attr.italic {
+            span.push_str(" style=\"italic\"");
+        }
+        if attr.undercurl {
+            span.push_str(" underline=\"error\"");
+        } else if attr.underdouble {
+            span.push_str(" underline=\"double\"");
+        } else if attr.has_underline() {
+            span.push_str(" underline=\"single\"");
+        }
+        if attr.strikethrough {
+            span.push_str(" strikethrough=\"true\"");
+        }
+
+        span.push('>');
+        markup.push_str(&span);
+        markup.push_str(&encode_minimal(text));
+        markup.push_str("</span>");
+    }
+
+    markup
+}
+
+fn with_font(markup: &str, font_desc: &pango::FontDescription) -> String {
+    format!(
+        "<span font_desc=\"{}\">{}</span>",
+        encode_minimal(&font_desc.to_string()),
+        markup
+    )
+}
+
+fn content_to_text(content: &[(u64, String)]) -> String {
+    content.iter().map(|c| c.1.as_str()).collect()
+}
+
+fn update_css(css_provider: &gtk::CssProvider, hl: &HighlightMap) {
+    if let Err(e) = gtk::CssProviderExt::load_from_data(
+        css_provider,
+        format!(
+            ".nvim-message {{
+                color: {fg};
+                background-color: {bg};
+                border: 1px solid alpha({fg}, 0.2);
+                border-radius: 4px;
+                padding: 6px 10px;
+                margin: 2px;
+                box-shadow: 0 2px 6px 1px alpha(black, 0.35);
+            }}
+            .nvim-message.error {{ border-left: 3px solid #e01b24; }}
+            .nvim-message.warning {{ border-left: 3px solid #f5c211; }}
+            .nvim-message.prompt {{ border-left: 3px solid #3584e4; }}
+            .nvim-message-history {{ background-color: {bg}; color: {fg}; }}",
+            fg = hl.fg().to_hex(),
+            bg = hl.bg().to_hex(),
+        )
+        .as_bytes(),
+    ) {
+        error!("Can't update messages css {}", e);
+    }
+}
+
+struct Toast {
+    event_box: gtk::EventBox,
+    label: gtk::Label,
+    kind: MessageKind,
+    timer: Rc<RefCell<Option<glib::SourceId>>>,
+}
+
+impl Toast {
+    fn new(css_provider: &gtk::CssProvider) -> Self {
+        let label = gtk::Label::new(None);
+        label.set_line_wrap(true);
+        label.set_line_wrap_mode(pango::WrapMode::WordChar);
+        label.set_max_width_chars(100);
+        label.set_xalign(0.0);
+        label.set_selectable(false);
+
+        let event_box = gtk::EventBox::new();
+        event_box.add(&label);
+
+        let style_context = event_box.get_style_context();
+        style_context.add_class("nvim-message");
+        style_context.add_provider(css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+
+        Toast {
+            event_box,
+            label,
+            kind: MessageKind::Info,
+            timer: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    fn set_kind(&mut self, kind: MessageKind) {
+        let style_context = self.event_box.get_style_context();
+        if let Some(class) = self.kind.css_class() {
+            style_context.remove_class(class);
+        }
+        if let Some(class) = kind.css_class() {
+            style_context.add_class(class);
+        }
+        self.kind = kind;
+    }
+
+    fn stop_timer(&self) {
+        if let Some(timer) = self.timer.borrow_mut().take() {
+            glib::source_remove(timer);
+        }
+    }
+}
+
+/// Neovim messages shown with ext_messages
+///
+/// Messages are shown as stacked toasts over the drawing area,
+/// all messages also saved to history, that can be viewed in popover.
+/// Mode, pending command and ruler are shown in status box
+/// that can be placed in header bar.
+pub struct Messages {
+    toast_box: gtk::Box,
+    toasts: Rc<RefCell<VecDeque<Toast>>>,
+    css_provider: gtk::CssProvider,
+
+    history: VecDeque<(String, String)>,
+    history_popover: gtk::Popover,
+    history_list: gtk::Box,
+    history_scroll: gtk::ScrolledWindow,
+
+    status_box: gtk::Box,
+    showmode: gtk::Label,
+    showcmd: gtk::Label,
+    ruler: gtk::Label,
+}
+
+impl Messages {
+    pub fn new(drawing_area: &gtk::DrawingArea) -> Self {
+        let css_provider = gtk::CssProvider::new();
+
+        let toast_box = gtk::Box::new(gtk::Orientation::Vertical, 4);
+        toast_box.set_halign(gtk::Align::End);
+        toast_box.set_valign(gtk::Align::End);
+        toast_box.set_margin_end(12);
+        toast_box.set_margin_bottom(12);
+        toast_box.set_no_show_all(true);
+
+        let history_list = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        history_list
+            .get_style_context()
+            .add_class("nvim-message-history");
+        history_list
+            .get_style_context()
+            .add_provider(&css_provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+
+        let history_scroll = gtk::ScrolledWindow::new(
+            Option::<&gtk::Adjustment>::None,
+            Option::<&gtk::Adjustment>::None,
+        );
+        history_scroll.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        history_scroll.set_min_content_height(300);
+        history_scroll.set_min_content_width(500);
+        history_scroll.add(&history_list);
+        history_scroll.show_all();
+
+        let history_popover = gtk::Popover::new(Some(drawing_area));
+        history_popover.set_position(gtk::PositionType::Top);
+        history_popover.add(&history_scroll);
+
+        let status_box = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let showmode = gtk::Label::new(None);
+        let showcmd = gtk::Label::new(None);
+        let ruler = gtk::Label::new(None);
+        status_box.pack_start(&showmode, false, false, 0);
+        status_box.pack_start(&showcmd, false, false, 0);
+        status_box.pack_start(&ruler, false, false, 0);
+        status_box.get_style_context().add_class("dim-label");
+        status_box.show_all();
+
+        Messages {
+            toast_box,
+            toasts: Rc::new(RefCell::new(VecDeque::new())),
+            css_provider,
+
+            history: VecDeque::new(),
+            history_popover,
+            history_list,
+            history_scroll,
+
+            status_box,
+            showmode,
+            showcmd,
+            ruler,
+        }
+    }
+
+    /// Toasts container, must be added as overlay over drawing area
+    pub fn toast_widget(&self) -> &gtk::Box {
+        &self.toast_box
+    }
+
+    /// Mode/showcmd/ruler labels, can be placed in header bar
+    pub fn status_widget(&self) -> &gtk::Box {
+        &self.status_box
+    }
+
+    pub fn show(
+        &mut self,
+        kind: &str,
+        content: &[(u64, String)],
+        replace_last: bool,
+        hl: &HighlightMap,
+        font_desc: &pango::FontDescription,
+    ) {
+        update_css(&self.css_provider, hl);
+
+        let message_kind = MessageKind::from_kind(kind);
+        let markup = content_to_markup(content, hl);
+
+        if message_kind != MessageKind::Prompt {
+            self.push_history(kind, content_to_text(content));
+        }
+
+        let mut toasts = self.toasts.borrow_mut();
+
+        let reuse_last = replace_last && !toasts.is_empty();
+        if !reuse_last {
+            if toasts.len() >= MAX_TOASTS {
+                if let Some(toast) = toasts.pop_front() {
+                    toast.stop_timer();
+                    self.toast_box.remove(&toast.event_box);
+                }
+            }
+
+            let toast = Toast::new(&self.css_provider);
+            self.toast_box.pack_start(&toast.event_box, false, false, 0);
+
+            let toasts_ref = self.toasts.clone();
+            let toast_box = self.toast_box.clone();
+            toast
+                .event_box
+                .connect_button_press_event(move |event_box, _| {
+                    remove_toast(&toasts_ref, &toast_box, event_box);
+                    Inhibit(true)
+                });
+
+            toasts.push_back(toast);
+        }
+
+        let toast = toasts.back_mut().unwrap();
+        toast.set_kind(message_kind);
+        toast.label.set_markup(&with_font(&markup, font_desc));
+        toast.event_box.show_all();
+        self.toast_box.show();
+
+        toast.stop_timer();
+        if let Some(timeout) = message_kind.timeout() {
+            *toast.timer.borrow_mut() = Some(self.dismiss_timer(&toast.event_box, timeout));
+        }
+    }
+
+    fn dismiss_timer(&self, event_box: &gtk::EventBox, timeout: u32) -> glib::SourceId {
+        let toasts = self.toasts.clone();
+        let toast_box = self.toast_box.clone();
+        let event_box = event_box.clone();
+
+        gtk::timeout_add_seconds(timeout, move || {
+            if let Some(toast) = toasts.borrow().iter().find(|t| t.event_box == event_box) {
+                toast.timer.borrow_mut().take();
+            }
+            remove_toast(&toasts, &toast_box, &event_box);
+            Continue(false)
+        })
+    }
+
+    /// Nvim clears message area, prompts are removed,
+    /// other messages are left until their timeout
+    pub fn clear(&mut self) {
+        let prompts: Vec<gtk::EventBox> = self
+            .toasts
+            .borrow()
+            .iter()
+            .filter(|t| t.kind == MessageKind::Prompt)
+            .map(|t| t.event_box.clone())
+            .collect();
+
+        for event_box in prompts {
+            remove_toast(&self.toasts, &self.toast_box, &event_box);
+        }
+    }
+
+    pub fn set_showmode(&self, content: &[(u64, String)], hl: &HighlightMap) {
+        self.showmode.set_markup(&content_to_markup(content, hl));
+    }
+
+    pub fn set_showcmd(&self, content: &[(u64, String)], hl: &HighlightMap) {
+        self.showcmd.set_markup(&content_to_markup(content, hl));
+    }
+
+    pub fn set_ruler(&self, content: &[(u64, String)], hl: &HighlightMap) {
+        self.ruler.set_markup(&content_to_markup(content, hl));
+    }
+
+    fn push_history(&mut self, kind: &str, text: String) {
+        if text.is_empty() {
+            return;
+        }
+
+        if self.history.len() >= MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back((kind.to_owned(), text));
+    }
+
+    /// Show `:messages` output from nvim
+    pub fn history_show(
+        &mut self,
+        entries: &[(String, Vec<(u64, String)>)],
+        hl: &HighlightMap,
+        font_desc: &pango::FontDescription,
+    ) {
+        update_css(&self.css_provider, hl);
+
+        let entries: Vec<(MessageKind, String)> = entries
+            .iter()
+            .map(|(kind, content)| (MessageKind::from_kind(kind), content_to_markup(content, hl)))
+            .collect();
+        self.fill_history(&entries, font_desc);
+    }
+
+    /// Show messages received by this UI
+    pub fn show_history(&self, hl: &HighlightMap, font_desc: &pango::FontDescription) {
+        update_css(&self.css_provider, hl);
+
+        let entries: Vec<(MessageKind, String)> = self
+            .history
+            .iter()
+            .map(|(kind, text)| (MessageKind::from_kind(kind), encode_minimal(text)))
+            .collect();
+        self.fill_history(&entries, font_desc);
+    }
+
+    pub fn history_clear(&mut self) {
+        self.history.clear();
+        for child in self.history_list.get_children() {
+            self.history_list.remove(&child);
+        }
+    }
+
+    fn fill_history(&self, entries: &[(MessageKind, String)], font_desc: &pango::FontDescription) {
+        for child in self.history_list.get_children() {
+            self.history_list.remove(&child);
+        }
+
+        for &(kind, ref markup) in entries {
+            let label = gtk::Label::new(None);
+            label.set_markup(&with_font(markup, font_desc));
+            label.set_xalign(0.0);
+            label.set_line_wrap(true);
+            label.set_line_wrap_mode(pango::WrapMode::WordChar);
+            label.set_selectable(true);
+            if kind == MessageKind::Error {
+                label.get_style_context().add_class("error");
+            }
+            self.history_list.pack_start(&label, false, false, 0);
+        }
+        self.history_list.show_all();
+
+        self.history_popover.popup();
+
+        // scroll to latest message
+        let history_scroll = self.history_scroll.clone();
+        gtk::idle_add(move || {
+            if let Some(adj) = history_scroll.get_vadjustment() {
+                adj.set_value(adj.get_upper());
+            }
+            Continue(false)
+        });
+    }
+}
+
+fn remove_toast(
+    toasts: &Rc<RefCell<VecDeque<Toast>>>,
+    toast_box: &gtk::Box,
+    event_box: &gtk::EventBox,
+) {
+    let mut toasts = toasts.borrow_mut();
+    if let Some(idx) = toasts.iter().position(|t| t.event_box == *event_box) {
+        let toast = toasts.remove(idx).unwrap();
+        toast.stop_timer();
+        toast_box.remove(&toast.event_box);
+    }
+
+    if toasts.is_empty() {
+        toast_box.hide();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_kind() {
+        assert_eq!(MessageKind::Error, MessageKind::from_kind("emsg"));
+        assert_eq!(MessageKind::Error, MessageKind::from_kind("echoerr"));
+        assert_eq!(MessageKind::Warning, MessageKind::from_kind("wmsg"));
+        assert_eq!(MessageKind::Prompt, MessageKind::from_kind("return_prompt"));
+        assert_eq!(MessageKind::Info, MessageKind::from_kind(""));
+        assert_eq!(None, MessageKind::Prompt.timeout());
+    }
+
+    #[test]
+    fn test_content_to_markup_escape() {
+        let hl = HighlightMap::new();
+        let markup = content_to_markup(&[(0, "a < b".to_owned())], &hl);
+        assert_eq!("<span>a &lt; b</span>", markup);
+    }
+}
@@ -77,11 +77,39 @@ impl CursorShape {
     }
 }
 
+/// Blink timings (wait, on, off) in ms, used when mode does not send it
+pub const DEFAULT_BLINK: (u32, u32, u32) = (500, 700, 500);
+
+/// Gdk cursor names for nvim mouse shapes, in nvim shape table order
+const MOUSE_SHAPES: [&str; 16] = [
+    "default",     // arrow
+    "none",        // blank
+    "text",        // beam
+    "ns-resize",   // updown
+    "row-resize",  // udsizing
+    "ew-resize",   // leftright
+    "col-resize",  // lrsizing
+    "wait",        // busy
+    "not-allowed", // no
+    "crosshair",   // crosshair
+    "grab",        // hand1
+    "pointer",     // hand2
+    "crosshair",   // pencil
+    "help",        // question
+    "default",     // rightup-arrow
+    "default",     // up-arrow
+];
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct ModeInfo {
     cursor_shape: Option<CursorShape>,
     cell_percentage: Option<u64>,
     pub blinkwait: Option<u32>,
+    pub blinkon: Option<u32>,
+    pub blinkoff: Option<u32>,
+    attr_id: Option<u64>,
+    attr_id_lm: Option<u64>,
+    mouse_shape: Option<u64>,
 }
 
 impl ModeInfo {
@@ -92,10 +120,22 @@ impl ModeInfo {
             None
         };
 
+        let get_u32 = |name| {
+            mode_info_map
+                .get(name)
+                .and_then(Value::as_u64)
+                .map(|v| v as u32)
+        };
+
         Ok(ModeInfo {
             cursor_shape,
             cell_percentage: mode_info_map.get("cell_percentage").and_then(|cp| cp.as_u64()),
-            blinkwait: mode_info_map.get("blinkwait").and_then(|cp| cp.as_u64()).map(|v| v as u32),
+            blinkwait: get_u32("blinkwait"),
+            blinkon: get_u32("blinkon"),
+            blinkoff: get_u32("blinkoff"),
+            attr_id: mode_info_map.get("attr_id").and_then(Value::as_u64),
+            attr_id_lm: mode_info_map.get("attr_id_lm").and_then(Value::as_u64),
+            mouse_shape: mode_info_map.get("mouse_shape").and_then(Value::as_u64),
         })
     }
 
@@ -106,4 +146,93 @@ impl ModeInfo {
     pub fn cell_percentage(&self) -> u64 {
         self.cell_percentage.unwrap_or(0)
     }
+
+    /// Highlight of cursor, `attr_id_lm` is used when language mapping is on,
+    /// 0 means default `Cursor` highlight
+    pub fn attr_id(&self, langmap: bool) -> Option<u64> {
+        let attr_id = if langmap {
+            self.attr_id_lm.or(self.attr_id)
+        } else {
+            self.attr_id
+        };
+
+        attr_id.filter(|&id| id != 0)
+    }
+
+    /// Blink timings (wait, on, off) in ms, None if cursor does not blink
+    ///
+    /// As in guicursor any zero value disables blinking.
+    pub fn blink(&self) -> Option<(u32, u32, u32)> {
+        let wait = self.blinkwait.unwrap_or(DEFAULT_BLINK.0);
+        let on = self.blinkon.unwrap_or(DEFAULT_BLINK.1);
+        let off = self.blinkoff.unwrap_or(DEFAULT_BLINK.2);
+
+        if wait == 0 || on == 0 || off == 0 {
+            None
+        } else {
+            Some((wait, on, off))
+        }
+    }
+
+    /// Gdk name of mouse pointer, arrow is default shape for all modes
+    /// so text cursor is kept for it
+    pub fn mouse_cursor(&self) -> Option<&'static str> {
+        self.mouse_shape
+            .filter(|&shape| shape != 0)
+            .and_then(|shape| MOUSE_SHAPES.get(shape as usize))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode_info(values: &[(&str, Value)]) -> ModeInfo {
+        let map = values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect();
+        ModeInfo::new(&map).unwrap()
+    }
+
+    #[test]
+    fn test_blink() {
+        assert_eq!(Some(DEFAULT_BLINK), mode_info(&[]).blink());
+
+        let info = mode_info(&[
+            ("blinkwait", Value::from(700)),
+            ("blinkon", Value::from(400)),
+            ("blinkoff", Value::from(250)),
+        ]);
+        assert_eq!(Some((700, 400, 250)), info.blink());
+
+        let info = mode_info(&[
+            ("blinkwait", Value::from(0)),
+            ("blinkon", Value::from(400)),
+            ("blinkoff", Value::from(250)),
+        ]);
+        assert_eq!(None, info.blink());
+    }
+
+    #[test]
+    fn test_attr_id() {
+        let info = mode_info(&[("attr_id", Value::from(5)), ("attr_id_lm", Value::from(6))]);
+        assert_eq!(Some(5), info.attr_id(false));
+        assert_eq!(Some(6), info.attr_id(true));
+
+        let info = mode_info(&[("attr_id", Value::from(0))]);
+        assert_eq!(None, info.attr_id(false));
+        assert_eq!(None, info.attr_id(true));
+    }
+
+    #[test]
+    fn test_mouse_cursor() {
+        assert_eq!(None, mode_info(&[("mouse_shape", Value::from(0))]).mouse_cursor());
+        assert_eq!(
+            Some("text"),
+            mode_info(&[("mouse_shape", Value::from(2))]).mouse_cursor()
+        );
+        assert_eq!(None, mode_info(&[("mouse_shape", Value::from(100))]).mouse_cursor());
+    }
 }
@@ -3,7 +3,8 @@ use std::ops::{Deref, DerefMut};
 use std::sync::{Arc, Mutex, MutexGuard};
 
 use super::ErrorReport;
-use neovim_lib::{Neovim, NeovimApi};
+use neovim_lib::neovim_api::{Buffer, Tabpage, Window};
+use neovim_lib::{CallError, Neovim, NeovimApi, UiOption, Value};
 
 #[derive(Clone, Copy, PartialEq)]
 enum NeovimClientState {
@@ -46,13 +47,113 @@ impl<'a> NeovimRef<'a> {
     }
 
     pub fn non_blocked(mut self) -> Option<Self> {
-        self.get_mode().ok_and_report().and_then(|mode| {
+        // nvim_get_mode is answered even if nvim waits for input
+        self.blocking().get_mode().ok_and_report().and_then(|mode| {
             mode.iter()
                 .find(|kv| kv.0.as_str().map(|key| key == "blocking").unwrap_or(false))
                 .map(|kv| kv.1.as_bool().unwrap_or(false))
                 .and_then(|block| if block { None } else { Some(self) })
         })
     }
+
+    /// Neovim without blocking request check, for requests that must wait
+    /// for answer even from gtk signal handler
+    pub fn blocking(&mut self) -> &mut Neovim {
+        &mut *self
+    }
+
+    pub fn set_ui_option(&mut self, option: UiOption) -> Result<(), CallError> {
+        check_blocking("nvim_ui_set_option");
+        self.blocking().set_option(option)
+    }
+}
+
+/// Window is frozen until nvim answers blocking request,
+/// so gtk signal handler must use async request
+fn check_blocking(method: &str) {
+    debug_assert!(
+        !(gtk::is_initialized_main_thread() && gtk::get_current_event().is_some()),
+        "Blocking nvim request {} from gtk signal handler, use async request",
+        method
+    );
+}
+
+macro_rules! checked_api {
+    ($($name:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        impl<'a> NeovimApi for NeovimRef<'a> {
+            $(
+                fn $name(&mut self, $($arg: $ty),*) -> Result<$ret, CallError> {
+                    check_blocking(stringify!($name));
+                    NeovimApi::$name(self.blocking(), $($arg),*)
+                }
+            )*
+        }
+    };
+}
+
+checked_api! {
+    ui_detach() -> ();
+    ui_try_resize(width: i64, height: i64) -> ();
+    ui_set_option(name: &str, value: Value) -> ();
+    command(command: &str) -> ();
+    get_hl_by_name(name: &str, rgb: bool) -> Vec<(Value, Value)>;
+    get_hl_by_id(hl_id: i64, rgb: bool) -> Vec<(Value, Value)>;
+    feedkeys(keys: &str, mode: &str, escape_csi: bool) -> ();
+    input(keys: &str) -> i64;
+    replace_termcodes(str: &str, from_part: bool, do_lt: bool, special: bool) -> String;
+    command_output(command: &str) -> String;
+    eval(expr: &str) -> Value;
+    execute_lua(code: &str, args: Vec<Value>) -> Value;
+    call_function(fname: &str, args: Vec<Value>) -> Value;
+    call_dict_function(dict: Value, fname: &str, args: Vec<Value>) -> Value;
+    strwidth(text: &str) -> i64;
+    list_runtime_paths() -> Vec<String>;
+    set_current_dir(dir: &str) -> ();
+    get_current_line() -> String;
+    set_current_line(line: &str) -> ();
+    del_current_line() -> ();
+    get_var(name: &str) -> Value;
+    set_var(name: &str, value: Value) -> ();
+    del_var(name: &str) -> ();
+    get_vvar(name: &str) -> Value;
+    get_option(name: &str) -> Value;
+    set_option(name: &str, value: Value) -> ();
+    out_write(str: &str) -> ();
+    err_write(str: &str) -> ();
+    err_writeln(str: &str) -> ();
+    list_bufs() -> Vec<Buffer>;
+    get_current_buf() -> Buffer;
+    set_current_buf(buffer: &Buffer) -> ();
+    list_wins() -> Vec<Window>;
+    get_current_win() -> Window;
+    set_current_win(window: &Window) -> ();
+    list_tabpages() -> Vec<Tabpage>;
+    get_current_tabpage() -> Tabpage;
+    set_current_tabpage(tabpage: &Tabpage) -> ();
+    create_namespace(name: &str) -> i64;
+    get_namespaces() -> Vec<(Value, Value)>;
+    subscribe(event: &str) -> ();
+    unsubscribe(event: &str) -> ();
+    get_color_by_name(name: &str) -> i64;
+    get_color_map() -> Vec<(Value, Value)>;
+    get_mode() -> Vec<(Value, Value)>;
+    get_keymap(mode: &str) -> Vec<Vec<(Value, Value)>>;
+    get_commands(opts: Vec<(Value, Value)>) -> Vec<(Value, Value)>;
+    get_api_info() -> Vec<Value>;
+    set_client_info(
+        name: &str,
+        version: Vec<(Value, Value)>,
+        typ: &str,
+        methods: Vec<(Value, Value)>,
+        attributes: Vec<(Value, Value)>
+    ) -> ();
+    get_chan_info(chan: i64) -> Vec<(Value, Value)>;
+    list_chans() -> Vec<Value>;
+    call_atomic(calls: Vec<Value>) -> Vec<Value>;
+    parse_expression(expr: &str, flags: &str, highlight: bool) -> Vec<(Value, Value)>;
+    list_uis() -> Vec<Value>;
+    get_proc_children(pid: i64) -> Vec<Value>;
+    get_proc(pid: i64) -> Value;
 }
 
 impl<'a> Deref for NeovimRef<'a> {
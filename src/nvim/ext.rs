@@ -2,6 +2,8 @@ use std::result;
 
 use neovim_lib::CallError;
 
+use crate::ui::UiMutex;
+
 pub trait ErrorReport<T> {
     fn report_err(&self);
 
@@ -20,3 +22,24 @@ impl<T> ErrorReport<T> for result::Result<T, CallError> {
         self.ok()
     }
 }
+
+/// Callback of async request that is called on ui thread
+///
+/// Async callbacks are called on rpc dispatch thread, so result
+/// is passed to ui thread through `glib::idle_add`.
+pub fn on_ui_thread<T, F>(cb: F) -> impl FnOnce(T) + Send + 'static
+where
+    T: Send + 'static,
+    F: FnOnce(T) + 'static,
+{
+    let cb = UiMutex::new(Some(cb));
+    move |result| {
+        let mut result = Some(result);
+        glib::idle_add(move || {
+            if let (Some(cb), Some(result)) = (cb.replace(None), result.take()) {
+                cb(result);
+            }
+            glib::Continue(false)
+        });
+    }
+}
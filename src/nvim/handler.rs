@@ -8,12 +8,17 @@ use crate::shell;
 use glib;
 
 use super::repaint_mode::RepaintMode;
+use super::redraw_batch::{RedrawBatch, RedrawStats};
 use super::redraw_handler;
 
 pub struct NvimHandler {
     shell: Arc<UiMutex<shell::State>>,
 
     delayed_redraw_event_id: Arc<UiMutex<Option<glib::SourceId>>>,
+
+    // events are applied on flush
+    redraw_batch: RedrawBatch,
+    redraw_stats: Arc<RedrawStats>,
 }
 
 impl NvimHandler {
@@ -21,20 +26,23 @@ impl NvimHandler {
         NvimHandler {
             shell,
             delayed_redraw_event_id: Arc::new(UiMutex::new(None)),
+            redraw_batch: RedrawBatch::new(),
+            redraw_stats: Arc::new(RedrawStats::default()),
         }
     }
 
     pub fn schedule_redraw_event(&self, event: Value) {
         let shell = self.shell.clone();
         let delayed_redraw_event_id = self.delayed_redraw_event_id.clone();
+        let stats = self.redraw_stats.clone();
 
         glib::idle_add(move || {
             let id = Some(glib::timeout_add(
                 250,
-                clone!(shell, event, delayed_redraw_event_id => move || {
+                clone!(shell, event, delayed_redraw_event_id, stats => move || {
                 delayed_redraw_event_id.replace(None);
 
-                if let Err(msg) = call_redraw_handler(vec![event.clone()], &shell) {
+                if let Err(msg) = call_redraw_handler(vec![event.clone()], &shell, &stats) {
                     error!("Error call function: {}", msg);
                 }
 
@@ -60,12 +68,18 @@ impl NvimHandler {
         });
     }
 
-    fn nvim_cb(&self, method: &str, mut params: Vec<Value>) {
+    fn nvim_cb(&mut self, method: &str, params: Vec<Value>) {
         match method {
             "redraw" => {
-                redraw_handler::remove_or_delay_uneeded_events(self, &mut params);
+                self.redraw_stats.notification();
+
+                // partially applied batch must not be painted
+                if let Some(mut batch) = self.redraw_batch.push(params) {
+                    redraw_handler::remove_or_delay_uneeded_events(self, &mut batch);
 
-                self.safe_call(move |ui| call_redraw_handler(params, ui));
+                    let stats = self.redraw_stats.clone();
+                    self.safe_call(move |ui| call_redraw_handler(batch, ui, &stats));
+                }
             }
             "Gui" => {
                 if !params.is_empty() {
@@ -109,6 +123,7 @@ impl NvimHandler {
 
     fn nvim_cb_req(&self, method: &str, params: Vec<Value>) -> result::Result<Value, Value> {
         match method {
+            "RedrawStats" => Ok(self.redraw_stats.to_value()),
             "Gui" => {
                 if !params.is_empty() {
                     let mut params_iter = params.into_iter();
@@ -161,9 +176,11 @@ impl NvimHandler {
     }
 }
 
+/// Apply batch of redraw events and queue repaint of merged area
 fn call_redraw_handler(
     params: Vec<Value>,
     ui: &Arc<UiMutex<shell::State>>,
+    stats: &RedrawStats,
 ) -> result::Result<(), String> {
     let ui = &mut ui.borrow_mut();
     let mut repaint_mode = RepaintMode::Nothing;
@@ -179,10 +196,15 @@ fn call_redraw_handler(
                             Value::Array(ar) => ar,
                             _ => vec![],
                         };
+                        // rest of batch is applied, so window is not left half updated
                         let call_reapint_mode = match redraw_handler::call(ui, &ev_name, args) {
                             Ok(mode) => mode,
-                            Err(desc) => return Err(format!("Event {}\n{}", ev_name, desc)),
+                            Err(desc) => {
+                                error!("Event {}\n{}", ev_name, desc);
+                                RepaintMode::Nothing
+                            }
                         };
+                        stats.event(&call_reapint_mode);
                         repaint_mode = repaint_mode.join(call_reapint_mode);
                     }
                 } else {
@@ -196,6 +218,7 @@ fn call_redraw_handler(
         }
     }
 
+    stats.commit(&repaint_mode);
     ui.on_redraw(&repaint_mode);
     Ok(())
 }
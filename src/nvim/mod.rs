@@ -1,5 +1,6 @@
 mod client;
 mod handler;
+mod redraw_batch;
 mod redraw_handler;
 mod repaint_mode;
 mod ext;
@@ -7,19 +8,24 @@ mod ext;
 pub use self::redraw_handler::{CompleteItem, NvimCommand};
 pub use self::repaint_mode::RepaintMode;
 pub use self::client::{NeovimClient, NeovimClientAsync, NeovimRef};
-pub use self::ext::ErrorReport;
+pub use self::ext::{on_ui_thread, ErrorReport};
 pub use self::handler::NvimHandler;
 
 use std::error;
 use std::fmt;
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::result;
+use std::thread;
 use std::time::Duration;
 
-use neovim_lib::{Neovim, NeovimApi, Session, UiAttachOptions};
+use neovim_lib::{Neovim, NeovimApi, Session, Value};
 
 use crate::nvim_config::NvimConfig;
+use crate::remote::RemoteProfile;
+use crate::sessions;
 
 #[derive(Debug)]
 pub struct NvimInitError {
@@ -48,6 +54,16 @@ impl NvimInitError {
         }
     }
 
+    pub fn new_connect<E>(addr: &str, error: E) -> NvimInitError
+    where
+        E: Into<Box<dyn error::Error>>,
+    {
+        NvimInitError {
+            cmd: Some(format!("connect to {}", addr)),
+            source: error.into(),
+        }
+    }
+
     pub fn source(&self) -> String {
         format!("{}", self.source)
     }
@@ -85,13 +101,86 @@ pub fn start(
     timeout: Option<Duration>,
     args_for_neovim: Vec<String>,
 ) -> result::Result<Neovim, NvimInitError> {
+    let mut cmd = nvim_cmd(nvim_bin_path, "--embed", args_for_neovim);
+
+    let session = Session::new_child_cmd(&mut cmd);
+
+    let mut session = match session {
+        Err(e) => return Err(NvimInitError::new(&cmd, e)),
+        Ok(s) => s,
+    };
+
+    session.set_timeout(timeout.unwrap_or(Duration::from_millis(10_000)));
+
+    let mut nvim = Neovim::new(session);
+
+    nvim.session.start_event_loop_handler(handler);
+
+    Ok(nvim)
+}
+
+/// Start embedded nvim on remote host through ssh
+///
+/// Gui runtime is not available on remote host, so shim
+/// source is sent to nvim after start
+pub fn start_remote(
+    handler: NvimHandler,
+    profile: &RemoteProfile,
+    timeout: Option<Duration>,
+    args_for_neovim: Vec<String>,
+) -> result::Result<Neovim, NvimInitError> {
+    let mut cmd = profile.nvim_cmd(&args_for_neovim);
+    cmd.stderr(Stdio::inherit());
+
+    #[cfg(target_os = "windows")]
+    set_windows_creation_flags(&mut cmd);
+
+    let mut session = Session::new_child_cmd(&mut cmd).map_err(|e| NvimInitError::new(&cmd, e))?;
+    session.set_timeout(timeout.unwrap_or(Duration::from_millis(10_000)));
+
+    let mut nvim = Neovim::new(session);
+    nvim.session.start_event_loop_handler(handler);
+
+    let shim_path = Path::new(&runtime_path()).join("plugin/nvim_gui_shim.vim");
+    let shim = fs::read_to_string(&shim_path).map_err(NvimInitError::new_post_init)?;
+    nvim.session
+        .call("nvim_exec", vec![Value::from(shim), Value::from(false)])
+        .map_err(|e| NvimInitError::new_post_init(e.to_string()))?;
+
+    Ok(nvim)
+}
+
+/// Start nvim server listening on given socket, server is not
+/// bound to ui and keeps running when ui is closed.
+/// Returns when server accepts connections
+pub fn start_server(
+    nvim_bin_path: Option<&String>,
+    socket: &Path,
+    args_for_neovim: Vec<String>,
+    timeout: Duration,
+) -> result::Result<(), NvimInitError> {
+    sessions::remove_dead_socket(socket).map_err(NvimInitError::new_post_init)?;
+
+    let mut cmd = nvim_cmd(nvim_bin_path, "--headless", args_for_neovim);
+    cmd.arg("--listen").arg(socket).stdin(Stdio::null()).stdout(Stdio::null());
+
+    let mut child = cmd.spawn().map_err(|e| NvimInitError::new(&cmd, e))?;
+    let started = sessions::wait_alive(&mut child, socket, timeout);
+
+    // reap server when it quits
+    thread::spawn(move || child.wait());
+
+    started.map_err(|e| NvimInitError::new(&cmd, e))
+}
+
+fn nvim_cmd(nvim_bin_path: Option<&String>, mode: &str, args_for_neovim: Vec<String>) -> Command {
     let mut cmd = if let Some(path) = nvim_bin_path {
         Command::new(path)
     } else {
         Command::new("nvim")
     };
 
-    cmd.arg("--embed")
+    cmd.arg(mode)
         .arg("--cmd")
         .arg("set termguicolors")
         .arg("--cmd")
@@ -101,15 +190,10 @@ pub fn start(
     #[cfg(target_os = "windows")]
     set_windows_creation_flags(&mut cmd);
 
-    if let Ok(runtime_path) = env::var("NVIM_GTK_RUNTIME_PATH") {
-        cmd.arg("--cmd")
-            .arg(format!("let &rtp.=',{}'", runtime_path));
-    } else if let Some(prefix) = option_env!("PREFIX") {
-        cmd.arg("--cmd")
-            .arg(format!("let &rtp.=',{}/share/nvim-gtk/runtime'", prefix));
-    } else {
-        cmd.arg("--cmd").arg("let &rtp.=',runtime'");
-    }
+    cmd.arg("--cmd").arg(format!(
+        "let &rtp.=',{}'",
+        escape_rtp_entry(&runtime_path()).replace('\'', "''")
+    ));
 
     if let Some(nvim_config) = NvimConfig::config_path() {
         if let Some(path) = nvim_config.to_str() {
@@ -121,39 +205,158 @@ pub fn start(
         cmd.arg(arg);
     }
 
-    let session = Session::new_child_cmd(&mut cmd);
+    cmd
+}
 
-    let mut session = match session {
-        Err(e) => return Err(NvimInitError::new(&cmd, e)),
-        Ok(s) => s,
+/// Runtime path with gui shim plugin, relative path is resolved,
+/// so it is valid for server started in other directory
+fn runtime_path() -> String {
+    let runtime_path = if let Ok(runtime_path) = env::var("NVIM_GTK_RUNTIME_PATH") {
+        runtime_path
+    } else if let Some(prefix) = option_env!("PREFIX") {
+        format!("{}/share/nvim-gtk/runtime", prefix)
+    } else {
+        "runtime".to_owned()
+    };
+
+    fs::canonicalize(&runtime_path)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or(runtime_path)
+}
+
+/// Escape path for `'runtimepath'`, commas separate entries there
+fn escape_rtp_entry(path: &str) -> String {
+    path.replace(',', "\\,")
+}
+
+/// Address of running nvim, same as given to `nvim --listen`
+#[derive(Clone, PartialEq, Debug)]
+pub enum ServerAddress {
+    Tcp(String),
+    Socket(PathBuf),
+}
+
+impl ServerAddress {
+    /// `host:port` is tcp address, anything else is socket path
+    pub fn parse(addr: &str) -> ServerAddress {
+        let is_tcp = match addr.rfind(':') {
+            Some(idx) => {
+                !addr.contains('/') && !addr.contains('\\') && addr[idx + 1..].parse::<u16>().is_ok()
+            }
+            None => false,
+        };
+
+        if is_tcp {
+            ServerAddress::Tcp(addr.to_owned())
+        } else {
+            ServerAddress::Socket(PathBuf::from(addr))
+        }
+    }
+}
+
+/// Connect to nvim started with `--listen`, server is not owned
+/// by ui, so it keeps running after ui detach
+pub fn connect(
+    handler: NvimHandler,
+    addr: &str,
+    timeout: Option<Duration>,
+) -> result::Result<Neovim, NvimInitError> {
+    let session = match ServerAddress::parse(addr) {
+        ServerAddress::Tcp(addr) => Session::new_tcp(&addr),
+        #[cfg(unix)]
+        ServerAddress::Socket(path) => Session::new_unix_socket(path),
+        #[cfg(not(unix))]
+        ServerAddress::Socket(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "Socket address is not supported on this platform",
+        )),
     };
 
+    let mut session = session.map_err(|e| NvimInitError::new_connect(addr, e))?;
     session.set_timeout(timeout.unwrap_or(Duration::from_millis(10_000)));
 
     let mut nvim = Neovim::new(session);
-
     nvim.session.start_event_loop_handler(handler);
 
+    // gui runtime is not known to server, so shim is loaded here,
+    // shim notifies this channel instead of embedded nvim channel 1
+    let channel = nvim
+        .get_api_info()
+        .map_err(NvimInitError::new_post_init)?
+        .first()
+        .and_then(Value::as_i64)
+        .ok_or_else(|| NvimInitError::new_post_init("Can't get channel id"))?;
+
+    nvim.set_var("GtkGuiChannel", Value::from(channel))
+        .map_err(NvimInitError::new_post_init)?;
+
+    let rtp = runtime_path();
+    let runtime_paths = nvim
+        .list_runtime_paths()
+        .map_err(NvimInitError::new_post_init)?;
+    if !runtime_paths.contains(&rtp) {
+        let rtp_option = nvim
+            .get_option("runtimepath")
+            .map_err(NvimInitError::new_post_init)?;
+        let rtp_option = format!(
+            "{},{}",
+            rtp_option.as_str().unwrap_or_default(),
+            escape_rtp_entry(&rtp)
+        );
+        NeovimApi::set_option(&mut nvim, "runtimepath", Value::from(rtp_option))
+            .map_err(NvimInitError::new_post_init)?;
+    }
+
+    nvim.command("runtime! plugin/nvim_gui_shim.vim")
+        .map_err(NvimInitError::new_post_init)?;
+
     Ok(nvim)
 }
 
+/// Attach ui with given ui extensions enabled
+///
+/// `UiAttachOptions` from neovim-lib does not know about
+/// newer extensions like ext_multigrid, so options map is built here
+fn ui_attach(
+    nvim: &mut Neovim,
+    cols: i64,
+    rows: i64,
+    extensions: &[&str],
+) -> result::Result<(), String> {
+    let options = extensions
+        .iter()
+        .map(|ext| (Value::from(*ext), Value::from(true)))
+        .collect();
+
+    nvim.session
+        .call(
+            "nvim_ui_attach",
+            vec![Value::from(cols), Value::from(rows), Value::Map(options)],
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
 pub fn post_start_init(
     nvim: NeovimClientAsync,
     cols: i64,
     rows: i64,
     input_data: Option<String>,
+    ext_messages: bool,
 ) -> result::Result<(), NvimInitError> {
-    nvim.borrow()
-        .unwrap()
-        .ui_attach(
-            cols,
-            rows,
-            UiAttachOptions::new()
-                .set_popupmenu_external(true)
-                .set_tabline_external(true)
-                .set_linegrid_external(true)
-                .set_hlstate_external(true)
-        )
+    let mut extensions = vec![
+        "ext_popupmenu",
+        "ext_tabline",
+        "ext_linegrid",
+        "ext_hlstate",
+        "ext_multigrid",
+    ];
+    if ext_messages {
+        // nvim turns ext_cmdline on together with ext_messages
+        extensions.extend_from_slice(&["ext_cmdline", "ext_messages"]);
+    }
+
+    ui_attach(&mut nvim.borrow().unwrap(), cols, rows, &extensions)
         .map_err(NvimInitError::new_post_init)?;
 
     nvim.borrow()
@@ -178,3 +381,33 @@ pub fn post_start_init(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_address() {
+        assert_eq!(
+            ServerAddress::Tcp("127.0.0.1:6666".to_owned()),
+            ServerAddress::parse("127.0.0.1:6666")
+        );
+        assert_eq!(
+            ServerAddress::Tcp("localhost:6666".to_owned()),
+            ServerAddress::parse("localhost:6666")
+        );
+        assert_eq!(
+            ServerAddress::Socket(PathBuf::from("/tmp/nvim.sock")),
+            ServerAddress::parse("/tmp/nvim.sock")
+        );
+        assert_eq!(
+            ServerAddress::Socket(PathBuf::from("nvim.sock")),
+            ServerAddress::parse("nvim.sock")
+        );
+    }
+    #[test]
+    fn test_escape_rtp_entry() {
+        assert_eq!("/usr/share/runtime", escape_rtp_entry("/usr/share/runtime"));
+        assert_eq!("/home/a\\,b/runtime", escape_rtp_entry("/home/a,b/runtime"));
+    }
+}
@@ -0,0 +1,166 @@
+//! Redraw events are staged until nvim `flush` event,
+//! so ui never paints partially applied batch.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use neovim_lib::Value;
+
+use super::repaint_mode::RepaintMode;
+
+/// Events of redraw notifications received after last `flush`
+pub struct RedrawBatch {
+    events: Vec<Value>,
+}
+
+impl RedrawBatch {
+    pub fn new() -> Self {
+        RedrawBatch { events: vec![] }
+    }
+
+    /// Stage events of redraw notification
+    ///
+    /// Returns events up to last `flush` as one batch, `flush`
+    /// events are removed. Events after it stay staged.
+    pub fn push(&mut self, params: Vec<Value>) -> Option<Vec<Value>> {
+        let mut complete = None;
+        for ev in params {
+            if is_flush(&ev) {
+                complete = Some(self.events.len());
+            } else {
+                self.events.push(ev);
+            }
+        }
+
+        complete.map(|len| {
+            let rest = self.events.split_off(len);
+            std::mem::replace(&mut self.events, rest)
+        })
+    }
+}
+
+fn is_flush(ev: &Value) -> bool {
+    ev.as_array()
+        .and_then(|ev| ev.first())
+        .and_then(Value::as_str)
+        == Some("flush")
+}
+
+/// Counters of redraw coalescing, `:NGRedrawStats` shows them
+#[derive(Default)]
+pub struct RedrawStats {
+    // redraw notifications received
+    notifications: AtomicUsize,
+    // batches committed on flush
+    batches: AtomicUsize,
+    // events applied
+    events: AtomicUsize,
+    // repaint areas returned by events
+    areas: AtomicUsize,
+    // repaint areas queued after merge across batch
+    merged_areas: AtomicUsize,
+    // batches that repaint whole window
+    full_repaints: AtomicUsize,
+}
+
+impl RedrawStats {
+    pub fn notification(&self) {
+        self.notifications.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Event applied with given repaint mode
+    pub fn event(&self, mode: &RepaintMode) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+        self.areas.fetch_add(area_count(mode), Ordering::Relaxed);
+    }
+
+    /// Batch committed with merged repaint mode
+    pub fn commit(&self, mode: &RepaintMode) {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        match *mode {
+            RepaintMode::All => {
+                self.full_repaints.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {
+                self.merged_areas.fetch_add(area_count(mode), Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let counter = |name: &str, counter: &AtomicUsize| {
+            (
+                Value::from(name),
+                Value::from(counter.load(Ordering::Relaxed) as u64),
+            )
+        };
+
+        Value::Map(vec![
+            counter("notifications", &self.notifications),
+            counter("batches", &self.batches),
+            counter("events", &self.events),
+            counter("areas", &self.areas),
+            counter("merged_areas", &self.merged_areas),
+            counter("full_repaints", &self.full_repaints),
+        ])
+    }
+}
+
+fn area_count(mode: &RepaintMode) -> usize {
+    match *mode {
+        RepaintMode::Nothing | RepaintMode::All => 0,
+        RepaintMode::Area(_) => 1,
+        RepaintMode::AreaList(ref list) => list.list.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ui_model::ModelRect;
+
+    fn event(name: &str) -> Value {
+        Value::Array(vec![Value::from(name), Value::Array(vec![])])
+    }
+
+    #[test]
+    fn test_staged_until_flush() {
+        let mut batch = RedrawBatch::new();
+
+        assert_eq!(None, batch.push(vec![event("grid_line"), event("grid_scroll")]));
+        assert_eq!(
+            Some(vec![
+                event("grid_line"),
+                event("grid_scroll"),
+                event("hl_attr_define"),
+            ]),
+            batch.push(vec![event("hl_attr_define"), event("flush"), event("grid_cursor_goto")])
+        );
+        assert_eq!(
+            Some(vec![event("grid_cursor_goto")]),
+            batch.push(vec![event("flush")])
+        );
+        assert_eq!(None, batch.push(vec![]));
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = RedrawStats::default();
+        stats.notification();
+        stats.event(&RepaintMode::Area(ModelRect::point(0, 0)));
+        stats.event(&RepaintMode::Area(ModelRect::point(0, 1)));
+        stats.commit(&RepaintMode::Area(ModelRect::new(0, 0, 0, 1)));
+
+        assert_eq!(
+            Value::Map(vec![
+                (Value::from("notifications"), Value::from(1)),
+                (Value::from("batches"), Value::from(1)),
+                (Value::from("events"), Value::from(2)),
+                (Value::from("areas"), Value::from(2)),
+                (Value::from("merged_areas"), Value::from(1)),
+                (Value::from("full_repaints"), Value::from(0)),
+            ]),
+            stats.to_value()
+        );
+    }
+}
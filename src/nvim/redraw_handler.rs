@@ -37,6 +37,13 @@ macro_rules! try_uint {
     };
 }
 
+macro_rules! try_float {
+    ($exp:expr) => {
+        $exp.as_f64()
+            .ok_or_else(|| "Can't convert argument to float".to_owned())?
+    };
+}
+
 macro_rules! try_bool {
     ($exp:expr) => {
         $exp.as_bool()
@@ -129,6 +136,7 @@ pub enum NvimCommand {
     SidebarShowHidden,
     SidebarHideHidden,
     SidebarToggleHidden,
+    Chdir(String),
 }
 
 pub fn call_gui_event(
@@ -139,6 +147,12 @@ pub fn call_gui_event(
     match method {
         "Font" => call!(ui->set_font(args: str)),
         "FontFeatures" => call!(ui->set_font_features(args: str)),
+        "FontFace" => call!(ui->set_font_face(args: str, str)),
+        "FontVariations" => call!(ui->set_font_variations(args: str)),
+        "FontWide" => call!(ui->set_font_wide(args: str)),
+        "FontFallback" => call!(ui->set_font_fallback(args: str)),
+        "LetterSpace" => call!(ui->set_letter_space(args: str)),
+        "Padding" => call!(ui->set_padding(args: str)),
         "Linespace" => call!(ui->set_line_space(args: str)),
         "Clipboard" => match try_str!(args[0]) {
             "Set" => match try_str!(args[1]) {
@@ -152,23 +166,23 @@ pub fn call_gui_event(
                 .nvim()
                 .ok_or_else(|| "Nvim not initialized".to_owned())
                 .and_then(|mut nvim| {
-                    nvim.set_option(UiOption::ExtPopupmenu(try_uint!(args[1]) == 1))
+                    nvim.set_ui_option(UiOption::ExtPopupmenu(try_uint!(args[1]) == 1))
                         .map_err(|e| e.to_string())
                 })?,
             "Tabline" => ui
                 .nvim()
                 .ok_or_else(|| "Nvim not initialized".to_owned())
                 .and_then(|mut nvim| {
-                    nvim.set_option(UiOption::ExtTabline(try_uint!(args[1]) == 1))
+                    nvim.set_ui_option(UiOption::ExtTabline(try_uint!(args[1]) == 1))
                         .map_err(|e| e.to_string())
                 })?,
             "Cmdline" => ui
                 .nvim()
                 .ok_or_else(|| "Nvim not initialized".to_owned())
                 .and_then(|mut nvim| {
-                    nvim.set_option(UiOption::ExtCmdline(try_uint!(args[1]) == 1))
+                    nvim.set_ui_option(UiOption::ExtCmdline(try_uint!(args[1]) == 1))
                         .map_err(|e| e.to_string())?;
-                    nvim.set_option(UiOption::ExtWildmenu(try_uint!(args[1]) == 1))
+                    nvim.set_ui_option(UiOption::ExtWildmenu(try_uint!(args[1]) == 1))
                         .map_err(|e| e.to_string())
                 })?,
             opt => error!("Unknown option {}", opt),
@@ -206,6 +220,51 @@ pub fn call_gui_event(
                         };
                     ui.set_cursor_blink(blink_count);
                 }
+                "CursorAnimation" => {
+                    let duration = args
+                        .get(1)
+                        .and_then(Value::as_str)
+                        .and_then(|d| d.parse::<u32>().ok())
+                        .unwrap_or(0);
+                    let trail = matches!(
+                        args.get(2).and_then(Value::as_str),
+                        Some("trail") | Some("on")
+                    );
+                    ui.set_cursor_animation(duration, trail);
+                }
+                "UiEvent" => {
+                    let event = args.get(1).and_then(Value::as_str).unwrap_or("");
+                    let enabled =
+                        !matches!(args.get(2).and_then(Value::as_str), Some("0") | Some("off"));
+                    ui.set_ui_event(event, enabled);
+                }
+                "ColorSwatches" => {
+                    let enabled =
+                        !matches!(args.get(1).and_then(Value::as_str), Some("0") | Some("off"));
+                    ui.set_color_swatches(enabled);
+                }
+                "ColorPicker" => {
+                    let buf = args.get(1).and_then(Value::as_i64).unwrap_or(0);
+                    let row = args.get(2).and_then(Value::as_i64);
+                    let col = args.get(3).and_then(Value::as_u64);
+                    let line = args.get(4).and_then(Value::as_str);
+
+                    if let (Some(row), Some(col), Some(line)) = (row, col, line) {
+                        ui.pick_color(buf, row, col as usize, line);
+                    } else {
+                        error!("Wrong ColorPicker arguments {:?}", args);
+                    }
+                }
+                "BackgroundImage" => {
+                    let path = args.get(1).and_then(Value::as_str).unwrap_or("");
+                    let mode = args.get(2).and_then(Value::as_str).unwrap_or("scaled");
+                    let opacity = args
+                        .get(3)
+                        .and_then(Value::as_str)
+                        .and_then(|o| o.parse::<f64>().ok())
+                        .unwrap_or(1.0);
+                    ui.set_background_image(path, mode, opacity);
+                }
                 "ToggleFullscreen" => ui.on_command(NvimCommand::ToggleFullscreen),
                 "Fullscreen" => ui.on_command(NvimCommand::Fullscreen),
                 "Unfullscreen" => ui.on_command(NvimCommand::Unfullscreen),
@@ -295,6 +354,19 @@ pub fn call(
         "grid_cursor_goto" => call!(ui->grid_cursor_goto(args: uint, uint, uint)),
         "grid_scroll" => call!(ui->grid_scroll(args: uint, uint, uint, uint, uint, int, int)),
         "grid_resize" => call!(ui->grid_resize(args: uint, uint, uint)),
+        "win_pos" => call!(ui->win_pos(args: uint, val, uint, uint, uint, uint)),
+        "win_float_pos" => ui.win_float_pos(
+            try_uint!(args[0]),
+            try_str!(args[2]),
+            try_uint!(args[3]),
+            try_float!(args[4]),
+            try_float!(args[5]),
+            args.get(7).and_then(Value::as_u64),
+        ),
+        "win_hide" => call!(ui->win_hide(args: uint)),
+        "win_close" => call!(ui->win_close(args: uint)),
+        "win_viewport" | "win_external_pos" => RepaintMode::Nothing,
+        "msg_set_pos" => call!(ui->msg_set_pos(args: uint, uint, bool, str)),
         "default_colors_set" => call!(ui->default_colors_set(args: int, int, int, int, int)),
         "hl_attr_define" => call!(ui->hl_attr_define(args: uint, ext, val_ref, ext)),
         "mode_change" => call!(ui->on_mode_change(args: str, uint)),
@@ -302,6 +374,10 @@ pub fn call(
         "mouse_off" => ui.on_mouse(false),
         "busy_start" => ui.on_busy(true),
         "busy_stop" => ui.on_busy(false),
+        "bell" => ui.on_bell(),
+        "visual_bell" => ui.on_visual_bell(),
+        "suspend" => ui.on_suspend(),
+        "chdir" => call!(ui->on_chdir(args: str)),
         "popupmenu_show" => {
             let menu_items = map_array!(args[0], "Error get menu list array", |item| map_array!(
                 item,
@@ -314,6 +390,7 @@ pub fn call(
                 try_int!(args[1]),
                 try_uint!(args[2]),
                 try_uint!(args[3]),
+                args.get(4).and_then(Value::as_i64),
             )
         }
         "popupmenu_hide" => ui.popupmenu_hide(),
@@ -348,6 +425,13 @@ pub fn call(
         "wildmenu_show" => call!(ui->wildmenu_show(args: ext)),
         "wildmenu_hide" => ui.wildmenu_hide(),
         "wildmenu_select" => call!(ui->wildmenu_select(args: int)),
+        "msg_show" => call!(ui->msg_show(args: str, ext, bool)),
+        "msg_clear" => ui.msg_clear(),
+        "msg_showmode" => call!(ui->msg_showmode(args: ext)),
+        "msg_showcmd" => call!(ui->msg_showcmd(args: ext)),
+        "msg_ruler" => call!(ui->msg_ruler(args: ext)),
+        "msg_history_show" => call!(ui->msg_history_show(args: ext)),
+        "msg_history_clear" => ui.msg_history_clear(),
         _ => {
             warn!("Event {}({:?})", method, args);
             RepaintMode::Nothing
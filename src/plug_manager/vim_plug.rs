@@ -24,6 +24,8 @@ impl Manager {
 
     pub fn get_plugs(&self) -> Result<Box<[VimPlugInfo]>, String> {
         if let Some(mut nvim) = self.nvim() {
+            // plug manager dialog is built from this list, so it waits for answer
+            let nvim = nvim.blocking();
             let g_plugs = nvim.eval("g:plugs").map_err(|e| {
                 format!("Can't retrive g:plugs map: {}", e)
             })?;
@@ -68,7 +70,7 @@ impl Manager {
 
     pub fn is_loaded(&self) -> bool {
         if let Some(mut nvim) = self.nvim() {
-            let loaded_plug = nvim.eval("exists('g:loaded_plug')");
+            let loaded_plug = nvim.blocking().eval("exists('g:loaded_plug')");
             loaded_plug
                 .ok_and_report()
                 .and_then(|loaded_plug| loaded_plug.as_i64())
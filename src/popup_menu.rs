@@ -9,7 +9,7 @@ use gtk;
 use gtk::prelude::*;
 use pango;
 
-use neovim_lib::{Neovim, NeovimApi};
+use neovim_lib::{Neovim, NeovimApiAsync};
 
 use crate::highlight::HighlightMap;
 use crate::input;
@@ -24,6 +24,7 @@ struct State {
     tree: gtk::TreeView,
     scroll: gtk::ScrolledWindow,
     css_provider: gtk::CssProvider,
+    popover_css_provider: gtk::CssProvider,
     info_label: gtk::Label,
     word_column: gtk::TreeViewColumn,
     kind_column: gtk::TreeViewColumn,
@@ -79,6 +80,7 @@ impl State {
             renderer,
             scroll,
             css_provider,
+            popover_css_provider: gtk::CssProvider::new(),
             info_label,
             word_column,
             kind_column,
@@ -155,7 +157,8 @@ impl State {
         self.renderer
             .set_property_foreground_rgba(Some(&hl.pmenu_fg().into()));
 
-        update_css(&self.css_provider, hl);
+        update_css(&self.css_provider, hl, ctx.blend);
+        update_popover_css(&self.popover_css_provider, hl, ctx.blend);
 
         let list_store = gtk::ListStore::new(&[gtk::Type::String; 4]);
         let all_column_ids: Vec<u32> = (0..4).map(|i| i as u32).collect();
@@ -223,6 +226,10 @@ impl PopupMenu {
         let state = State::new();
         let popover = gtk::Popover::new(Some(drawing));
         popover.set_modal(false);
+        popover.get_style_context().add_provider(
+            &state.popover_css_provider,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
 
         let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
@@ -318,6 +325,8 @@ pub struct PopupMenuContext<'a> {
     pub width: i32,
     pub height: i32,
     pub max_width: i32,
+    /// pumblend value
+    pub blend: u8,
 }
 
 pub fn tree_button_press(
@@ -360,7 +369,7 @@ pub fn tree_button_press(
                 .collect()
         };
 
-        nvim.input(&apply_command).report_err();
+        nvim.input_async(&apply_command).cb(|r| r.report_err()).call();
     }
 }
 
@@ -388,9 +397,14 @@ fn on_treeview_allocate(
     }));
 }
 
-pub fn update_css(css_provider: &gtk::CssProvider, hl: &HighlightMap) {
+fn blend_to_alpha(blend: u8) -> f64 {
+    1.0 - f64::from(blend.min(100)) / 100.0
+}
+
+pub fn update_css(css_provider: &gtk::CssProvider, hl: &HighlightMap, blend: u8) {
     let bg = hl.pmenu_bg_sel();
     let fg = hl.pmenu_fg_sel();
+    let alpha = blend_to_alpha(blend);
 
     if let Err(e) = gtk::CssProviderExt::load_from_data(
         css_provider,
@@ -398,8 +412,21 @@ pub fn update_css(css_provider: &gtk::CssProvider, hl: &HighlightMap) {
             ".view :selected {{ color: {}; background-color: {};}}\n
                 .view {{ background-color: {}; }}",
             fg.to_hex(),
-            bg.to_hex(),
-            hl.pmenu_bg().to_hex(),
+            bg.to_rgba(alpha),
+            hl.pmenu_bg().to_rgba(alpha),
+        )
+        .as_bytes(),
+    ) {
+        error!("Can't update css {}", e)
+    };
+}
+
+fn update_popover_css(css_provider: &gtk::CssProvider, hl: &HighlightMap, blend: u8) {
+    if let Err(e) = gtk::CssProviderExt::load_from_data(
+        css_provider,
+        format!(
+            "popover {{ background-color: {}; }}",
+            hl.pmenu_bg().to_rgba(blend_to_alpha(blend)),
         )
         .as_bytes(),
     ) {
@@ -12,8 +12,7 @@ use std::rc::Rc;
 use std::ops::Deref;
 use std::cell::RefCell;
 
-use neovim_lib::NeovimApi;
-use neovim_lib::NeovimApiAsync;
+use neovim_lib::{CallError, NeovimApiAsync, Value};
 use crate::nvim;
 use crate::nvim::ErrorReport;
 use crate::color::Color;
@@ -62,6 +61,54 @@ impl State {
     }
 }
 
+/// Current buffer content shown in preview
+#[derive(Debug, PartialEq)]
+struct PreviewContent {
+    lines: String,
+    file_name: String,
+    line_number: usize,
+}
+
+impl PreviewContent {
+    /// Requests of `nvim_call_atomic`, buffer 0 is current buffer
+    fn calls() -> Vec<Value> {
+        let call = |method: &str, args: Vec<Value>| Value::Array(vec![Value::from(method), Value::Array(args)]);
+        vec![
+            call("nvim_buf_get_option", vec![Value::from(0), Value::from("buflisted")]),
+            call("nvim_buf_get_lines", vec![Value::from(0), Value::from(0), Value::from(-1), Value::from(true)]),
+            call("nvim_buf_get_name", vec![Value::from(0)]),
+            call("nvim_call_function", vec![Value::from("getcwd"), Value::Array(vec![])]),
+            call("nvim_eval", vec![Value::from("line('.')")]),
+        ]
+    }
+
+    /// Content from `nvim_call_atomic` results, unlisted buffer is not shown
+    fn parse(res: &[Value]) -> Option<Self> {
+        let results = res.first()?.as_array()?;
+        if results.len() < 5 || !results[0].as_bool().unwrap_or(false) {
+            return None;
+        }
+
+        let lines = results[1]
+            .as_array()?
+            .iter()
+            .map(|line| line.as_str().unwrap_or(""))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let file_name = format!("file://{}", match results[2].as_str()? {
+            "" => format!("{}/temp", results[3].as_str()?),
+            path => path.to_string(),
+        });
+        let line_number = results[4].as_u64().unwrap_or(1).saturating_sub(1) as usize;
+
+        Some(PreviewContent {
+            lines,
+            file_name,
+            line_number,
+        })
+    }
+}
+
 pub struct Preview {
     container: gtk::Box,
     webview: WebView,
@@ -170,56 +217,42 @@ impl Preview {
     }
 
     pub fn refresh(&self) {
-        if ! self.state.borrow().should_refresh {
+        let state = self.state.borrow();
+        if !state.should_refresh {
             return
         }
 
-        let state = self.state.borrow();
-        let mut nvim = state.nvim.as_ref().unwrap().nvim().unwrap();
-        let buffer = nvim.get_current_buf().unwrap();
+        let mut nvim = match state.nvim.as_ref().and_then(|nvim| nvim.nvim()) {
+            Some(nvim) => nvim,
+            None => return,
+        };
 
-        if let Ok(listed) = buffer.get_option(&mut nvim, "buflisted") {
-            if ! listed.as_bool().unwrap_or(false) {
-                return
-            }
-        }
+        let webview = self.webview.clone();
+        let state_ref = self.state.clone();
+        nvim.call_atomic_async(PreviewContent::calls())
+            .cb(nvim::on_ui_thread(move |res: Result<Vec<Value>, CallError>| {
+                if let Some(content) = res.ok_and_report().and_then(|res| PreviewContent::parse(&res)) {
+                    Preview::load(&webview, &state_ref.borrow(), &content);
+                }
+            }))
+            .call();
+    }
 
-        let lines = buffer.get_lines(&mut nvim, 0, -1, true).unwrap();
-        let lines = lines.join("\n");
-        let file_name = format!("file://{}", match buffer.get_name(&mut nvim).unwrap().as_str() {
-            "" => {
-                format!("{}/temp", nvim.eval("getcwd()").unwrap().as_str().unwrap())
-            },
-            path => path.to_string()
-        });
-        let window = nvim.get_current_win().unwrap();
-        let cursor = window.get_cursor(&mut nvim).unwrap();
-        let line_number = {
-            let num = Preview::offset_into_line(&lines, buffer.get_offset(&mut nvim, cursor.0).unwrap_or(1) as usize);
-            if num == 0 { 0 } else { num - 1 }
-        };
+    fn load(webview: &WebView, state: &State, content: &PreviewContent) {
+        let PreviewContent { ref lines, ref file_name, line_number } = *content;
         match &state.prev_type {
-            PreviewType::HTML => self.webview.load_html(&lines, Some(&file_name)),
-            PreviewType::Markdown => self.webview.load_html(&self.render(&lines, line_number), Some(&file_name)),
-            PreviewType::Plain => self.webview.load_html(&self.render(format!("```\n{}\n```", &lines).as_str(), line_number), None),
+            PreviewType::HTML => webview.load_html(lines, Some(file_name)),
+            PreviewType::Markdown => webview.load_html(&Preview::render(state, lines, line_number), Some(file_name)),
+            PreviewType::Plain => webview.load_html(&Preview::render(state, format!("```\n{}\n```", lines).as_str(), line_number), None),
         }
     }
 
-    pub fn refresh_scroll(&self, line_number: usize) {
-        if ! self.state.borrow().should_refresh {
+    /// Scroll to line of current buffer, `listed` is `buflisted` of it
+    pub fn refresh_scroll(&self, line_number: usize, listed: bool) {
+        if ! self.state.borrow().should_refresh || ! listed {
             return
         }
 
-        let state = self.state.borrow();
-        let mut nvim = state.nvim.as_ref().unwrap().nvim().unwrap();
-        let buffer = nvim.get_current_buf().unwrap();
-
-        if let Ok(listed) = buffer.get_option(&mut nvim, "buflisted") {
-            if ! listed.as_bool().unwrap_or(false) {
-                return
-            }
-        }
-
         let scroll = format!(
             r#"
             for (i = {}; i >= 0; i--) {{
@@ -260,8 +293,7 @@ impl Preview {
         }
     }
 
-    pub fn render(&self, markdown: &str, line: usize) -> String {
-        let state = self.state.borrow();
+    fn render(state: &State, markdown: &str, line: usize) -> String {
         let scroll = format!(
             r#"
             document.getElementById('line_{}').scrollIntoView();
@@ -376,3 +408,38 @@ impl Deref for Preview {
         &self.container
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_content() {
+        let results = |listed, name: &str| {
+            vec![
+                Value::Array(vec![
+                    Value::from(listed),
+                    Value::Array(vec![Value::from("# Title"), Value::from("text")]),
+                    Value::from(name),
+                    Value::from("/home/user"),
+                    Value::from(2),
+                ]),
+                Value::Nil,
+            ]
+        };
+
+        assert_eq!(
+            Some(PreviewContent {
+                lines: "# Title\ntext".to_owned(),
+                file_name: "file:///home/user/temp".to_owned(),
+                line_number: 1,
+            }),
+            PreviewContent::parse(&results(true, ""))
+        );
+        assert_eq!(
+            "file:///tmp/a.md",
+            PreviewContent::parse(&results(true, "/tmp/a.md")).unwrap().file_name
+        );
+        assert_eq!(None, PreviewContent::parse(&results(false, "/tmp/a.md")));
+    }
+}
@@ -2,7 +2,9 @@ use std::cell::RefCell;
 use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::thread;
 
+use gio::prelude::*;
 use gtk;
 use gtk::prelude::*;
 use gtk::{
@@ -11,8 +13,9 @@ use gtk::{
 };
 use pango;
 
-use neovim_lib::{Neovim, NeovimApi, Value};
-use crate::nvim::ErrorReport;
+use neovim_lib::{CallError, NeovimApiAsync, Value};
+use crate::nvim::{on_ui_thread, ErrorReport};
+use crate::sessions::{self, ServerSession};
 use crate::shell::Shell;
 use crate::ui::UiMutex;
 use crate::misc;
@@ -58,6 +61,9 @@ pub struct Projects {
     tree: TreeView,
     scroll: ScrolledWindow,
     store: Option<EntryStore>,
+    search_box: gtk::Entry,
+    sessions_box: gtk::Box,
+    sessions_list: gtk::ListBox,
     name_renderer: CellRendererText,
     path_renderer: CellRendererText,
     toggle_renderer: CellRendererToggle,
@@ -74,6 +80,9 @@ impl Projects {
                 Option::<&gtk::Adjustment>::None,
             ),
             store: None,
+            search_box: gtk::Entry::new(),
+            sessions_box: gtk::Box::new(Orientation::Vertical, 5),
+            sessions_list: gtk::ListBox::new(),
             name_renderer: CellRendererText::new(),
             path_renderer: CellRendererText::new(),
             toggle_renderer: CellRendererToggle::new(),
@@ -92,7 +101,7 @@ impl Projects {
         let vbox = gtk::Box::new(Orientation::Vertical, 5);
         vbox.set_border_width(5);
 
-        let search_box = gtk::Entry::new();
+        let search_box = projects.search_box.clone();
         search_box.set_icon_from_icon_name(gtk::EntryIconPosition::Primary, Some("edit-find-symbolic"));
 
         vbox.pack_start(&search_box, false, true, 0);
@@ -109,6 +118,19 @@ impl Projects {
         let open_btn = gtk::Button::new_with_label("Other Documents…");
         vbox.pack_start(&open_btn, true, true, 5);
 
+        let server_btn = gtk::Button::new_with_label("Connect to Server…");
+        vbox.pack_start(&server_btn, true, true, 0);
+
+        let sessions_label = gtk::Label::new(None);
+        sessions_label.set_markup("<b>Running Sessions</b>");
+        sessions_label.set_halign(gtk::Align::Start);
+        projects.sessions_list.set_selection_mode(gtk::SelectionMode::None);
+        projects.sessions_box.pack_start(&sessions_label, false, true, 0);
+        projects
+            .sessions_box
+            .pack_start(&projects.sessions_list, false, true, 0);
+        vbox.pack_start(&projects.sessions_box, false, true, 5);
+
         vbox.show_all();
         projects.popup.add(&vbox);
 
@@ -166,6 +188,13 @@ impl Projects {
             popup.popdown();
         });
 
+        let prj_ref = projects.clone();
+        server_btn.connect_clicked(move |_| {
+            let popup = prj_ref.borrow().popup.clone();
+            popup.popdown();
+            prj_ref.borrow().show_connect_server_dlg();
+        });
+
         let prj_ref = projects.clone();
         projects
             .borrow()
@@ -271,21 +300,176 @@ impl Projects {
         dlg.destroy();
     }
 
-    pub fn show(&mut self) {
-        self.load_oldfiles();
+    /// Ask server address and open new window attached to it
+    fn show_connect_server_dlg(&self) {
+        let window = self
+            .popup
+            .get_toplevel()
+            .unwrap()
+            .downcast::<gtk::Window>()
+            .ok();
+        let dlg = gtk::Dialog::new_with_buttons(
+            Some("Connect to Server"),
+            window.as_ref(),
+            gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+            &[
+                ("_Connect", gtk::ResponseType::Ok),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        dlg.set_default_response(gtk::ResponseType::Ok);
+
+        let entry = gtk::Entry::new();
+        entry.set_placeholder_text(Some("host:port or socket path"));
+        entry.set_activates_default(true);
+        let content = dlg.get_content_area();
+        content.set_border_width(5);
+        content.pack_start(&entry, true, true, 0);
+        content.show_all();
 
-        self.popup.popup();
+        if dlg.run() == gtk::ResponseType::Ok {
+            let server = entry.get_text().map(|s| s.trim().to_owned()).unwrap_or_default();
+            if !server.is_empty() {
+                if let Some(app) = gio::Application::get_default() {
+                    app.activate_action("connect-server", Some(&server.to_variant()));
+                }
+            }
+        }
+        dlg.destroy();
     }
 
-    fn load_oldfiles(&mut self) {
-        let shell_borrow = self.shell.borrow();
-        let shell_state = shell_borrow.state.borrow_mut();
+    /// Show persistent sessions with running server
+    fn load_sessions(&self) {
+        for child in self.sessions_list.get_children() {
+            self.sessions_list.remove(&child);
+        }
+
+        let sessions = sessions::list();
+        for session in sessions.iter() {
+            self.sessions_list.add(&self.create_session_row(session));
+        }
+
+        if sessions.is_empty() {
+            self.sessions_box.hide();
+        } else {
+            self.sessions_box.show_all();
+        }
+    }
+
+    fn create_session_row(&self, session: &ServerSession) -> gtk::ListBoxRow {
+        let row = gtk::ListBoxRow::new();
+        let hbox = gtk::Box::new(Orientation::Horizontal, 5);
+        hbox.set_border_width(3);
+
+        let label = gtk::Label::new(Some(&session.project_name()));
+        label.set_tooltip_text(Some(&session.project.to_string_lossy()));
+        label.set_halign(gtk::Align::Start);
+        hbox.pack_start(&label, true, true, 0);
+
+        let attach_btn = gtk::Button::new_with_label("Attach");
+        let kill_btn = gtk::Button::new_with_label("Kill");
+        hbox.pack_start(&attach_btn, false, true, 0);
+        hbox.pack_start(&kill_btn, false, true, 0);
+        row.add(&hbox);
+
+        let popup = self.popup.clone();
+        let socket = session.socket.to_string_lossy().into_owned();
+        attach_btn.connect_clicked(move |_| {
+            popup.popdown();
+            if let Some(app) = gio::Application::get_default() {
+                app.activate_action("connect-server", Some(&socket.to_variant()));
+            }
+        });
+
+        let session = session.clone();
+        let sessions_list = self.sessions_list.clone();
+        let sessions_box = self.sessions_box.clone();
+        let session_row = row.clone();
+        kill_btn.connect_clicked(move |btn| {
+            if !confirm_kill_session(btn, &session) {
+                return;
+            }
+
+            // server can take a while to quit, window is not blocked meanwhile
+            btn.set_sensitive(false);
+            let (btn, sessions_list, sessions_box, session_row) = (
+                btn.clone(),
+                sessions_list.clone(),
+                sessions_box.clone(),
+                session_row.clone(),
+            );
+            let project = session.project.clone();
+            let done = on_ui_thread(move |res: Result<(), String>| match res {
+                Ok(()) => {
+                    sessions_list.remove(&session_row);
+                    if sessions_list.get_children().is_empty() {
+                        sessions_box.hide();
+                    }
+                }
+                Err(err) => {
+                    error!("Can't kill session {}: {}", project.display(), err);
+                    btn.set_sensitive(true);
+                }
+            });
+
+            let session = session.clone();
+            thread::spawn(move || done(sessions::kill(&session)));
+        });
+
+        row
+    }
+
+    pub fn show(projects: &Arc<UiMutex<Projects>>) {
+        Projects::load_oldfiles(projects);
+
+        let projects = projects.borrow();
+        projects.load_sessions();
+        projects.popup.popup();
+    }
+
+    /// Entries are shown when nvim answers, popup is not blocked until then
+    fn load_oldfiles(projects: &Arc<UiMutex<Projects>>) {
+        let prj = projects.borrow();
+        let shell_borrow = prj.shell.borrow();
+        let shell_state = shell_borrow.state.borrow();
 
         let nvim = shell_state.try_nvim();
         if let Some(mut nvim) = nvim {
-            let store = EntryStore::load(&mut nvim);
-            store.populate(&self.get_list_store(), None);
-            self.store = Some(store);
+            let projects = projects.clone();
+            nvim.eval_async("[getcwd(), v:oldfiles]")
+                .cb(on_ui_thread(move |res: Result<Value, CallError>| {
+                    let res = res.ok_and_report();
+                    let (cwd, old_files) = match res.as_ref().and_then(Value::as_array) {
+                        Some(res) => (
+                            res.first().and_then(Value::as_str),
+                            res.get(1).map(parse_old_files).unwrap_or_default(),
+                        ),
+                        None => (None, vec![]),
+                    };
+
+                    let mut projects = projects.borrow_mut();
+                    // popup closed before answer, store is already saved
+                    if !projects.popup.is_visible() {
+                        return;
+                    }
+                    let store = EntryStore::load(cwd, &old_files);
+                    let list_store = projects.get_list_store();
+                    list_store.clear();
+                    store.populate(&list_store, projects.search_box.get_text().as_ref());
+                    projects.store = Some(store);
+                }))
+                .call();
+        }
+    }
+
+    /// Update current directory entry of shown list
+    pub fn set_current_dir(&mut self, dir: &str) {
+        let list_store = self.get_list_store();
+        if let Some(ref mut store) = self.store {
+            store.set_current_dir(dir);
+
+            list_store.clear();
+            store.populate(&list_store, self.search_box.get_text().as_ref());
         }
     }
 
@@ -391,30 +575,45 @@ fn on_treeview_allocate(projects: Arc<UiMutex<Projects>>) {
     });
 }
 
-fn list_old_files(nvim: &mut Neovim) -> Vec<String> {
-    let oldfiles_var = nvim.get_vvar("oldfiles");
-
-    match oldfiles_var {
-        Ok(files) => {
-            if let Some(files) = files.as_array() {
-                files
-                    .iter()
-                    .map(Value::as_str)
-                    .filter(Option::is_some)
-                    .map(|path| path.unwrap().to_owned())
-                    .filter(|path| !path.starts_with("term:"))
-                    .collect()
-            } else {
-                vec![]
-            }
-        }
-        err @ Err(_) => {
-            err.report_err();
-            vec![]
-        }
+fn parse_old_files(files: &Value) -> Vec<String> {
+    if let Some(files) = files.as_array() {
+        files
+            .iter()
+            .map(Value::as_str)
+            .filter(Option::is_some)
+            .map(|path| path.unwrap().to_owned())
+            .filter(|path| !path.starts_with("term:"))
+            .collect()
+    } else {
+        vec![]
     }
 }
 
+fn confirm_kill_session(widget: &gtk::Button, session: &ServerSession) -> bool {
+    let window = widget
+        .get_toplevel()
+        .and_then(|w| w.downcast::<gtk::Window>().ok());
+    let dlg = gtk::MessageDialog::new(
+        window.as_ref(),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        &format!(
+            "Quit nvim of '{}'? Unsaved changes will be lost.",
+            session.project.display()
+        ),
+    );
+    dlg.add_buttons(&[
+        ("_Kill", gtk::ResponseType::Yes),
+        ("_Cancel", gtk::ResponseType::Cancel),
+    ]);
+
+    let res = dlg.run() == gtk::ResponseType::Yes;
+    dlg.destroy();
+
+    res
+}
+
 pub struct EntryStore {
     entries: Vec<Entry>,
     changed: bool,
@@ -425,32 +624,36 @@ impl EntryStore {
         self.entries.iter_mut().find(|e| e.project && e.uri == uri)
     }
 
-    pub fn load(nvim: &mut Neovim) -> EntryStore {
+    pub fn load(cwd: Option<&str>, old_files: &[String]) -> EntryStore {
         let mut entries = Vec::new();
 
         for project in ProjectSettings::load().projects {
             entries.push(project.to_entry());
         }
 
-        match nvim.call_function("getcwd", vec![]) {
-            Ok(pwd) => {
-                if let Some(pwd) = pwd.as_str() {
-                    if entries.iter().find(|e| e.project && e.uri == pwd).is_none() {
-                        entries.insert(0, Entry::new_current_project(pwd));
-                    }
-                } else {
-                    error!("Error get current directory");
-                }
-            }
-            err @ Err(_) => err.report_err(),
+        let mut store = EntryStore {
+            entries,
+            changed: false,
+        };
+
+        if let Some(pwd) = cwd {
+            store.set_current_dir(pwd);
+        } else {
+            error!("Error get current directory");
         }
 
-        let old_files = list_old_files(nvim);
-        entries.extend(old_files.iter().map(|p| Entry::new_from_path(p)));
+        store
+            .entries
+            .extend(old_files.iter().map(|p| Entry::new_from_path(p)));
 
-        EntryStore {
-            entries,
-            changed: false,
+        store
+    }
+
+    /// Current directory is shown first, unless it is stored project
+    pub fn set_current_dir(&mut self, dir: &str) {
+        self.entries.retain(|e| !e.project || e.stored);
+        if self.entries.iter().find(|e| e.project && e.uri == dir).is_none() {
+            self.entries.insert(0, Entry::new_current_project(dir));
         }
     }
 
@@ -0,0 +1,194 @@
+//! Remote profiles: nvim embedded on other host through ssh
+//!
+//! Profiles are stored in `remotes.toml`, e.g.
+//!
+//! ```toml
+//! [[profiles]]
+//! name = "build"
+//! host = "user@build.example.com"
+//! ssh_args = ["-p", "2222"]
+//! nvim = "/opt/nvim/bin/nvim"
+//! ```
+//!
+//! ssh binary can be changed with `ssh` profile field or `NVIM_GTK_SSH`
+//! environment variable, so wrapper script can be used instead of real ssh.
+//! Remote command is the last argument, wrapper that runs it locally:
+//!
+//! ```sh
+//! #!/bin/sh
+//! for cmd; do :; done
+//! exec sh -c "$cmd"
+//! ```
+
+use std::env;
+use std::process::Command;
+
+use crate::settings::SettingsLoader;
+
+const SSH_ENV: &str = "NVIM_GTK_SSH";
+
+#[derive(Serialize, Deserialize, Default)]
+struct RemoteSettings {
+    #[serde(default)]
+    profiles: Vec<RemoteProfile>,
+}
+
+impl SettingsLoader for RemoteSettings {
+    const SETTINGS_FILE: &'static str = "remotes.toml";
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        toml::from_str(s).map_err(|e| format!("{}", e))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RemoteProfile {
+    name: String,
+    pub host: String,
+    // ssh binary, NVIM_GTK_SSH or `ssh` is used when not set
+    ssh: Option<String>,
+    #[serde(default)]
+    ssh_args: Vec<String>,
+    // nvim binary on remote host
+    nvim: Option<String>,
+}
+
+impl RemoteProfile {
+    fn new(host: &str) -> Self {
+        RemoteProfile {
+            name: host.to_owned(),
+            host: host.to_owned(),
+            ssh: None,
+            ssh_args: vec![],
+            nvim: None,
+        }
+    }
+
+    /// Stored profile with given name, not stored name is used as host
+    pub fn find(name: &str) -> Self {
+        let settings = RemoteSettings::load();
+        find_profile(settings.profiles, name)
+    }
+
+    /// Command that runs `nvim --embed` on remote host, nvim stdio
+    /// is forwarded by ssh
+    pub fn nvim_cmd(&self, args_for_neovim: &[String]) -> Command {
+        let ssh = self
+            .ssh
+            .clone()
+            .or_else(|| env::var(SSH_ENV).ok())
+            .unwrap_or_else(|| "ssh".to_owned());
+
+        let mut cmd = Command::new(ssh);
+        cmd.args(&self.ssh_args)
+            .arg(&self.host)
+            .arg("--")
+            .arg(self.remote_command_line(args_for_neovim));
+
+        cmd
+    }
+
+    /// ssh joins arguments into remote shell command line,
+    /// so nvim command is given as one quoted string
+    fn remote_command_line(&self, args_for_neovim: &[String]) -> String {
+        let nvim = self.nvim.as_deref().unwrap_or("nvim");
+        let remote_args = [nvim, "--embed", "--cmd", "set termguicolors"];
+
+        remote_args
+            .iter()
+            .cloned()
+            .chain(args_for_neovim.iter().map(String::as_str))
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn find_profile(profiles: Vec<RemoteProfile>, name: &str) -> RemoteProfile {
+    profiles
+        .into_iter()
+        .find(|profile| profile.name == name)
+        .unwrap_or_else(|| RemoteProfile::new(name))
+}
+
+/// Quote argument for POSIX shell
+fn shell_quote(arg: &str) -> String {
+    let is_safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@+".contains(c));
+
+    if is_safe {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!("--embed", shell_quote("--embed"));
+        assert_eq!("'set termguicolors'", shell_quote("set termguicolors"));
+        assert_eq!("'it'\\''s'", shell_quote("it's"));
+        assert_eq!("''", shell_quote(""));
+    }
+
+    #[test]
+    fn test_find_profile() {
+        let settings: RemoteSettings = RemoteSettings::from_str(
+            "[[profiles]]\nname = \"build\"\nhost = \"user@build\"\nssh_args = [\"-p\", \"2222\"]\n",
+        )
+        .unwrap();
+
+        let profile = find_profile(settings.profiles, "build");
+        assert_eq!("user@build", profile.host);
+        assert_eq!(vec!["-p", "2222"], profile.ssh_args);
+
+        assert_eq!(RemoteProfile::new("other"), find_profile(vec![], "other"));
+    }
+
+    #[test]
+    fn test_remote_command_line() {
+        let mut profile = RemoteProfile::new("host");
+        profile.nvim = Some("/opt/nvim".to_owned());
+
+        assert_eq!(
+            "/opt/nvim --embed --cmd 'set termguicolors' 'a b.txt'",
+            profile.remote_command_line(&["a b.txt".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_nvim_cmd_wrapper() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        // wrapper from module docs, runs remote command locally
+        let dir = env::temp_dir().join(format!("nvim-gtk-ssh-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let ssh = dir.join("ssh");
+        fs::write(&ssh, "#!/bin/sh\nfor cmd; do :; done\nexec sh -c \"$cmd\"\n").unwrap();
+        fs::set_permissions(&ssh, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut profile = RemoteProfile::new("host");
+        profile.ssh = Some(ssh.to_str().unwrap().to_owned());
+        profile.ssh_args = vec!["-p".to_owned(), "2222".to_owned()];
+        profile.nvim = Some("echo".to_owned());
+
+        let output = profile
+            .nvim_cmd(&["it's a b.txt".to_owned()])
+            .output()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(
+            "--embed --cmd set termguicolors it's a b.txt\n",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use gdk::ContextExt;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BackgroundMode {
+    /// Cover whole area keeping aspect ratio
+    Scaled,
+    Tiled,
+    Centered,
+}
+
+impl BackgroundMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "scaled" => Some(BackgroundMode::Scaled),
+            "tiled" => Some(BackgroundMode::Tiled),
+            "centered" => Some(BackgroundMode::Centered),
+            _ => None,
+        }
+    }
+}
+
+/// Image painted over default background, under grid cells
+pub struct BackgroundImage {
+    surface: cairo::ImageSurface,
+    mode: BackgroundMode,
+    opacity: f64,
+}
+
+impl BackgroundImage {
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        mode: BackgroundMode,
+        opacity: f64,
+    ) -> Result<Self, String> {
+        let pixbuf = gdk_pixbuf::Pixbuf::new_from_file(path).map_err(|e| format!("{}", e))?;
+
+        // convert once, so pixbuf is not converted on every draw
+        let surface = cairo::ImageSurface::create(
+            cairo::Format::ARgb32,
+            pixbuf.get_width(),
+            pixbuf.get_height(),
+        )
+        .map_err(|e| format!("{:?}", e))?;
+        {
+            let ctx = cairo::Context::new(&surface);
+            ctx.set_source_pixbuf(&pixbuf, 0.0, 0.0);
+            ctx.paint();
+        }
+
+        Ok(BackgroundImage {
+            surface,
+            mode,
+            opacity: opacity.clamp(0.0, 1.0),
+        })
+    }
+
+    /// Paint image over area from (0, 0) of given size
+    pub fn paint(&self, ctx: &cairo::Context, width: f64, height: f64) {
+        let image_width = f64::from(self.surface.get_width());
+        let image_height = f64::from(self.surface.get_height());
+        if image_width <= 0.0 || image_height <= 0.0 {
+            return;
+        }
+
+        ctx.save();
+        ctx.rectangle(0.0, 0.0, width, height);
+        ctx.clip();
+
+        match self.mode {
+            BackgroundMode::Scaled => {
+                let scale = cover_scale((image_width, image_height), (width, height));
+                ctx.translate(
+                    (width - image_width * scale) / 2.0,
+                    (height - image_height * scale) / 2.0,
+                );
+                ctx.scale(scale, scale);
+                ctx.set_source_surface(&self.surface, 0.0, 0.0);
+            }
+            BackgroundMode::Tiled => {
+                ctx.set_source_surface(&self.surface, 0.0, 0.0);
+                ctx.get_source().set_extend(cairo::Extend::Repeat);
+            }
+            BackgroundMode::Centered => {
+                ctx.set_source_surface(
+                    &self.surface,
+                    ((width - image_width) / 2.0).round(),
+                    ((height - image_height) / 2.0).round(),
+                );
+            }
+        }
+
+        ctx.paint_with_alpha(self.opacity);
+        ctx.restore();
+    }
+}
+
+/// Minimal scale of image that covers whole area
+fn cover_scale(image: (f64, f64), area: (f64, f64)) -> f64 {
+    (area.0 / image.0).max(area.1 / image.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_from_name() {
+        assert_eq!(Some(BackgroundMode::Tiled), BackgroundMode::from_name("tiled"));
+        assert_eq!(None, BackgroundMode::from_name("stretched"));
+    }
+
+    #[test]
+    fn test_cover_scale() {
+        assert_eq!(2.0, cover_scale((100.0, 50.0), (200.0, 50.0)));
+        assert_eq!(2.0, cover_scale((100.0, 50.0), (100.0, 100.0)));
+        assert_eq!(0.5, cover_scale((400.0, 400.0), (200.0, 100.0)));
+    }
+}
@@ -1,45 +1,105 @@
+use std::cell::RefCell;
 use std::collections::HashSet;
 
 use pango;
 
+use super::glyph_cache::{CacheStats, GlyphCache};
+
 use crate::sys::pango as sys_pango;
 
-use super::itemize::ItemizeIterator;
 use crate::ui_model::StyledLine;
 
 pub struct Context {
     font_metrics: FontMetrix,
     font_features: FontFeatures,
     line_space: i32,
+    letter_space: i32,
+    padding: Padding,
+    scale_factor: f64,
+    glyph_cache: RefCell<GlyphCache>,
 }
 
 impl Context {
     pub fn new(pango_context: pango::Context) -> Self {
         Context {
             line_space: 0,
-            font_metrics: FontMetrix::new(pango_context, 0),
+            letter_space: 0,
+            padding: Padding::default(),
+            scale_factor: 1.0,
+            font_metrics: FontMetrix::new(pango_context, 0, 0, 1.0),
             font_features: FontFeatures::new(),
+            glyph_cache: RefCell::new(GlyphCache::new()),
         }
     }
 
     pub fn update(&mut self, pango_context: pango::Context) {
-        self.font_metrics = FontMetrix::new(pango_context, self.line_space);
+        self.font_metrics = FontMetrix::new(
+            pango_context,
+            self.line_space,
+            self.letter_space,
+            self.scale_factor,
+        );
+        self.font_features.update_font(
+            &self.font_metrics.pango_context,
+            &self.font_metrics.cell_metrics,
+        );
+        self.clear_glyph_cache();
     }
 
-    pub fn update_font_features(&mut self, font_features: FontFeatures) {
+    pub fn update_font_features(&mut self, mut font_features: FontFeatures) {
+        font_features.update_font(
+            &self.font_metrics.pango_context,
+            &self.font_metrics.cell_metrics,
+        );
         self.font_features = font_features;
+        self.clear_glyph_cache();
     }
 
     pub fn update_line_space(&mut self, line_space: i32) {
         self.line_space = line_space;
         let pango_context = self.font_metrics.pango_context.clone();
-        self.font_metrics = FontMetrix::new(pango_context, self.line_space);
+        self.font_metrics = FontMetrix::new(
+            pango_context,
+            self.line_space,
+            self.letter_space,
+            self.scale_factor,
+        );
+    }
+
+    /// Extra pixels added to every column
+    pub fn update_letter_space(&mut self, letter_space: i32) {
+        self.letter_space = letter_space;
+        // glyphs are placed on columns, so they must be shaped again
+        self.update(self.font_metrics.pango_context.clone());
+    }
+
+    pub fn update_padding(&mut self, padding: Padding) {
+        self.padding = padding;
     }
 
+    /// Space between drawing area edges and grid
+    pub fn padding(&self) -> &Padding {
+        &self.padding
+    }
+
+    /// Cell geometry is snapped to device pixels of given scale, it can be
+    /// fractional, e.g. 1.5, return false if scale factor is not changed
+    pub fn update_scale_factor(&mut self, scale_factor: f64) -> bool {
+        if (self.scale_factor - scale_factor).abs() < f64::EPSILON {
+            return false;
+        }
+
+        self.scale_factor = scale_factor;
+        self.update(self.font_metrics.pango_context.clone());
+        true
+    }
+
+    /// Itemize each highlight run of line as a whole,
+    /// so ligatures and complex scripts are shaped across words
     pub fn itemize(&self, line: &StyledLine) -> Vec<pango::Item> {
         let attr_iter = line.attr_list.get_iterator();
 
-        ItemizeIterator::new(&line.line_str)
+        line.runs()
             .flat_map(|(offset, len)| {
                 pango::itemize(
                     &self.font_metrics.pango_context,
@@ -69,6 +129,26 @@ impl Context {
         &self.font_features
     }
 
+    pub fn glyph_cache(&self) -> &RefCell<GlyphCache> {
+        &self.glyph_cache
+    }
+
+    /// Glyph cache hits and misses since start, used by benchmarks
+    #[allow(dead_code)]
+    pub fn glyph_cache_stats(&self) -> CacheStats {
+        self.glyph_cache.borrow().stats()
+    }
+
+    fn clear_glyph_cache(&self) {
+        let mut glyph_cache = self.glyph_cache.borrow_mut();
+        let stats = glyph_cache.stats();
+        debug!(
+            "Clear glyph cache, hits: {}, misses: {}",
+            stats.hits, stats.misses
+        );
+        glyph_cache.clear();
+    }
+
     pub fn font_families(&self) -> HashSet<glib::GString> {
         self.font_metrics
             .pango_context
@@ -86,13 +166,23 @@ struct FontMetrix {
 }
 
 impl FontMetrix {
-    pub fn new(pango_context: pango::Context, line_space: i32) -> Self {
+    pub fn new(
+        pango_context: pango::Context,
+        line_space: i32,
+        letter_space: i32,
+        scale_factor: f64,
+    ) -> Self {
         let font_metrics = pango_context.get_metrics(None, None).unwrap();
         let font_desc = pango_context.get_font_description().unwrap();
 
         FontMetrix {
             pango_context,
-            cell_metrics: CellMetrics::new(&font_metrics, line_space),
+            cell_metrics: CellMetrics::new(
+                &font_metrics,
+                line_space,
+                letter_space,
+                scale_factor,
+            ),
             font_desc,
         }
     }
@@ -106,44 +196,66 @@ pub struct CellMetrics {
     pub underline_thickness: f64,
     pub strikethrough_position: f64,
     pub strikethrough_thickness: f64,
+    pub letter_space: f64,
     pub pango_ascent: i32,
     pub pango_descent: i32,
     pub pango_char_width: i32,
 }
 
 impl CellMetrics {
-    fn new(font_metrics: &pango::FontMetrics, line_space: i32) -> Self {
-        let ascent = (f64::from(font_metrics.get_ascent()) / f64::from(pango::SCALE)).ceil();
-        let descent = (f64::from(font_metrics.get_descent()) / f64::from(pango::SCALE)).ceil();
+    fn new(
+        font_metrics: &pango::FontMetrics,
+        line_space: i32,
+        letter_space: i32,
+        scale: f64,
+    ) -> Self {
+        let to_px = |value: i32| f64::from(value) / f64::from(pango::SCALE);
+
+        let ascent = ceil_to_device(to_px(font_metrics.get_ascent()), scale);
+        let descent = ceil_to_device(to_px(font_metrics.get_descent()), scale);
 
         // distance above top of underline, will typically be negative
-        let pango_underline_position = f64::from(font_metrics.get_underline_position());
-        let underline_position = (pango_underline_position / f64::from(pango::SCALE))
-            .abs()
-            .ceil()
+        let pango_underline_position = to_px(font_metrics.get_underline_position());
+        let underline_position = ceil_to_device(pango_underline_position.abs(), scale)
             .copysign(pango_underline_position);
 
         let underline_thickness =
-            (f64::from(font_metrics.get_underline_thickness()) / f64::from(pango::SCALE)).ceil();
+            ceil_to_device(to_px(font_metrics.get_underline_thickness()), scale);
 
         let strikethrough_position =
-            (f64::from(font_metrics.get_strikethrough_position()) / f64::from(pango::SCALE)).ceil();
-        let strikethrough_thickness = (f64::from(font_metrics.get_strikethrough_thickness())
-            / f64::from(pango::SCALE))
-        .ceil();
+            ceil_to_device(to_px(font_metrics.get_strikethrough_position()), scale);
+        let strikethrough_thickness =
+            ceil_to_device(to_px(font_metrics.get_strikethrough_thickness()), scale);
+
+        // every cell edge must land on device pixel, otherwise
+        // not antialiased backgrounds leave seams between cells
+        let char_width = round_to_device(to_px(font_metrics.get_approximate_char_width()), scale)
+            .max(1.0 / scale)
+            + f64::from(letter_space);
 
         CellMetrics {
             pango_ascent: font_metrics.get_ascent(),
             pango_descent: font_metrics.get_descent(),
-            pango_char_width: font_metrics.get_approximate_char_width(),
+            pango_char_width: (char_width * f64::from(pango::SCALE)).round() as i32,
             ascent,
             line_height: ascent + descent + f64::from(line_space),
-            char_width: f64::from(font_metrics.get_approximate_char_width())
-                / f64::from(pango::SCALE),
+            char_width,
             underline_position: ascent - underline_position + underline_thickness / 2.0,
             underline_thickness,
             strikethrough_position: ascent - strikethrough_position + strikethrough_thickness / 2.0,
             strikethrough_thickness,
+            letter_space: f64::from(letter_space),
+        }
+    }
+
+    /// Scale that fits text of given width into `cells` columns,
+    /// text is never enlarged
+    pub fn fit_scale(&self, width: f64, cells: usize) -> f64 {
+        let max_width = self.char_width * cells as f64;
+        if width > max_width {
+            max_width / width
+        } else {
+            1.0
         }
     }
 
@@ -160,32 +272,427 @@ impl CellMetrics {
             underline_thickness: 0.0,
             strikethrough_position: 0.0,
             strikethrough_thickness: 0.0,
+            letter_space: 0.0,
         }
     }
 }
 
+/// Grid padding in pixels
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Padding {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl Padding {
+    /// Parse padding in CSS order: "all", "vertical horizontal",
+    /// "top horizontal bottom" or "top right bottom left"
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        let values = s
+            .split_whitespace()
+            .map(|value| {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| *value >= 0.0)
+                    .ok_or_else(|| format!("Wrong padding value {}", value))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (top, right, bottom, left) = match *values.as_slice() {
+            [all] => (all, all, all, all),
+            [vertical, horizontal] => (vertical, horizontal, vertical, horizontal),
+            [top, horizontal, bottom] => (top, horizontal, bottom, horizontal),
+            [top, right, bottom, left] => (top, right, bottom, left),
+            _ => return Err(format!("Padding must have 1 to 4 values: {}", s)),
+        };
+
+        Ok(Padding {
+            top,
+            right,
+            bottom,
+            left,
+        })
+    }
+
+    /// Move grid area to drawing area coordinates
+    pub fn shift(&self, area: (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
+        let (x, y, width, height) = area;
+        let (x, y) = (f64::from(x) + self.left, f64::from(y) + self.top);
+        let (x2, y2) = (x + f64::from(width), y + f64::from(height));
+        (
+            x.floor() as i32,
+            y.floor() as i32,
+            (x2.ceil() - x.floor()) as i32,
+            (y2.ceil() - y.floor()) as i32,
+        )
+    }
+}
+
+/// Font face used for highlight with given bold and italic attributes
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FontFace {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
+impl FontFace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "regular" => Some(FontFace::Regular),
+            "bold" => Some(FontFace::Bold),
+            "italic" => Some(FontFace::Italic),
+            "bold_italic" => Some(FontFace::BoldItalic),
+            _ => None,
+        }
+    }
+
+    pub fn of(bold: bool, italic: bool) -> Self {
+        match (bold, italic) {
+            (false, false) => FontFace::Regular,
+            (true, false) => FontFace::Bold,
+            (false, true) => FontFace::Italic,
+            (true, true) => FontFace::BoldItalic,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct FontFeatures {
     attr: Option<pango::Attribute>,
+    features: String,
+    // explicit font faces, indexed by FontFace
+    faces: [Option<pango::FontDescription>; 4],
+    // variation axes of all faces, like "@wght=450"
+    variations: Option<pango::FontDescription>,
+    // faces with variations applied
+    resolved_faces: [Option<pango::FontDescription>; 4],
+    // ordered fallback families, used before fontconfig choice
+    fallback: Vec<String>,
+    // font for double width cells, like guifontwide
+    wide_font: Option<pango::FontDescription>,
+
+    // attributes calculated for current main font
+    fallback_attr: Option<pango::Attribute>,
+    wide_attr: Option<pango::Attribute>,
 }
 
 impl FontFeatures {
     pub fn new() -> Self {
-        FontFeatures { attr: None }
+        FontFeatures {
+            attr: None,
+            features: String::new(),
+            faces: [None, None, None, None],
+            variations: None,
+            resolved_faces: [None, None, None, None],
+            fallback: Vec::new(),
+            wide_font: None,
+            fallback_attr: None,
+            wide_attr: None,
+        }
     }
 
-    pub fn from(font_features: String) -> Self {
+    pub fn set_features(&mut self, font_features: String) {
         if font_features.trim().is_empty() {
-            return Self::new();
+            self.attr = None;
+            self.features = String::new();
+        } else {
+            self.attr = sys_pango::attribute::new_features(&font_features);
+            self.features = font_features;
         }
+    }
 
-        FontFeatures {
-            attr: sys_pango::attribute::new_features(&font_features),
+    /// Set face from font description string, empty string resets face
+    ///
+    /// Description may contain only some fields, e.g. "Iosevka Heavy"
+    /// or variation axes "@wght=700,slnt=-10", other fields are taken from main font.
+    pub fn set_face(&mut self, face: FontFace, desc: &str) {
+        self.faces[face as usize] = if desc.trim().is_empty() {
+            None
+        } else {
+            Some(pango::FontDescription::from_string(desc))
+        };
+        self.resolve_faces();
+    }
+
+    /// Set variation axes of all faces, e.g. "wght=450",
+    /// axes given in face description take precedence
+    pub fn set_variations(&mut self, variations: &str) {
+        let variations = variations.trim();
+        self.variations = if variations.is_empty() {
+            None
+        } else {
+            Some(pango::FontDescription::from_string(&format!("@{}", variations)))
+        };
+        self.resolve_faces();
+    }
+
+    fn resolve_faces(&mut self) {
+        for (resolved, face) in self.resolved_faces.iter_mut().zip(self.faces.iter()) {
+            *resolved = match (face, &self.variations) {
+                (Some(face), Some(variations)) => {
+                    let mut face = face.clone();
+                    face.merge(Some(variations), false);
+                    Some(face)
+                }
+                (Some(face), None) => Some(face.clone()),
+                (None, variations) => variations.clone(),
+            };
         }
     }
 
+    pub fn face(&self, face: FontFace) -> Option<&pango::FontDescription> {
+        self.resolved_faces[face as usize].as_ref()
+    }
+
+    /// Set comma separated list of fallback families, e.g.
+    /// "Noto Sans CJK JP, Symbols Nerd Font, Noto Color Emoji"
+    pub fn set_fallback(&mut self, families: &str) {
+        self.fallback = families
+            .split(',')
+            .map(str::trim)
+            .filter(|family| !family.is_empty())
+            .map(str::to_owned)
+            .collect();
+    }
+
+    /// Set font of double width cells, empty string resets it
+    pub fn set_wide_font(&mut self, desc: &str) {
+        self.wide_font = if desc.trim().is_empty() {
+            None
+        } else {
+            Some(pango::FontDescription::from_string(desc))
+        };
+    }
+
+    /// Calculate attributes that depend on main font,
+    /// must be called after main font or settings change
+    pub fn update_font(&mut self, pango_context: &pango::Context, cell_metrics: &CellMetrics) {
+        let font_desc = pango_context.get_font_description().unwrap();
+
+        self.fallback_attr = if self.fallback.is_empty() {
+            None
+        } else {
+            pango::Attribute::new_family(&self.families(&font_desc))
+        };
+
+        self.wide_attr = self.wide_font.as_ref().and_then(|wide_font| {
+            let mut desc = font_desc.clone();
+            desc.merge(Some(wide_font), true);
+            desc.set_family(&self.families(&desc));
+            fit_wide_font(pango_context, &mut desc, cell_metrics);
+            sys_pango::attribute::new_font_desc(&desc)
+        });
+    }
+
+    fn families(&self, font_desc: &pango::FontDescription) -> String {
+        let mut families: Vec<&str> = Vec::with_capacity(self.fallback.len() + 1);
+        let family = font_desc.get_family();
+        if let Some(ref family) = family {
+            families.push(family);
+        }
+        families.extend(self.fallback.iter().map(String::as_str));
+        families.join(",")
+    }
+
+    /// Fallback families of whole line
+    pub fn insert_fallback_into(&self, attr_list: &pango::AttrList) {
+        if let Some(ref attr) = self.fallback_attr {
+            attr_list.insert(attr.clone());
+        }
+    }
+
+    pub fn wide_attr(&self) -> Option<&pango::Attribute> {
+        self.wide_attr.as_ref()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.features
+    }
+
     pub fn insert_into(&self, attr_list: &pango::AttrList) {
         if let Some(ref attr) = self.attr {
             attr_list.insert(attr.clone());
         }
     }
 }
+
+/// Round pixel value to device pixels of given scale factor
+fn round_to_device(value: f64, scale: f64) -> f64 {
+    (value * scale).round() / scale
+}
+
+fn ceil_to_device(value: f64, scale: f64) -> f64 {
+    (value * scale).ceil() / scale
+}
+
+// Wide glyph used to measure wide font
+const WIDE_SAMPLE: &str = "中";
+
+/// Decrease font size so wide glyph does not overflow two cells
+fn fit_wide_font(
+    pango_context: &pango::Context,
+    desc: &mut pango::FontDescription,
+    cell_metrics: &CellMetrics,
+) {
+    let layout = pango::Layout::new(pango_context);
+    layout.set_font_description(Some(desc));
+    layout.set_text(WIDE_SAMPLE);
+    let (_, logical_rect) = layout.get_extents();
+
+    let scale =
+        cell_metrics.fit_scale(f64::from(logical_rect.width) / f64::from(pango::SCALE), 2);
+    if scale < 1.0 {
+        let size = f64::from(desc.get_size()) * scale;
+        if desc.get_size_is_absolute() {
+            desc.set_absolute_size(size.floor());
+        } else {
+            desc.set_size(size.floor() as i32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap_to_device() {
+        assert_eq!(8.0, round_to_device(8.3, 1.0));
+        assert_eq!(8.5, round_to_device(8.3, 2.0));
+        assert_eq!(9.0, ceil_to_device(8.3, 1.0));
+        assert_eq!(8.5, ceil_to_device(8.3, 2.0));
+        assert_eq!(-8.0, ceil_to_device(-8.3, 1.0));
+    }
+
+    #[test]
+    fn test_padding_from_str() {
+        assert_eq!(
+            Ok(Padding {
+                top: 2.0,
+                right: 2.0,
+                bottom: 2.0,
+                left: 2.0,
+            }),
+            Padding::from_str("2")
+        );
+        assert_eq!(
+            Ok(Padding {
+                top: 1.0,
+                right: 4.0,
+                bottom: 1.0,
+                left: 4.0,
+            }),
+            Padding::from_str("1 4")
+        );
+        assert_eq!(
+            Ok(Padding {
+                top: 1.0,
+                right: 2.0,
+                bottom: 3.0,
+                left: 4.0,
+            }),
+            Padding::from_str(" 1 2 3 4 ")
+        );
+        assert!(Padding::from_str("").is_err());
+        assert!(Padding::from_str("1 2 3 4 5").is_err());
+        assert!(Padding::from_str("-1").is_err());
+        assert!(Padding::from_str("a").is_err());
+    }
+
+    #[test]
+    fn test_padding_shift() {
+        let padding = Padding::from_str("2.5 0 0 4").unwrap();
+        assert_eq!((14, 12, 5, 6), padding.shift((10, 10, 5, 5)));
+    }
+
+    #[test]
+    fn test_cell_metrics_fractional_scale() {
+        use pango::FontMapExt;
+
+        let font_map = pangocairo::FontMap::get_default().unwrap();
+        let pango_context = font_map.create_context().unwrap();
+        pango_context.set_font_description(&pango::FontDescription::from_string(
+            "DejaVu Sans Mono 11",
+        ));
+        let font_metrics = pango_context.get_metrics(None, None).unwrap();
+
+        let scale = 1.5;
+        let cell_metrics = CellMetrics::new(&font_metrics, 0, 0, scale);
+        let on_device_pixel = |value: f64| ((value * scale) - (value * scale).round()).abs() < 1e-9;
+        assert!(on_device_pixel(cell_metrics.char_width));
+        assert!(on_device_pixel(cell_metrics.line_height));
+        assert!(on_device_pixel(cell_metrics.ascent));
+    }
+
+    #[test]
+    fn test_fit_scale() {
+        let cell_metrics = CellMetrics::new_hw(10.0, 5.0);
+        assert_eq!(1.0, cell_metrics.fit_scale(8.0, 2));
+        assert_eq!(1.0, cell_metrics.fit_scale(10.0, 2));
+        assert_eq!(0.5, cell_metrics.fit_scale(20.0, 2));
+    }
+
+    #[test]
+    fn test_set_fallback() {
+        let mut features = FontFeatures::new();
+        features.set_fallback("Noto Sans CJK JP, Symbols Nerd Font,,");
+        assert_eq!(
+            "Monospace,Noto Sans CJK JP,Symbols Nerd Font",
+            features.families(&pango::FontDescription::from_string("Monospace 12"))
+        );
+    }
+
+    #[test]
+    fn test_font_face_of() {
+        assert_eq!(FontFace::Regular, FontFace::of(false, false));
+        assert_eq!(FontFace::BoldItalic, FontFace::of(true, true));
+        assert_eq!(Some(FontFace::BoldItalic), FontFace::from_name("bold_italic"));
+        assert_eq!(None, FontFace::from_name("oblique"));
+    }
+
+    #[test]
+    fn test_set_face() {
+        let mut features = FontFeatures::new();
+        features.set_face(FontFace::Bold, "Monospace Heavy");
+        assert_eq!(
+            pango::Weight::Heavy,
+            features.face(FontFace::Bold).unwrap().get_weight()
+        );
+        assert!(features.face(FontFace::Italic).is_none());
+
+        features.set_face(FontFace::Bold, " ");
+        assert!(features.face(FontFace::Bold).is_none());
+
+        features.set_variations("wght=450");
+        assert!(features.face(FontFace::Regular).is_some());
+        features.set_variations("");
+        assert!(features.face(FontFace::Regular).is_none());
+    }
+
+    #[test]
+    fn test_variations_of_all_faces() {
+        let mut features = FontFeatures::new();
+        features.set_face(FontFace::Regular, "Monospace Light");
+        features.set_face(FontFace::Italic, "@wght=700");
+        features.set_variations("wght=450");
+
+        let face = |features: &FontFeatures, face| features.face(face).unwrap().to_string();
+        let desc = |desc: &str| pango::FontDescription::from_string(desc).to_string();
+        assert_eq!(desc("Monospace Light @wght=450"), face(&features, FontFace::Regular));
+        assert_eq!(desc("@wght=450"), face(&features, FontFace::Bold));
+        assert_eq!(desc("@wght=700"), face(&features, FontFace::Italic));
+
+        // face set after variations keeps them
+        features.set_face(FontFace::Regular, "Monospace Heavy");
+        assert_eq!(desc("Monospace Heavy @wght=450"), face(&features, FontFace::Regular));
+
+        features.set_variations("");
+        assert!(features.face(FontFace::Bold).is_none());
+        assert_eq!(desc("Monospace Heavy"), face(&features, FontFace::Regular));
+    }
+}
@@ -0,0 +1,284 @@
+use std::hash::Hash;
+
+use fnv::FnvHashMap;
+
+use super::context::FontFeatures;
+use crate::highlight::Highlight;
+use crate::ui_model::Glyphs;
+
+const GLYPH_CACHE_SIZE: usize = 4096;
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// Least recently used cache with fixed capacity
+///
+/// Entries are stored in a vector and linked in usage order,
+/// evicted entry slot is reused for new value.
+pub struct LruCache<K, V> {
+    map: FnvHashMap<K, usize>,
+    entries: Vec<Entry<K, V>>,
+    // most recently used
+    head: Option<usize>,
+    // least recently used
+    tail: Option<usize>,
+    capacity: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        debug_assert!(capacity > 0);
+
+        LruCache {
+            map: FnvHashMap::default(),
+            entries: Vec::new(),
+            head: None,
+            tail: None,
+            capacity,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let idx = *self.map.get(key)?;
+        self.move_to_head(idx);
+        Some(&self.entries[idx].value)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some(&idx) = self.map.get(&key) {
+            self.entries[idx].value = value;
+            self.move_to_head(idx);
+            return;
+        }
+
+        let idx = if self.entries.len() < self.capacity {
+            self.entries.push(Entry {
+                key: key.clone(),
+                value,
+                prev: None,
+                next: None,
+            });
+            self.entries.len() - 1
+        } else {
+            let idx = self.tail.unwrap();
+            self.unlink(idx);
+            let entry = &mut self.entries[idx];
+            self.map.remove(&entry.key);
+            entry.key = key.clone();
+            entry.value = value;
+            idx
+        };
+
+        self.map.insert(key, idx);
+        self.push_head(idx);
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.entries.clear();
+        self.head = None;
+        self.tail = None;
+    }
+
+    fn move_to_head(&mut self, idx: usize) {
+        if self.head != Some(idx) {
+            self.unlink(idx);
+            self.push_head(idx);
+        }
+    }
+
+    fn unlink(&mut self, idx: usize) {
+        let (prev, next) = (self.entries[idx].prev, self.entries[idx].next);
+
+        match prev {
+            Some(prev) => self.entries[prev].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => self.entries[next].prev = prev,
+            None => self.tail = prev,
+        }
+
+        self.entries[idx].prev = None;
+        self.entries[idx].next = None;
+    }
+
+    fn push_head(&mut self, idx: usize) {
+        self.entries[idx].next = self.head;
+        if let Some(head) = self.head {
+            self.entries[head].prev = Some(idx);
+        }
+        self.head = Some(idx);
+
+        if self.tail.is_none() {
+            self.tail = Some(idx);
+        }
+    }
+}
+
+/// Shaped run identity: text and attributes that change font selection
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct GlyphKey {
+    text: String,
+    bold: bool,
+    italic: bool,
+    font_features: String,
+    cells_count: usize,
+}
+
+impl GlyphKey {
+    pub fn new(
+        text: &str,
+        hl: &Highlight,
+        font_features: &FontFeatures,
+        cells_count: usize,
+    ) -> Self {
+        GlyphKey {
+            text: text.to_owned(),
+            bold: hl.bold,
+            italic: hl.italic,
+            font_features: font_features.as_str().to_owned(),
+            cells_count,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Cache of shaped glyphs, must be cleared on font or font features change
+pub struct GlyphCache {
+    cache: LruCache<GlyphKey, Glyphs>,
+    stats: CacheStats,
+}
+
+impl GlyphCache {
+    pub fn new() -> Self {
+        GlyphCache {
+            cache: LruCache::new(GLYPH_CACHE_SIZE),
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn get(&mut self, key: &GlyphKey) -> Option<Glyphs> {
+        let glyphs = self.cache.get(key).cloned();
+        if glyphs.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        glyphs
+    }
+
+    pub fn insert(&mut self, key: GlyphKey, glyphs: Glyphs) {
+        self.cache.insert(key, glyphs);
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_evict() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+
+        // 1 is used now, so 2 must be evicted
+        assert_eq!(Some(&"a"), cache.get(&1));
+        cache.insert(3, "c");
+
+        assert_eq!(2, cache.len());
+        assert_eq!(None, cache.get(&2));
+        assert_eq!(Some(&"a"), cache.get(&1));
+        assert_eq!(Some(&"c"), cache.get(&3));
+    }
+
+    #[test]
+    fn test_lru_update() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        cache.insert(1, "x");
+        cache.insert(3, "c");
+
+        assert_eq!(Some(&"x"), cache.get(&1));
+        assert_eq!(None, cache.get(&2));
+    }
+
+    #[test]
+    fn test_lru_capacity_one() {
+        let mut cache = LruCache::new(1);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+
+        assert_eq!(1, cache.len());
+        assert_eq!(None, cache.get(&1));
+        assert_eq!(Some(&"b"), cache.get(&2));
+    }
+
+    #[test]
+    fn test_glyph_key() {
+        let mut bold = Highlight::new();
+        bold.bold = true;
+        let no_features = FontFeatures::new();
+        let mut features = FontFeatures::new();
+        features.set_features("-liga".to_owned());
+
+        assert_eq!(
+            GlyphKey::new("->", &Highlight::new(), &no_features, 2),
+            GlyphKey::new("->", &Highlight::new(), &no_features, 2)
+        );
+        assert_ne!(
+            GlyphKey::new("->", &Highlight::new(), &no_features, 2),
+            GlyphKey::new("->", &bold, &no_features, 2)
+        );
+        assert_ne!(
+            GlyphKey::new("->", &Highlight::new(), &no_features, 2),
+            GlyphKey::new("->", &Highlight::new(), &features, 2)
+        );
+    }
+
+    #[test]
+    fn test_glyph_cache_stats() {
+        let mut cache = GlyphCache::new();
+        let key = GlyphKey::new("abc", &Highlight::new(), &FontFeatures::new(), 3);
+
+        assert!(cache.get(&key).is_none());
+        cache.insert(
+            key.clone(),
+            Glyphs {
+                glyphs: pango::GlyphString::new(),
+                boxed: Vec::new(),
+            },
+        );
+        assert!(cache.get(&key).is_some());
+
+        assert_eq!(CacheStats { hits: 1, misses: 1 }, cache.stats());
+
+        cache.clear();
+        assert!(cache.get(&key).is_none());
+        assert_eq!(CacheStats { hits: 1, misses: 2 }, cache.stats());
+    }
+}
@@ -1,12 +1,25 @@
+mod background;
 mod context;
-mod itemize;
+mod glyph_cache;
 mod model_clip_iterator;
-
-pub use self::context::CellMetrics;
-pub use self::context::{Context, FontFeatures};
+// entry point for snapshot tests and benchmarks
+#[cfg_attr(not(test), allow(dead_code))]
+mod offscreen;
+mod shape;
+mod swatch;
+
+pub use self::background::{BackgroundImage, BackgroundMode};
+pub use self::context::{CellMetrics, Padding};
+pub use self::context::{Context, FontFace, FontFeatures};
+use self::glyph_cache::GlyphKey;
+#[allow(unused_imports)]
+pub use self::offscreen::{render_offscreen, render_offscreen_with, write_png};
+pub use self::shape::{align_to_columns, boxed_clusters, BoxedCluster};
+pub use self::swatch::{draw_color_swatches, has_color_literal};
 use self::model_clip_iterator::{ModelClipIteratorFactory, RowView};
 
 use crate::color;
+use crate::sys::pango::glyph_string;
 use crate::sys::pangocairo::*;
 use cairo;
 use pango;
@@ -89,6 +102,20 @@ fn draw_cursor<C: Cursor>(
     let line_x = cursor_col as f64 * cell_metrics.char_width;
     let line_y = cursor_row as f64 * cell_metrics.line_height;
 
+    let cursor_line = match ui_model.model().get(cursor_row) {
+        Some(cursor_line) => cursor_line,
+        None => return,
+    };
+    let double_width = cursor_line
+        .line
+        .get(cursor_col + 1)
+        .map_or(false, |c| c.double_width);
+
+    // moving cursor is drawn over text without inverting it
+    if cursor.draw_moving(ctx, font_ctx, line_x, line_y, double_width, hl) {
+        return;
+    }
+
     if line_x < x1 || line_y < y1 || line_x > x2 || line_y > y2 || !cursor.is_visible() {
         return;
     }
@@ -97,53 +124,46 @@ fn draw_cursor<C: Cursor>(
     let row_view = ui_model.get_row_view(ctx, cell_metrics, cursor_row);
     let cell_start_col = row_view.line.cell_to_item(cursor_col);
 
-    if let Some(cursor_line) = ui_model.model().get(cursor_row) {
-        let double_width = cursor_line
-            .line
-            .get(cursor_col + 1)
-            .map_or(false, |c| c.double_width);
-
-        if cell_start_col >= 0 {
-            let cell = &cursor_line[cursor_col];
-
-            // clip cursor position
-            let (clip_y, clip_width, clip_height) =
-                cursor_rect(cursor.mode_info(), cell_metrics, line_y, double_width);
-            ctx.rectangle(line_x, clip_y, clip_width, clip_height);
-            ctx.clip();
-
-            // repaint cell backgound
-            // disable antialiase for rectangle borders, so they will not be visible
-            ctx.set_antialias(cairo::Antialias::None);
-            ctx.set_operator(cairo::Operator::Source);
-            fill_background(ctx, hl, bg_alpha);
-            draw_cell_bg(&row_view, hl, cell, cursor_col, line_x, bg_alpha);
-            ctx.set_antialias(cairo::Antialias::Default);
-
-            // reapint cursor and text
-            ctx.set_operator(cairo::Operator::Over);
-            ctx.move_to(line_x, line_y);
-            let cursor_alpha = cursor.draw(ctx, font_ctx, line_y, double_width, &hl);
-
-            let cell_start_line_x =
-                line_x - (cursor_col as i32 - cell_start_col) as f64 * cell_metrics.char_width;
-
-            debug_assert!(cell_start_line_x >= 0.0);
-
-            ctx.set_operator(cairo::Operator::Xor);
-            draw_cell(
-                &row_view,
-                hl,
-                cell,
-                cell_start_col as usize,
-                cell_start_line_x,
-                cursor_alpha,
-            );
-            draw_underline_strikethrough(&row_view, hl, cell, line_x, cursor_alpha);
-        } else {
-            ctx.move_to(line_x, line_y);
-            cursor.draw(ctx, font_ctx, line_y, double_width, &hl);
-        }
+    if cell_start_col >= 0 {
+        let cell = &cursor_line[cursor_col];
+
+        // clip cursor position
+        let (clip_y, clip_width, clip_height) =
+            cursor_rect(cursor.mode_info(), cell_metrics, line_y, double_width);
+        ctx.rectangle(line_x, clip_y, clip_width, clip_height);
+        ctx.clip();
+
+        // repaint cell backgound
+        // disable antialiase for rectangle borders, so they will not be visible
+        ctx.set_antialias(cairo::Antialias::None);
+        ctx.set_operator(cairo::Operator::Source);
+        fill_background(ctx, hl, bg_alpha);
+        draw_cell_bg(&row_view, hl, cell, cursor_col, line_x, bg_alpha);
+        ctx.set_antialias(cairo::Antialias::Default);
+
+        // reapint cursor and text
+        ctx.set_operator(cairo::Operator::Over);
+        ctx.move_to(line_x, line_y);
+        let cursor_alpha = cursor.draw(ctx, font_ctx, line_y, double_width, hl);
+
+        let cell_start_line_x =
+            line_x - (cursor_col as i32 - cell_start_col) as f64 * cell_metrics.char_width;
+
+        debug_assert!(cell_start_line_x >= 0.0);
+
+        ctx.set_operator(cairo::Operator::Xor);
+        draw_cell(
+            &row_view,
+            hl,
+            cell,
+            cell_start_col as usize,
+            cell_start_line_x,
+            cursor_alpha,
+        );
+        draw_underline_strikethrough(&row_view, hl, cell, line_x, cursor_alpha);
+    } else {
+        ctx.move_to(line_x, line_y);
+        cursor.draw(ctx, font_ctx, line_y, double_width, hl);
     }
 }
 
@@ -154,7 +174,7 @@ fn draw_underline_strikethrough(
     line_x: f64,
     inverse_level: f64,
 ) {
-    if cell.hl.underline || cell.hl.undercurl || cell.hl.strikethrough {
+    if cell.hl.has_underline() || cell.hl.strikethrough {
         let &RowView {
             ctx,
             line_y,
@@ -195,17 +215,43 @@ fn draw_underline_strikethrough(
                 char_width,
                 undercurl_height,
             );
-        } else if cell.hl.underline {
-            let fg = hl.actual_cell_fg(cell).inverse(inverse_level);
-            ctx.set_source_rgb(fg.0, fg.1, fg.2);
+        } else if cell.hl.has_underline() {
+            let sp = hl.underline_color(cell).inverse(inverse_level);
+            ctx.set_source_rgb(sp.0, sp.1, sp.2);
             ctx.set_line_width(underline_thickness);
-            ctx.move_to(line_x, line_y + underline_position);
-            ctx.line_to(line_x + char_width, line_y + underline_position);
-            ctx.stroke();
+
+            let underline_y = line_y + underline_position;
+
+            if cell.hl.underdouble {
+                // second line goes under first one, both are moved up
+                // in case there is no space left in cell
+                let gap = underline_thickness * 2.0;
+                let max_y = line_y + line_height - underline_thickness / 2.0 - gap;
+                let underline_y = underline_y.min(max_y);
+
+                draw_line(ctx, line_x, underline_y, char_width);
+                draw_line(ctx, line_x, underline_y + gap, char_width);
+            } else {
+                // dash offset continues pattern from previous cell
+                if cell.hl.underdotted {
+                    ctx.set_dash(&[underline_thickness, underline_thickness], line_x);
+                } else if cell.hl.underdashed {
+                    ctx.set_dash(&[underline_thickness * 3.0, underline_thickness * 2.0], line_x);
+                }
+
+                draw_line(ctx, line_x, underline_y, char_width);
+                ctx.set_dash(&[], 0.0);
+            }
         }
     }
 }
 
+fn draw_line(ctx: &cairo::Context, x: f64, y: f64, width: f64) {
+    ctx.move_to(x, y);
+    ctx.line_to(x + width, y);
+    ctx.stroke();
+}
+
 fn draw_cell_bg(
     cell_view: &RowView,
     hl: &HighlightMap,
@@ -227,11 +273,17 @@ fn draw_cell_bg(
         ..
     } = cell_view;
 
-    let bg = hl.cell_bg(cell);
+    // blended cells are painted over transparent surface,
+    // so default background must be painted too
+    let blend = cell.hl.blend > 0;
+    let bg = hl
+        .cell_bg(cell)
+        .or_else(|| if blend { Some(hl.bg()) } else { None });
+    let bg_alpha = cell.hl.blend_alpha(bg_alpha);
 
     if let Some(bg) = bg {
         if !line.is_binded_to_item(col) {
-            if bg != hl.bg() {
+            if bg != hl.bg() || blend {
                 ctx.set_source_rgbo(bg, bg_alpha);
                 ctx.rectangle(line_x, line_y, char_width, line_height);
                 ctx.fill();
@@ -261,7 +313,12 @@ fn draw_cell(
         ctx,
         line,
         line_y,
-        cell_metrics: &CellMetrics { ascent, .. },
+        cell_metrics:
+            &CellMetrics {
+                ascent,
+                letter_space,
+                ..
+            },
         ..
     } = row_view;
 
@@ -269,14 +326,84 @@ fn draw_cell(
         if let Some(ref glyphs) = item.glyphs {
             let fg = hl.actual_cell_fg(cell).inverse(inverse_level);
 
-            ctx.move_to(line_x, line_y + ascent);
+            // letter space is split between both sides of glyph
+            ctx.move_to(line_x + letter_space / 2.0, line_y + ascent);
             ctx.set_source_rgb(fg.0, fg.1, fg.2);
 
             show_glyph_string(ctx, item.font(), glyphs);
+
+            for boxed in &item.boxed_glyphs {
+                draw_boxed_glyphs(row_view, item.font(), boxed, line_x);
+            }
         }
     }
 }
 
+/// Draw glyphs scaled around vertical center of line and clipped by own cells
+fn draw_boxed_glyphs(
+    row_view: &RowView,
+    font: &pango::Font,
+    boxed: &ui_model::BoxedGlyphs,
+    item_x: f64,
+) {
+    let &RowView {
+        ctx,
+        line_y,
+        cell_metrics:
+            &CellMetrics {
+                char_width,
+                line_height,
+                ascent,
+                ..
+            },
+        ..
+    } = row_view;
+
+    let box_x = item_x + char_width * boxed.col as f64;
+    let center_y = line_y + line_height / 2.0;
+
+    ctx.save();
+    ctx.rectangle(box_x, line_y, char_width * boxed.cells as f64, line_height);
+    ctx.clip();
+
+    ctx.translate(box_x + boxed.x_offset, center_y);
+    ctx.scale(boxed.scale, boxed.scale);
+    ctx.move_to(0.0, line_y + ascent - center_y);
+    show_glyph_string(ctx, font, &boxed.glyphs);
+
+    ctx.restore();
+}
+
+/// Move glyphs of cluster to separate glyph string, that is fitted into cluster cells
+fn box_cluster(
+    ctx: &context::Context,
+    glyphs: &mut pango::GlyphString,
+    geometry: &[(i32, i32)],
+    cluster: BoxedCluster,
+) -> ui_model::BoxedGlyphs {
+    let cell_metrics = ctx.cell_metrics();
+    let box_x = cluster.col as i32 * cell_metrics.pango_char_width;
+
+    let mut boxed = glyph_string::slice(glyphs, cluster.glyphs.start, cluster.glyphs.end);
+    let box_geometry: Vec<_> = geometry[cluster.glyphs.clone()]
+        .iter()
+        .map(|&(_, x_offset)| (0, x_offset - box_x))
+        .collect();
+    glyph_string::set_geometry(&mut boxed, &box_geometry);
+    glyph_string::hide(glyphs, cluster.glyphs.start, cluster.glyphs.end);
+
+    let width = f64::from(cluster.width) / f64::from(pango::SCALE);
+    let scale = cell_metrics.fit_scale(width, cluster.cells);
+
+    ui_model::BoxedGlyphs {
+        col: cluster.col,
+        cells: cluster.cells,
+        scale,
+        x_offset: (cell_metrics.char_width * cluster.cells as f64 - width * scale) / 2.0,
+        glyphs: boxed,
+    }
+}
+
 pub fn shape_dirty(ctx: &context::Context, ui_model: &mut ui_model::UiModel, hl: &HighlightMap) {
     for line in ui_model.model_mut() {
         if !line.dirty_line {
@@ -290,19 +417,65 @@ pub fn shape_dirty(ctx: &context::Context, ui_model: &mut ui_model::UiModel, hl:
         for (col, cell) in line.line.iter_mut().enumerate() {
             if cell.dirty {
                 if let Some(item) = line.item_line[col].as_mut() {
-                    let mut glyphs = pango::GlyphString::new();
+                    let mut shaped = None;
                     {
                         let analysis = item.analysis();
                         let offset = item.item.offset() as usize;
                         let length = item.item.length() as usize;
                         if let Some(line_str) = styled_line.line_str.get(offset..offset + length) {
-                            pango::shape(&line_str, analysis, &mut glyphs);
+                            let key = GlyphKey::new(
+                                line_str,
+                                &cell.hl,
+                                ctx.font_features(),
+                                item.cells_count,
+                            );
+                            let mut glyph_cache = ctx.glyph_cache().borrow_mut();
+
+                            if let Some(cached) = glyph_cache.get(&key) {
+                                shaped = Some(cached);
+                            } else {
+                                let mut glyphs = pango::GlyphString::new();
+                                pango::shape(line_str, analysis, &mut glyphs);
+
+                                let char_width = ctx.cell_metrics().pango_char_width;
+                                let cluster_col =
+                                    |cluster| styled_line.cell_for_byte(offset + cluster) - col;
+                                let raw_glyphs = glyph_string::get_glyphs(&mut glyphs);
+
+                                let geometry = align_to_columns(
+                                    &raw_glyphs,
+                                    cluster_col,
+                                    char_width,
+                                    item.cells_count,
+                                );
+                                glyph_string::set_geometry(&mut glyphs, &geometry);
+
+                                let boxed = boxed_clusters(
+                                    &raw_glyphs,
+                                    cluster_col,
+                                    char_width,
+                                    item.cells_count,
+                                )
+                                .into_iter()
+                                .map(|cluster| box_cluster(ctx, &mut glyphs, &geometry, cluster))
+                                .collect();
+
+                                let new_glyphs = ui_model::Glyphs { glyphs, boxed };
+                                glyph_cache.insert(key, new_glyphs.clone());
+                                shaped = Some(new_glyphs);
+                            }
                         } else {
                             warn!("Wrong itemize split");
                         }
                     }
 
-                    item.set_glyphs(ctx, glyphs);
+                    item.set_glyphs(
+                        ctx,
+                        shaped.unwrap_or_else(|| ui_model::Glyphs {
+                            glyphs: pango::GlyphString::new(),
+                            boxed: Vec::new(),
+                        }),
+                    );
                 }
             }
 
@@ -0,0 +1,530 @@
+use std::fs::File;
+use std::path::Path;
+
+use pango::FontMapExt;
+
+use super::context::{CellMetrics, Context};
+use crate::cursor::Cursor;
+use crate::highlight::HighlightMap;
+use crate::ui_model::UiModel;
+
+/// Create font context that does not depend on screen settings,
+/// so rendering result is same on every machine with same fonts
+pub fn create_font_ctx(font_desc: &pango::FontDescription) -> Context {
+    let font_map = pangocairo::FontMap::get_default().unwrap();
+    let pango_context = font_map.create_context().unwrap();
+
+    let mut font_options = cairo::FontOptions::new();
+    font_options.set_antialias(cairo::Antialias::Gray);
+    font_options.set_hint_style(cairo::HintStyle::None);
+    font_options.set_hint_metrics(cairo::HintMetrics::On);
+    pangocairo::functions::context_set_font_options(&pango_context, Some(&font_options));
+    pangocairo::functions::context_set_resolution(&pango_context, 96.0);
+
+    pango_context.set_font_description(font_desc);
+
+    Context::new(pango_context)
+}
+
+/// Render model to image without window, e.g. for snapshot tests
+pub fn render_offscreen<C: Cursor>(
+    ui_model: &mut UiModel,
+    hl: &HighlightMap,
+    font_desc: &pango::FontDescription,
+    cursor: &C,
+) -> Result<cairo::ImageSurface, cairo::Status> {
+    let font_ctx = create_font_ctx(font_desc);
+    render_offscreen_with(ui_model, hl, &font_ctx, cursor)
+}
+
+/// Same as `render_offscreen` but reuses font context,
+/// so glyph cache survives between frames
+pub fn render_offscreen_with<C: Cursor>(
+    ui_model: &mut UiModel,
+    hl: &HighlightMap,
+    font_ctx: &Context,
+    cursor: &C,
+) -> Result<cairo::ImageSurface, cairo::Status> {
+    let &CellMetrics {
+        line_height,
+        char_width,
+        ..
+    } = font_ctx.cell_metrics();
+
+    let surface = cairo::ImageSurface::create(
+        cairo::Format::Rgb24,
+        (ui_model.columns as f64 * char_width).ceil().max(1.0) as i32,
+        (ui_model.rows as f64 * line_height).ceil().max(1.0) as i32,
+    )?;
+
+    super::shape_dirty(font_ctx, ui_model, hl);
+
+    let ctx = cairo::Context::new(&surface);
+    super::fill_background(&ctx, hl, None);
+    super::render(&ctx, cursor, font_ctx, ui_model, hl, None);
+
+    drop(ctx);
+    surface.flush();
+
+    Ok(surface)
+}
+
+pub fn write_png<P: AsRef<Path>>(surface: &cairo::ImageSurface, path: P) -> Result<(), String> {
+    let mut file = File::create(path.as_ref()).map_err(|e| format!("{}", e))?;
+    surface
+        .write_to_png(&mut file)
+        .map_err(|e| format!("{:?}", e))
+}
+
+pub fn read_png<P: AsRef<Path>>(path: P) -> Result<cairo::ImageSurface, String> {
+    let mut file = File::open(path.as_ref()).map_err(|e| format!("{}", e))?;
+    cairo::ImageSurface::create_from_png(&mut file).map_err(|e| format!("{:?}", e))
+}
+
+/// Count pixels that differ more than `tolerance` in any channel,
+/// images of different size differ in every pixel
+pub fn diff_pixels(
+    left: &mut cairo::ImageSurface,
+    right: &mut cairo::ImageSurface,
+    tolerance: u8,
+) -> usize {
+    let (width, height) = (left.get_width(), left.get_height());
+    if width != right.get_width() || height != right.get_height() {
+        return (width.max(right.get_width()) * height.max(right.get_height())) as usize;
+    }
+
+    let mut diff = 0;
+    for_each_diff(left, right, tolerance, |_, _| diff += 1);
+    diff
+}
+
+/// Image with pixels that differ more than `tolerance` painted red
+/// over faded `left` image, both images must have same size
+pub fn diff_image(
+    left: &mut cairo::ImageSurface,
+    right: &mut cairo::ImageSurface,
+    tolerance: u8,
+) -> Result<cairo::ImageSurface, cairo::Status> {
+    let diff = cairo::ImageSurface::create(
+        cairo::Format::Rgb24,
+        left.get_width(),
+        left.get_height(),
+    )?;
+
+    {
+        let ctx = cairo::Context::new(&diff);
+        ctx.set_source_rgb(1.0, 1.0, 1.0);
+        ctx.paint();
+        ctx.set_source_surface(left, 0.0, 0.0);
+        ctx.paint_with_alpha(0.3);
+
+        ctx.set_source_rgb(1.0, 0.0, 0.0);
+        for_each_diff(left, right, tolerance, |x, y| {
+            ctx.rectangle(x as f64, y as f64, 1.0, 1.0)
+        });
+        ctx.fill();
+    }
+    diff.flush();
+
+    Ok(diff)
+}
+
+fn for_each_diff<F: FnMut(usize, usize)>(
+    left: &mut cairo::ImageSurface,
+    right: &mut cairo::ImageSurface,
+    tolerance: u8,
+    mut f: F,
+) {
+    let (width, height) = (left.get_width() as usize, left.get_height() as usize);
+    let (left_stride, right_stride) = (left.get_stride() as usize, right.get_stride() as usize);
+    let left_data = left.get_data().unwrap();
+    let right_data = right.get_data().unwrap();
+
+    for y in 0..height {
+        for x in 0..width {
+            let left_px = &left_data[y * left_stride + x * 4..y * left_stride + x * 4 + 3];
+            let right_px = &right_data[y * right_stride + x * 4..y * right_stride + x * 4 + 3];
+
+            if left_px
+                .iter()
+                .zip(right_px)
+                .any(|(l, r)| (i16::from(*l) - i16::from(*r)).abs() > i16::from(tolerance))
+            {
+                f(x, y);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::ops::Range;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    use neovim_lib::Value;
+    use pango::FontExt;
+
+    use crate::color::{Color, COLOR_BLACK, COLOR_RED, COLOR_WHITE};
+    use crate::cursor::StaticCursor;
+    use crate::highlight::Highlight;
+    use crate::mode::ModeInfo;
+
+    // antialiasing differs a bit between freetype versions
+    const TOLERANCE: u8 = 48;
+    const MAX_DIFF_PIXELS: usize = 20;
+
+    // golden images are rendered with this font, it is checked
+    // before rendering so missing font does not look like regression
+    const GOLDEN_FONT: &str = "DejaVu Sans Mono";
+
+    fn golden_path(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "resources", "golden", name]
+            .iter()
+            .collect()
+    }
+
+    fn output_path(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "target", "golden", name]
+            .iter()
+            .collect()
+    }
+
+    /// Compare with golden image, set `NVIM_GTK_UPDATE_GOLDEN=1`
+    /// to write new golden image after rendering change
+    fn assert_golden(name: &str, mut surface: cairo::ImageSurface) {
+        let path = golden_path(&format!("{}.png", name));
+
+        if env::var_os("NVIM_GTK_UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            write_png(&surface, &path).unwrap();
+            return;
+        }
+
+        let mut golden = read_png(&path).unwrap_or_else(|e| {
+            panic!(
+                "Can't read golden image {}: {}, run with NVIM_GTK_UPDATE_GOLDEN=1 to create it",
+                path.display(),
+                e
+            )
+        });
+        let diff = diff_pixels(&mut surface, &mut golden, TOLERANCE);
+        if diff > MAX_DIFF_PIXELS {
+            let actual = output_path(&format!("{}.actual.png", name));
+            fs::create_dir_all(actual.parent().unwrap()).unwrap();
+            write_png(&surface, &actual).unwrap();
+
+            let same_size = surface.get_width() == golden.get_width()
+                && surface.get_height() == golden.get_height();
+            if same_size {
+                let diff_img = diff_image(&mut golden, &mut surface, TOLERANCE).unwrap();
+                write_png(&diff_img, output_path(&format!("{}.diff.png", name))).unwrap();
+            }
+
+            panic!(
+                "{} differs from golden image in {} pixels, see {}",
+                name,
+                diff,
+                actual.display()
+            );
+        }
+    }
+
+    fn hl_map() -> HighlightMap {
+        let mut hl = HighlightMap::new();
+        hl.set_defaults(
+            COLOR_BLACK,
+            COLOR_WHITE,
+            COLOR_RED,
+            COLOR_BLACK,
+            COLOR_WHITE,
+        );
+
+        // as default nvim `Cursor` highlight, so cursor differs from background
+        let mut cursor = HashMap::new();
+        cursor.insert("reverse".to_owned(), Value::from(true));
+        let mut info = HashMap::new();
+        info.insert("hi_name".to_owned(), Value::from("Cursor"));
+        hl.set(1, &cursor, &[info]);
+
+        hl
+    }
+
+    fn font_desc() -> pango::FontDescription {
+        let font_desc = pango::FontDescription::from_string(&format!("{} 12", GOLDEN_FONT));
+
+        let font_map = pangocairo::FontMap::get_default().unwrap();
+        let family = font_map
+            .load_font(&font_map.create_context().unwrap(), &font_desc)
+            .and_then(|font| font.describe())
+            .and_then(|desc| desc.get_family());
+        assert_eq!(
+            Some(GOLDEN_FONT),
+            family.as_ref().map(|f| f.as_str()),
+            "golden images need {} font",
+            GOLDEN_FONT
+        );
+
+        font_desc
+    }
+
+    fn mode_info(shape: &str) -> Option<ModeInfo> {
+        let mut mode_data = HashMap::new();
+        mode_data.insert("cursor_shape".to_owned(), From::from(shape));
+        mode_data.insert("cell_percentage".to_owned(), From::from(25));
+        ModeInfo::new(&mode_data).ok()
+    }
+
+    fn put_str(model: &mut UiModel, row: usize, col: usize, text: &str, hl: &Rc<Highlight>) {
+        for (idx, ch) in text.chars().enumerate() {
+            model.put_one(row, col + idx, &ch.to_string(), false, hl.clone());
+        }
+    }
+
+    fn put_wide(model: &mut UiModel, row: usize, col: usize, text: &str, hl: &Rc<Highlight>) {
+        model.put_one(row, col, text, false, hl.clone());
+        model.put_one(row, col + 1, "", true, hl.clone());
+    }
+
+    /// Pixels covered by given cells, partially covered pixels are included
+    fn cells_pixels(
+        cell_metrics: &CellMetrics,
+        rows: Range<usize>,
+        cols: Range<usize>,
+    ) -> (Range<usize>, Range<usize>) {
+        let &CellMetrics {
+            line_height,
+            char_width,
+            ..
+        } = cell_metrics;
+
+        (
+            (cols.start as f64 * char_width).floor() as usize
+                ..(cols.end as f64 * char_width).ceil() as usize,
+            (rows.start as f64 * line_height).floor() as usize
+                ..(rows.end as f64 * line_height).ceil() as usize,
+        )
+    }
+
+    /// Bounding box of pixels in given cells that are not white background
+    fn painted_bounds(
+        surface: &mut cairo::ImageSurface,
+        cell_metrics: &CellMetrics,
+        rows: Range<usize>,
+        cols: Range<usize>,
+    ) -> Option<(Range<usize>, Range<usize>)> {
+        let (x_range, y_range) = cells_pixels(cell_metrics, rows, cols);
+        let x_range = x_range.start..x_range.end.min(surface.get_width() as usize);
+        let y_range = y_range.start..y_range.end.min(surface.get_height() as usize);
+        let stride = surface.get_stride() as usize;
+
+        let data = surface.get_data().unwrap();
+        y_range
+            .flat_map(|y| x_range.clone().map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                let idx = y * stride + x * 4;
+                data[idx..idx + 3].iter().any(|&ch| ch < 0xff - TOLERANCE)
+            })
+            .fold(None, |bounds, (x, y)| match bounds {
+                None => Some((x..x + 1, y..y + 1)),
+                Some((bx, by)) => Some((
+                    bx.start.min(x)..bx.end.max(x + 1),
+                    by.start.min(y)..by.end.max(y + 1),
+                )),
+            })
+    }
+
+    #[test]
+    fn test_diff_pixels() {
+        let mut left = cairo::ImageSurface::create(cairo::Format::Rgb24, 4, 4).unwrap();
+        let mut right = cairo::ImageSurface::create(cairo::Format::Rgb24, 4, 4).unwrap();
+        assert_eq!(0, diff_pixels(&mut left, &mut right, 0));
+
+        {
+            let ctx = cairo::Context::new(&right);
+            ctx.set_source_rgb(1.0, 1.0, 1.0);
+            ctx.rectangle(0.0, 0.0, 2.0, 1.0);
+            ctx.fill();
+        }
+        assert_eq!(2, diff_pixels(&mut left, &mut right, 0));
+
+        let mut small = cairo::ImageSurface::create(cairo::Format::Rgb24, 2, 2).unwrap();
+        assert_eq!(16, diff_pixels(&mut left, &mut small, 0));
+    }
+
+    #[test]
+    fn test_surface_size() {
+        let font_ctx = create_font_ctx(&font_desc());
+        let &CellMetrics {
+            line_height,
+            char_width,
+            ..
+        } = font_ctx.cell_metrics();
+
+        let mut model = UiModel::new(3, 10);
+        let surface =
+            render_offscreen_with(&mut model, &hl_map(), &font_ctx, &StaticCursor::new(None))
+                .unwrap();
+
+        assert_eq!((10.0 * char_width).ceil() as i32, surface.get_width());
+        assert_eq!((3.0 * line_height).ceil() as i32, surface.get_height());
+    }
+
+    #[test]
+    fn test_glyph_cache_hits() {
+        let font_ctx = create_font_ctx(&font_desc());
+        let hl = hl_map();
+
+        for _ in 0..2 {
+            let mut model = UiModel::new(2, 10);
+            put_str(&mut model, 0, 0, "fn main()", &hl.default_hl());
+            put_str(&mut model, 1, 0, "fn main()", &hl.default_hl());
+            render_offscreen_with(&mut model, &hl, &font_ctx, &StaticCursor::new(None)).unwrap();
+        }
+
+        let stats = font_ctx.glyph_cache_stats();
+        assert!(stats.misses > 0);
+        assert!(stats.hits > stats.misses);
+    }
+
+    #[test]
+    fn test_golden_double_width() {
+        let hl = hl_map();
+        let mut model = UiModel::new(2, 12);
+        // cell width comes from model, glyphs of golden font are used
+        // so image does not depend on installed CJK fonts
+        put_str(&mut model, 0, 0, "ab", &hl.default_hl());
+        put_wide(&mut model, 0, 2, "Ж", &hl.default_hl());
+        put_wide(&mut model, 0, 4, "Ω", &hl.default_hl());
+        put_str(&mut model, 0, 6, "cd", &hl.default_hl());
+        model.set_cursor(1, 0);
+
+        let surface =
+            render_offscreen(&mut model, &hl, &font_desc(), &StaticCursor::new(None)).unwrap();
+        assert_golden("double_width", surface);
+    }
+
+    #[test]
+    fn test_golden_undercurl_strikethrough() {
+        let hl = hl_map();
+
+        let mut undercurl = Highlight::new();
+        undercurl.undercurl = true;
+        undercurl.special = Some(Color(0.0, 0.0, 1.0));
+
+        let mut strikethrough = Highlight::new();
+        strikethrough.strikethrough = true;
+
+        let mut underline = Highlight::new();
+        underline.underline = true;
+
+        let mut model = UiModel::new(4, 12);
+        put_str(&mut model, 0, 0, "undercurl", &Rc::new(undercurl));
+        put_str(&mut model, 1, 0, "strike", &Rc::new(strikethrough));
+        put_str(&mut model, 2, 0, "underline", &Rc::new(underline));
+        model.set_cursor(3, 0);
+
+        let surface =
+            render_offscreen(&mut model, &hl, &font_desc(), &StaticCursor::new(None)).unwrap();
+        assert_golden("undercurl_strikethrough", surface);
+    }
+
+    #[test]
+    fn test_golden_underline_styles() {
+        let hl = hl_map();
+        let blue = Some(Color(0.0, 0.0, 1.0));
+
+        let mut underdouble = Highlight::new();
+        underdouble.underdouble = true;
+        underdouble.special = blue.clone();
+
+        let mut underdotted = Highlight::new();
+        underdotted.underdotted = true;
+        underdotted.special = blue.clone();
+
+        let mut underdashed = Highlight::new();
+        underdashed.underdashed = true;
+        underdashed.special = blue;
+
+        let mut model = UiModel::new(4, 12);
+        put_str(&mut model, 0, 0, "underdouble", &Rc::new(underdouble));
+        put_str(&mut model, 1, 0, "underdotted", &Rc::new(underdotted));
+        put_str(&mut model, 2, 0, "underdashed", &Rc::new(underdashed));
+        model.set_cursor(3, 0);
+
+        let surface =
+            render_offscreen(&mut model, &hl, &font_desc(), &StaticCursor::new(None)).unwrap();
+        assert_golden("underline_styles", surface);
+    }
+
+    #[test]
+    fn test_golden_cursor_shapes() {
+        let hl = hl_map();
+
+        for shape in &["block", "horizontal", "vertical"] {
+            let mut model = UiModel::new(1, 6);
+            put_str(&mut model, 0, 0, "cursor", &hl.default_hl());
+            model.set_cursor(0, 2);
+
+            let cursor = StaticCursor::new(mode_info(shape));
+            let surface = render_offscreen(&mut model, &hl, &font_desc(), &cursor).unwrap();
+            assert_golden(&format!("cursor_{}", shape), surface);
+        }
+    }
+
+    #[test]
+    fn test_emoji_clipped_to_cells() {
+        let font_ctx = create_font_ctx(&font_desc());
+        let hl = hl_map();
+        let sequences = [
+            "\u{1f600}",
+            // skin tone modifier
+            "\u{1f44d}\u{1f3fd}",
+            // family, zwj sequence
+            "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}",
+            // flag, regional indicators
+            "\u{1f1ef}\u{1f1f5}",
+        ];
+
+        // every sequence has empty row above and below,
+        // so painting outside of its cells is visible
+        let mut model = UiModel::new(sequences.len() as u64 * 2 + 2, 6);
+        for (idx, sequence) in sequences.iter().enumerate() {
+            put_wide(&mut model, idx * 2 + 1, 2, sequence, &hl.default_hl());
+        }
+        model.set_cursor(sequences.len() * 2 + 1, 0);
+
+        let mut surface =
+            render_offscreen_with(&mut model, &hl, &font_ctx, &StaticCursor::new(None)).unwrap();
+
+        // without emoji font tofu box is drawn, it must be clipped same way
+        let cell_metrics = font_ctx.cell_metrics();
+        for (idx, sequence) in sequences.iter().enumerate() {
+            let row = idx * 2 + 1;
+            let (x_range, y_range) =
+                painted_bounds(&mut surface, cell_metrics, row - 1..row + 2, 0..6)
+                    .unwrap_or_else(|| panic!("{} is not drawn", sequence));
+            let (cells_x, cells_y) = cells_pixels(cell_metrics, row..row + 1, 2..4);
+
+            assert!(
+                cells_x.start <= x_range.start && x_range.end <= cells_x.end,
+                "{} is painted at x {:?} outside of cells {:?}",
+                sequence,
+                x_range,
+                cells_x
+            );
+            assert!(
+                cells_y.start <= y_range.start && y_range.end <= cells_y.end,
+                "{} is painted at y {:?} outside of cells {:?}",
+                sequence,
+                y_range,
+                cells_y
+            );
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::ops::Range;
+
+/// Place glyph clusters of shaped run on grid columns
+///
+/// `glyphs` is `(cluster, width, x_offset)` of each glyph in visual order,
+/// where cluster is byte offset of glyph cluster in run text,
+/// `cluster_col` maps this offset to column relative to run start.
+///
+/// Returned `(width, x_offset)` positions every glyph with x_offset
+/// and zero advance, so clusters land on own columns regardless of
+/// run direction. Last glyph advance is whole run width.
+pub fn align_to_columns<F>(
+    glyphs: &[(i32, i32, i32)],
+    cluster_col: F,
+    char_width: i32,
+    cells_count: usize,
+) -> Vec<(i32, i32)>
+where
+    F: Fn(usize) -> usize,
+{
+    let mut geometry = Vec::with_capacity(glyphs.len());
+    let mut current_cluster = None;
+    let mut cluster_advance = 0;
+
+    for &(cluster, width, x_offset) in glyphs {
+        if current_cluster != Some(cluster) {
+            current_cluster = Some(cluster);
+            cluster_advance = 0;
+        }
+
+        let cluster_x = cluster_col(cluster as usize) as i32 * char_width;
+        geometry.push((0, cluster_x + cluster_advance + x_offset));
+
+        cluster_advance += width;
+    }
+
+    if let Some(last) = geometry.last_mut() {
+        last.0 = cells_count as i32 * char_width;
+    }
+
+    geometry
+}
+
+/// Glyph cluster that does not fill own cells, e.g. color emoji
+/// or glyph from fallback font, it is drawn scaled and centered in own box
+#[derive(Clone, PartialEq, Debug)]
+pub struct BoxedCluster {
+    /// glyphs of cluster in glyph string
+    pub glyphs: Range<usize>,
+    /// first column relative to run start
+    pub col: usize,
+    pub cells: usize,
+    /// sum of glyph advances
+    pub width: i32,
+}
+
+/// Find clusters that overflow own cells or are narrower than multi cell box
+///
+/// Arguments are same as for `align_to_columns`, `glyphs` must be
+/// in shaping result, not aligned.
+pub fn boxed_clusters<F>(
+    glyphs: &[(i32, i32, i32)],
+    cluster_col: F,
+    char_width: i32,
+    cells_count: usize,
+) -> Vec<BoxedCluster>
+where
+    F: Fn(usize) -> usize,
+{
+    let mut clusters: Vec<BoxedCluster> = Vec::new();
+    let mut current_cluster = None;
+
+    for (idx, &(cluster, width, _)) in glyphs.iter().enumerate() {
+        if current_cluster != Some(cluster) {
+            current_cluster = Some(cluster);
+            clusters.push(BoxedCluster {
+                glyphs: idx..idx + 1,
+                col: cluster_col(cluster as usize),
+                cells: 0,
+                width,
+            });
+        } else if let Some(last) = clusters.last_mut() {
+            last.glyphs.end = idx + 1;
+            last.width += width;
+        }
+    }
+
+    let mut cols: Vec<usize> = clusters.iter().map(|cluster| cluster.col).collect();
+    cols.sort();
+    cols.dedup();
+
+    for cluster in &mut clusters {
+        cluster.cells = cols
+            .iter()
+            .find(|&&col| col > cluster.col)
+            .map_or(cells_count, |&col| col)
+            - cluster.col;
+    }
+
+    // hinting can change advance a bit, such glyphs are left as is
+    let tolerance = char_width / 8;
+    clusters.retain(|cluster| {
+        let box_width = cluster.cells as i32 * char_width;
+        cluster.width > box_width + tolerance
+            || (cluster.cells > 1 && cluster.width < box_width - tolerance)
+    });
+
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_to_columns() {
+        // two glyphs of one cluster in first column, then one in second
+        let glyphs = [(0, 10, 0), (0, 0, 2), (1, 10, 0)];
+        let geometry = align_to_columns(&glyphs, |cluster| cluster, 10, 2);
+        assert_eq!(vec![(0, 0), (0, 12), (20, 10)], geometry);
+    }
+
+    #[test]
+    fn test_boxed_clusters_monospace() {
+        let glyphs = [(0, 10, 0), (1, 11, 0), (2, 20, 0)];
+        let cluster_col = |cluster| if cluster < 2 { cluster } else { 2 };
+        assert!(boxed_clusters(&glyphs, cluster_col, 10, 4).is_empty());
+    }
+
+    #[test]
+    fn test_boxed_clusters_wide() {
+        // emoji in two cells overflow, next cluster is narrow in two cells
+        let glyphs = [(0, 10, 0), (1, 28, 0), (5, 12, 0)];
+        let cluster_col = |cluster| match cluster {
+            0 => 0,
+            1 => 1,
+            _ => 3,
+        };
+
+        assert_eq!(
+            vec![
+                BoxedCluster {
+                    glyphs: 1..2,
+                    col: 1,
+                    cells: 2,
+                    width: 28,
+                },
+                BoxedCluster {
+                    glyphs: 2..3,
+                    col: 3,
+                    cells: 2,
+                    width: 12,
+                },
+            ],
+            boxed_clusters(&glyphs, cluster_col, 10, 5)
+        );
+    }
+
+    #[test]
+    fn test_boxed_clusters_zwj() {
+        // zwj sequence is one cluster of many glyphs
+        let glyphs = [(0, 12, 0), (0, 0, 0), (0, 12, 0)];
+        let clusters = boxed_clusters(&glyphs, |_| 0, 10, 2);
+        assert_eq!(1, clusters.len());
+        assert_eq!(0..3, clusters[0].glyphs);
+        assert_eq!(24, clusters[0].width);
+    }
+}
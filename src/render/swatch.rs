@@ -0,0 +1,184 @@
+use std::ops::Range;
+
+use super::context::{CellMetrics, Context};
+use super::model_clip_iterator::{ModelClipIteratorFactory, RowView};
+use crate::color::ColorLiteral;
+use crate::highlight::HighlightMap;
+use crate::ui_model;
+
+/// Where swatch of color literal is drawn
+#[derive(Clone, PartialEq, Debug)]
+pub enum SwatchPlace {
+    /// square in blank cell near literal
+    Cell(usize),
+    /// strip under literal columns
+    Under(Range<usize>),
+}
+
+/// Place swatches of literals, `literals` are column ranges in line order
+///
+/// Blank cell before literal is preferred, then blank cell after it,
+/// one cell is never shared by two swatches.
+pub fn place_swatches(blank: &[bool], literals: &[Range<usize>]) -> Vec<SwatchPlace> {
+    let is_blank = |col: usize| blank.get(col).cloned().unwrap_or(false);
+    let mut taken = None;
+
+    literals
+        .iter()
+        .map(|cols| {
+            let place =
+                if cols.start > 0 && is_blank(cols.start - 1) && taken != Some(cols.start - 1) {
+                    SwatchPlace::Cell(cols.start - 1)
+                } else if is_blank(cols.end) {
+                    SwatchPlace::Cell(cols.end)
+                } else {
+                    SwatchPlace::Under(cols.clone())
+                };
+
+            if let SwatchPlace::Cell(col) = place {
+                taken = Some(col);
+            }
+            place
+        })
+        .collect()
+}
+
+/// Draw swatches next to color literals of visible lines
+pub fn draw_color_swatches(
+    ctx: &cairo::Context,
+    font_ctx: &Context,
+    ui_model: &ui_model::UiModel,
+    hl: &HighlightMap,
+) {
+    let cell_metrics = font_ctx.cell_metrics();
+
+    for row_view in ui_model.get_clip_iterator(ctx, cell_metrics) {
+        draw_line_swatches(&row_view, hl);
+    }
+}
+
+/// Line has color literal, so its swatch can be drawn outside of changed cells
+pub fn has_color_literal(line: &ui_model::Line) -> bool {
+    !ColorLiteral::find_all(&line_text(&line.line).0).is_empty()
+}
+
+/// Text of line and column of every byte in it
+fn line_text(cells: &[ui_model::Cell]) -> (String, Vec<usize>) {
+    let mut text = String::new();
+    let mut byte_cols = Vec::new();
+    for (col, cell) in cells.iter().enumerate() {
+        let ch = if cell.ch.is_empty() && !cell.double_width {
+            " "
+        } else {
+            &cell.ch
+        };
+        text.push_str(ch);
+        byte_cols.extend(std::iter::repeat(col).take(ch.len()));
+    }
+
+    (text, byte_cols)
+}
+
+fn draw_line_swatches(row_view: &RowView, hl: &HighlightMap) {
+    let cells = &row_view.line.line;
+    let (text, byte_cols) = line_text(cells);
+
+    let literals = ColorLiteral::find_all(&text);
+    if literals.is_empty() {
+        return;
+    }
+
+    let blank: Vec<bool> = cells
+        .iter()
+        .map(|cell| !cell.double_width && cell.ch.trim().is_empty())
+        .collect();
+    let ranges: Vec<Range<usize>> = literals
+        .iter()
+        .map(|literal| byte_cols[literal.range.start]..byte_cols[literal.range.end - 1] + 1)
+        .collect();
+
+    let &RowView {
+        ctx,
+        line_y,
+        cell_metrics:
+            &CellMetrics {
+                char_width,
+                line_height,
+                ..
+            },
+        ..
+    } = row_view;
+
+    let border = hl.fg();
+    for (literal, place) in literals.iter().zip(place_swatches(&blank, &ranges)) {
+        let color = &literal.color;
+
+        match place {
+            SwatchPlace::Cell(col) => {
+                let size = (char_width.min(line_height) * 0.7).round();
+                let x = (col as f64 * char_width + (char_width - size) / 2.0).round();
+                let y = (line_y + (line_height - size) / 2.0).round();
+
+                ctx.rectangle(x + 0.5, y + 0.5, size - 1.0, size - 1.0);
+                ctx.set_source_rgb(color.0, color.1, color.2);
+                ctx.fill_preserve();
+                ctx.set_source_rgba(border.0, border.1, border.2, 0.5);
+                ctx.set_line_width(1.0);
+                ctx.stroke();
+            }
+            SwatchPlace::Under(cols) => {
+                let height = (line_height / 8.0).max(2.0).round();
+                ctx.rectangle(
+                    cols.start as f64 * char_width,
+                    line_y + line_height - height,
+                    cols.len() as f64 * char_width,
+                    height,
+                );
+                ctx.set_source_rgb(color.0, color.1, color.2);
+                ctx.fill();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_before_after() {
+        // "a: #fff" and "#fff;"
+        let blank = [false, false, true, false, false, false, false];
+        assert_eq!(vec![SwatchPlace::Cell(2)], place_swatches(&blank, &[3..7]));
+
+        let blank = [false, false, false, false, false];
+        assert_eq!(
+            vec![SwatchPlace::Under(0..4)],
+            place_swatches(&blank, &[0..4])
+        );
+    }
+
+    #[test]
+    fn test_place_not_shared() {
+        // "#fff #000 "
+        let blank = [
+            false, false, false, false, true, false, false, false, false, true,
+        ];
+        assert_eq!(
+            vec![SwatchPlace::Cell(4), SwatchPlace::Cell(9)],
+            place_swatches(&blank, &[0..4, 5..9])
+        );
+    }
+
+    #[test]
+    fn test_has_color_literal() {
+        let mut line = ui_model::Line::new(8);
+        for (cell, ch) in line.line.iter_mut().zip("a: #fff".chars()) {
+            cell.ch = ch.to_string();
+        }
+        assert!(has_color_literal(&line));
+
+        line.line[3].ch = "x".to_owned();
+        assert!(!has_color_literal(&line));
+    }
+}
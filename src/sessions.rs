@@ -0,0 +1,267 @@
+//! Persistent sessions: nvim servers that keep running when window is closed
+//!
+//! Every project has own directory with server socket and file
+//! with project path, so running servers can be listed.
+
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use fnv::FnvHasher;
+
+use crate::dirs;
+
+const SOCKET_NAME: &str = "nvim.sock";
+const PROJECT_FILE: &str = "project";
+// directory without socket can belong to server that is starting now
+const STARTING_TIMEOUT: Duration = Duration::from_secs(60);
+const QUIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Running server of project
+#[derive(Clone, PartialEq, Debug)]
+pub struct ServerSession {
+    pub project: PathBuf,
+    pub socket: PathBuf,
+}
+
+impl ServerSession {
+    pub fn project_name(&self) -> String {
+        self.project
+            .file_name()
+            .unwrap_or_else(|| self.project.as_os_str())
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+fn sessions_dir() -> Result<PathBuf, String> {
+    Ok(dirs::get_app_runtime_dir_create()?.join("sessions"))
+}
+
+/// Short stable directory name, socket path length is limited
+fn project_key(project: &Path) -> String {
+    let mut hasher = FnvHasher::default();
+    hasher.write(project.to_string_lossy().as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Socket of project server, session directory is created if needed
+pub fn socket_path(project: &Path) -> Result<PathBuf, String> {
+    let dir = sessions_dir()?.join(project_key(project));
+
+    fs::create_dir_all(&dir).map_err(|e| format!("{}", e))?;
+    fs::write(dir.join(PROJECT_FILE), project.to_string_lossy().as_bytes())
+        .map_err(|e| format!("{}", e))?;
+
+    Ok(dir.join(SOCKET_NAME))
+}
+
+/// Running servers ordered by project, directories of quit servers are removed
+pub fn list() -> Vec<ServerSession> {
+    let dir = match sessions_dir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            error!("Can't get sessions directory: {}", err);
+            return vec![];
+        }
+    };
+
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        // no session was started yet
+        Err(_) => return vec![],
+    };
+
+    let mut sessions: Vec<ServerSession> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|session_dir| {
+            let socket = session_dir.join(SOCKET_NAME);
+
+            if !is_alive(&socket) {
+                if is_stale(&session_dir, &socket) {
+                    fs::remove_dir_all(&session_dir).ok();
+                }
+                return None;
+            }
+
+            let project = fs::read_to_string(session_dir.join(PROJECT_FILE)).ok()?;
+            Some(ServerSession {
+                project: PathBuf::from(project),
+                socket,
+            })
+        })
+        .collect();
+
+    sessions.sort_by(|a, b| a.project.cmp(&b.project));
+    sessions
+}
+
+fn is_stale(session_dir: &Path, socket: &Path) -> bool {
+    if socket.exists() {
+        return true;
+    }
+
+    fs::metadata(session_dir.join(PROJECT_FILE))
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map_or(true, |age| age > STARTING_TIMEOUT)
+}
+
+#[cfg(unix)]
+pub fn is_alive(socket: &Path) -> bool {
+    std::os::unix::net::UnixStream::connect(socket).is_ok()
+}
+
+#[cfg(not(unix))]
+pub fn is_alive(_socket: &Path) -> bool {
+    false
+}
+
+/// Remove socket left by crashed server, nvim can't listen on existing path
+pub fn remove_dead_socket(socket: &Path) -> Result<(), String> {
+    if socket.exists() && !is_alive(socket) {
+        fs::remove_file(socket).map_err(|e| format!("{}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Wait until just started server accepts connections,
+/// fails as soon as server quits
+pub fn wait_alive(server: &mut Child, socket: &Path, timeout: Duration) -> Result<(), String> {
+    let start = Instant::now();
+
+    while !is_alive(socket) {
+        if let Some(status) = server.try_wait().map_err(|e| format!("{}", e))? {
+            return Err(format!("Server quit on start, {}", status));
+        }
+        if start.elapsed() > timeout {
+            return Err(format!("Server does not listen on {}", socket.display()));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    Ok(())
+}
+
+/// Wait until server stops accepting connections
+fn wait_quit(socket: &Path, timeout: Duration) -> Result<(), String> {
+    let start = Instant::now();
+
+    while is_alive(socket) {
+        if start.elapsed() > timeout {
+            return Err(format!("Server on {} does not quit", socket.display()));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    Ok(())
+}
+
+/// Quit server discarding changes, blocks until server quits
+///
+/// Session directory is removed only when server is gone,
+/// otherwise running server becomes unreachable.
+pub fn kill(session: &ServerSession) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use neovim_lib::{Neovim, NeovimApi, Session};
+
+        let mut nvim_session =
+            Session::new_unix_socket(&session.socket).map_err(|e| format!("{}", e))?;
+        nvim_session.set_timeout(Duration::from_secs(1));
+        nvim_session.start_event_loop();
+
+        // nvim closes connection on quit, so there is no response,
+        // whether server quit is checked by socket below
+        Neovim::new(nvim_session).command("qall!").ok();
+    }
+
+    wait_quit(&session.socket, QUIT_TIMEOUT)?;
+
+    if let Some(session_dir) = session.socket.parent() {
+        fs::remove_dir_all(session_dir).map_err(|e| format!("{}", e))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_key() {
+        let key = project_key(Path::new("/home/user/project"));
+        assert_eq!(16, key.len());
+        assert_eq!(key, project_key(Path::new("/home/user/project")));
+        assert_ne!(key, project_key(Path::new("/home/user/project2")));
+    }
+
+    #[test]
+    fn test_project_name() {
+        let session = ServerSession {
+            project: PathBuf::from("/home/user/project"),
+            socket: PathBuf::from("/run/nvim.sock"),
+        };
+        assert_eq!("project", session.project_name());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_alive_server_quit() {
+        let mut server = std::process::Command::new("sh")
+            .args(&["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+
+        let start = Instant::now();
+        let res = wait_alive(
+            &mut server,
+            Path::new("/nonexistent/nvim.sock"),
+            Duration::from_secs(10),
+        );
+        assert!(res.unwrap_err().contains("exit status: 3"));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_dead_socket() {
+        let dir = std::env::temp_dir().join(format!("nvim-gtk-session-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join(SOCKET_NAME);
+
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+        remove_dead_socket(&socket).unwrap();
+        assert!(!socket.exists());
+
+        let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+        remove_dead_socket(&socket).unwrap();
+        assert!(socket.exists());
+
+        drop(listener);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_quit() {
+        let dir = std::env::temp_dir().join(format!("nvim-gtk-quit-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join(SOCKET_NAME);
+
+        let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+        assert!(wait_quit(&socket, Duration::from_millis(100)).is_err());
+
+        drop(listener);
+        assert!(wait_quit(&socket, Duration::from_millis(100)).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,6 @@
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::env;
 use std::ops::Deref;
 use std::rc::Rc;
 use std::sync::{Arc, Condvar, Mutex};
@@ -21,22 +22,27 @@ use pangocairo;
 use neovim_lib::neovim_api::Tabpage;
 use neovim_lib::{Neovim, NeovimApi, NeovimApiAsync, Value};
 
-use crate::color::{Color, COLOR_BLACK, COLOR_WHITE};
-use crate::grid::GridMap;
+use crate::color::{Color, ColorLiteral, ColorNotation, COLOR_BLACK, COLOR_WHITE};
+use crate::grid::{self, GridKind, GridMap};
 use crate::highlight::HighlightMap;
 use crate::misc::{decode_uri, escape_filename, split_at_comma};
 use crate::nvim::{
     self, CompleteItem, ErrorReport, NeovimClient, NeovimClientAsync, NeovimRef, NvimHandler,
     RepaintMode,
 };
+use crate::remote::RemoteProfile;
+use crate::sessions;
 use crate::settings::{FontSource, Settings};
 use crate::ui_model::ModelRect;
 
+use crate::bell::VisualBell;
 use crate::cmd_line::{CmdLine, CmdLineContext};
-use crate::cursor::{BlinkCursor, Cursor, CursorRedrawCb};
+use crate::cursor::{BlinkCursor, Cursor, CursorRedrawCb, EmptyCursor};
 use crate::error;
+use crate::float_win::{self, FloatWindows};
+use crate::messages::Messages;
+use crate::smooth_scroll::{self, ScrollOffset, ScrollRegion, SmoothScroll};
 use crate::input;
-use crate::input::keyval_to_input_string;
 use crate::mode;
 use crate::popup_menu::{self, PopupMenu};
 use crate::render;
@@ -47,8 +53,23 @@ use crate::ui::UiMutex;
 use crate::preview::{Preview, PreviewType};
 
 const DEFAULT_FONT_NAME: &str = "DejaVu Sans Mono 12";
+// font zoom is in points added to font size
+const MAX_FONT_ZOOM: i32 = 48;
+const MIN_FONT_SIZE: i32 = 4;
 pub const MINIMUM_SUPPORTED_NVIM_VERSION: &str = "0.3.2";
 
+// buffer can change while color dialog is shown,
+// literal is replaced only if it is still in place
+const REPLACE_COLOR_LUA: &str = r#"
+local buf, row, start_col, end_col, literal, replacement = ...
+local line = vim.api.nvim_buf_get_lines(buf, row, row + 1, false)[1]
+if not line or line:sub(start_col + 1, end_col) ~= literal then
+  vim.api.nvim_err_writeln('Color literal changed, it is not replaced')
+  return
+end
+vim.api.nvim_buf_set_text(buf, row, start_col, row, end_col, { replacement })
+"#;
+
 macro_rules! idle_cb_call {
     ($state:ident.$cb:ident($( $x:expr ),*)) => (
             glib::idle_add(move || {
@@ -109,6 +130,40 @@ impl TransparencySettigns {
     }
 }
 
+/// Nvim ui events handling, each can be disabled with `:NGUiEvent`
+pub struct UiEvents {
+    bell: bool,
+    visual_bell: bool,
+    suspend: bool,
+    chdir: bool,
+    // nvim sends chdir, DirChanged autocmd is not needed
+    chdir_received: bool,
+}
+
+impl UiEvents {
+    pub fn new() -> Self {
+        UiEvents {
+            bell: true,
+            visual_bell: true,
+            suspend: true,
+            chdir: true,
+            chdir_received: false,
+        }
+    }
+
+    /// Returns false for unknown event
+    fn set(&mut self, event: &str, enabled: bool) -> bool {
+        match event {
+            "bell" => self.bell = enabled,
+            "visual_bell" => self.visual_bell = enabled,
+            "suspend" => self.suspend = enabled,
+            "chdir" => self.chdir = enabled,
+            _ => return false,
+        }
+        true
+    }
+}
+
 pub struct State {
     pub grids: GridMap,
 
@@ -117,6 +172,11 @@ pub struct State {
     cursor: Option<BlinkCursor<State>>,
     popup_menu: PopupMenu,
     cmd_line: CmdLine,
+    float_windows: FloatWindows,
+    messages: Messages,
+    smooth_scroll: SmoothScroll,
+    visual_bell: VisualBell,
+    ui_events: UiEvents,
     settings: Rc<RefCell<Settings>>,
     render_state: Rc<RefCell<RenderState>>,
 
@@ -136,10 +196,19 @@ pub struct State {
 
     options: ShellOptions,
     transparency_settings: TransparencySettigns,
+    pumblend: u8,
+
+    // font without zoom
+    font_desc: FontDescription,
+    font_zoom: i32,
+    zoom_gesture: gtk::GestureZoom,
+    background_image: Option<render::BackgroundImage>,
+    color_swatches: bool,
 
     detach_cb: Option<Box<RefCell<dyn FnMut() + Send + 'static>>>,
     nvim_started_cb: Option<Box<RefCell<dyn FnMut() + Send + 'static>>>,
     command_cb: Option<Box<dyn FnMut(&mut State, nvim::NvimCommand) + Send + 'static>>,
+    font_zoom_cb: Option<Box<dyn FnMut(i32) + Send + 'static>>,
 
     subscriptions: RefCell<Subscriptions>,
 }
@@ -157,9 +226,14 @@ impl State {
 
         let popup_menu = PopupMenu::new(&drawing_area);
         let cmd_line = CmdLine::new(&drawing_area, render_state.clone());
+        let float_windows = FloatWindows::new(&drawing_area);
+        let messages = Messages::new(&drawing_area);
+        let smooth_scroll = SmoothScroll::new(&drawing_area);
+        let visual_bell = VisualBell::new(&drawing_area);
         let preview_splitter = gtk::Paned::new(gtk::Orientation::Horizontal);
         let preview = Preview::new();
-        
+        let zoom_gesture = gtk::GestureZoom::new(&drawing_area);
+
         State {
             grids: GridMap::new(),
             nvim: Rc::new(NeovimClient::new()),
@@ -167,6 +241,11 @@ impl State {
             cursor: None,
             popup_menu,
             cmd_line,
+            float_windows,
+            messages,
+            smooth_scroll,
+            visual_bell,
+            ui_events: UiEvents::new(),
             settings,
             render_state,
 
@@ -187,10 +266,18 @@ impl State {
 
             options,
             transparency_settings: TransparencySettigns::new(),
+            pumblend: 0,
+
+            font_desc: FontDescription::from_string(DEFAULT_FONT_NAME),
+            font_zoom: 0,
+            zoom_gesture,
+            background_image: None,
+            color_swatches: false,
 
             detach_cb: None,
             nvim_started_cb: None,
             command_cb: None,
+            font_zoom_cb: None,
 
             subscriptions: RefCell::new(Subscriptions::new()),
         }
@@ -216,6 +303,11 @@ impl State {
         self.nvim.clone()
     }
 
+    /// Host of nvim started through ssh, its files are not on local file system
+    pub fn remote_host(&self) -> Option<&str> {
+        self.options.remote.as_ref().map(|remote| remote.host.as_str())
+    }
+
     pub fn start_nvim_initialization(&self) -> bool {
         if self.nvim.is_uninitialized() {
             self.nvim.set_in_progress();
@@ -258,6 +350,17 @@ impl State {
         }
     }
 
+    pub fn set_font_zoom_cb<F>(&mut self, cb: Option<F>)
+    where
+        F: FnMut(i32) + Send + 'static,
+    {
+        if let Some(c) = cb {
+            self.font_zoom_cb = Some(Box::new(c));
+        } else {
+            self.font_zoom_cb = None;
+        }
+    }
+
     pub fn set_font_desc(&mut self, desc: &str) {
         let font_description = FontDescription::from_string(desc);
 
@@ -266,6 +369,43 @@ impl State {
             return;
         }
 
+        self.font_desc = font_description;
+        self.apply_font();
+    }
+
+    pub fn font_zoom(&self) -> i32 {
+        self.font_zoom
+    }
+
+    /// Set zoom in points relative to font set by GuiFont or settings,
+    /// window size is kept and nvim is resized to new grid size
+    pub fn set_font_zoom(&mut self, zoom: i32) {
+        let zoom = zoom.clamp(-MAX_FONT_ZOOM, MAX_FONT_ZOOM);
+        if self.font_zoom != zoom {
+            self.font_zoom = zoom;
+            self.apply_font();
+
+            if let Some(ref mut cb) = self.font_zoom_cb {
+                cb(zoom);
+            }
+        }
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.set_font_zoom(self.font_zoom + 1);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.set_font_zoom(self.font_zoom - 1);
+    }
+
+    pub fn zoom_reset(&mut self) {
+        self.set_font_zoom(0);
+    }
+
+    fn apply_font(&mut self) {
+        let font_description = zoom_font_desc(&self.font_desc, self.font_zoom);
+
         let pango_context = self.drawing_area.create_pango_context().unwrap();
         pango_context.set_font_description(&font_description);
 
@@ -275,16 +415,63 @@ impl State {
             .update(pango_context);
         self.grids.clear_glyphs();
         self.try_nvim_resize();
+        self.show_floats();
         self.on_redraw(&RepaintMode::All);
     }
 
-    pub fn set_font_features(&mut self, font_features: String) {
-        let font_features = render::FontFeatures::from(font_features);
-
-        self.render_state
+    /// Widget moved to monitor with other scale, e.g. from 100% to 200%
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        let changed = self
+            .render_state
             .borrow_mut()
             .font_ctx
-            .update_font_features(font_features);
+            .update_scale_factor(scale_factor);
+        if !changed {
+            return;
+        }
+
+        debug!("Scale factor {}", scale_factor);
+        self.grids.clear_glyphs();
+        self.try_nvim_resize();
+        self.show_floats();
+        self.on_redraw(&RepaintMode::All);
+    }
+
+    pub fn set_font_features(&mut self, font_features: String) {
+        self.update_font_features(|features| features.set_features(font_features));
+    }
+
+    pub fn set_font_face(&mut self, face: String, desc: String) {
+        let face = match render::FontFace::from_name(&face) {
+            Some(face) => face,
+            None => {
+                error!("Unknown font face {}", face);
+                return;
+            }
+        };
+
+        self.update_font_features(|features| features.set_face(face, &desc));
+    }
+
+    pub fn set_font_variations(&mut self, variations: String) {
+        self.update_font_features(|features| features.set_variations(&variations));
+    }
+
+    pub fn set_font_wide(&mut self, desc: String) {
+        self.update_font_features(|features| features.set_wide_font(&desc));
+    }
+
+    pub fn set_font_fallback(&mut self, families: String) {
+        self.update_font_features(|features| features.set_fallback(&families));
+    }
+
+    fn update_font_features<F: FnOnce(&mut render::FontFeatures)>(&mut self, update: F) {
+        {
+            let mut render_state = self.render_state.borrow_mut();
+            let mut font_features = render_state.font_ctx.font_features().clone();
+            update(&mut font_features);
+            render_state.font_ctx.update_font_features(font_features);
+        }
         self.grids.clear_glyphs();
         self.on_redraw(&RepaintMode::All);
     }
@@ -304,6 +491,44 @@ impl State {
             .update_line_space(line_space);
         self.grids.clear_glyphs();
         self.try_nvim_resize();
+        self.show_floats();
+        self.on_redraw(&RepaintMode::All);
+    }
+
+    pub fn set_letter_space(&mut self, letter_space: String) {
+        let letter_space: i32 = match letter_space.parse() {
+            Ok(letter_space) => letter_space,
+            Err(e) => {
+                error!("Can't convert argument to integer: {}", e.to_string());
+                return;
+            }
+        };
+
+        self.render_state
+            .borrow_mut()
+            .font_ctx
+            .update_letter_space(letter_space.max(0));
+        self.grids.clear_glyphs();
+        self.try_nvim_resize();
+        self.show_floats();
+        self.on_redraw(&RepaintMode::All);
+    }
+
+    pub fn set_padding(&mut self, padding: String) {
+        let padding = match render::Padding::from_str(&padding) {
+            Ok(padding) => padding,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+
+        self.render_state
+            .borrow_mut()
+            .font_ctx
+            .update_padding(padding);
+        self.try_nvim_resize();
+        self.show_floats();
         self.on_redraw(&RepaintMode::All);
     }
 
@@ -330,6 +555,105 @@ impl State {
         }
     }
 
+    /// Empty path removes background image
+    pub fn set_background_image(&mut self, path: &str, mode: &str, opacity: f64) {
+        if path.is_empty() {
+            self.background_image = None;
+        } else {
+            let mode = match render::BackgroundMode::from_name(mode) {
+                Some(mode) => mode,
+                None => {
+                    error!("Unknown background image mode {}", mode);
+                    return;
+                }
+            };
+
+            match render::BackgroundImage::load(path, mode, opacity) {
+                Ok(image) => self.background_image = Some(image),
+                Err(e) => {
+                    error!("Can't load background image {}: {}", path, e);
+                    return;
+                }
+            }
+        }
+
+        self.drawing_area.queue_draw();
+    }
+
+    pub fn set_ui_event(&mut self, event: &str, enabled: bool) {
+        if !self.ui_events.set(event, enabled) {
+            error!("Unknown ui event {}", event);
+        }
+    }
+
+    /// Working directory changes come with chdir ui event,
+    /// DirChanged autocmd is used only if nvim doesn't send it,
+    /// `:NGUiEvent chdir off` disables both
+    pub fn follow_dir_changed(&self) -> bool {
+        self.ui_events.chdir && !self.ui_events.chdir_received
+    }
+
+    pub fn set_color_swatches(&mut self, enabled: bool) {
+        self.color_swatches = enabled;
+        self.drawing_area.queue_draw();
+    }
+
+    /// Open color chooser for color literal at byte `col` of buffer line `row`,
+    /// chosen color replaces literal in buffer
+    pub fn pick_color(&self, buf: i64, row: i64, col: usize, line: &str) {
+        let literal = match ColorLiteral::find_at(line, col) {
+            Some(literal) => literal,
+            None => {
+                if let Some(mut nvim) = self.nvim() {
+                    nvim.err_writeln_async("No color literal under cursor")
+                        .cb(|r| r.report_err())
+                        .call();
+                }
+                return;
+            }
+        };
+
+        let window = self
+            .drawing_area
+            .get_toplevel()
+            .and_then(|w| w.downcast::<gtk::Window>().ok());
+        let nvim = self.nvim.clone();
+        let text = line[literal.range.clone()].to_owned();
+
+        // dialog runs own main loop, so state must not be borrowed while it is shown
+        gtk::idle_add(move || {
+            if let Some(replacement) = choose_color(window.as_ref(), &literal) {
+                if let Some(mut nvim) = nvim.nvim() {
+                    nvim.session
+                        .call_async::<Value>(
+                            "nvim_exec_lua",
+                            vec![
+                                Value::from(REPLACE_COLOR_LUA),
+                                Value::Array(vec![
+                                    buf.into(),
+                                    row.into(),
+                                    Value::from(literal.range.start),
+                                    Value::from(literal.range.end),
+                                    Value::from(text.as_str()),
+                                    Value::from(replacement),
+                                ]),
+                            ],
+                        )
+                        .cb(|r| r.report_err())
+                        .call();
+                }
+            }
+            Continue(false)
+        });
+    }
+
+    pub fn set_cursor_animation(&mut self, duration: u32, trail: bool) {
+        if let Some(cursor) = &mut self.cursor {
+            cursor.set_animation(duration, trail);
+        }
+        self.cmd_line.set_cursor_animation(duration, trail);
+    }
+
     pub fn open_file(&self, path: &str) {
         if let Some(mut nvim) = self.nvim() {
             nvim.command_async(&format!("e {}", path))
@@ -357,7 +681,7 @@ impl State {
     fn close_popup_menu(&self) {
         if self.popup_menu.is_open() {
             if let Some(mut nvim) = self.nvim() {
-                nvim.input("<Esc>").report_err();
+                nvim.input_async("<Esc>").cb(|r| r.report_err()).call();
             }
         }
     }
@@ -369,7 +693,7 @@ impl State {
             .iter()
             .map(|rect| rect.as_ref().clone())
             .map(|mut rect| {
-                rect.extend_by_items(self.grids.current_model());
+                rect.extend_by_items(self.grids.default_model());
                 rect
             })
             .collect();
@@ -380,17 +704,19 @@ impl State {
         let cell_metrics = render_state.font_ctx.cell_metrics();
 
         for mut rect in rects {
-            rect.extend_by_items(self.grids.current_model());
+            rect.extend_by_items(self.grids.default_model());
 
-            let (x, y, width, height) =
-                rect.to_area_extend_ink(self.grids.current_model(), cell_metrics);
+            let (x, y, width, height) = render_state
+                .font_ctx
+                .padding()
+                .shift(rect.to_area_extend_ink(self.grids.default_model(), cell_metrics));
             self.drawing_area.queue_draw_area(x, y, width, height);
         }
     }
 
     fn update_dirty_glyphs(&mut self) {
         let render_state = self.render_state.borrow();
-        if let Some(model) = self.grids.current_model_mut() {
+        for model in self.grids.models_mut() {
             render::shape_dirty(&render_state.font_ctx, model, &render_state.hl);
         }
     }
@@ -402,15 +728,19 @@ impl State {
     }
 
     fn calc_nvim_size(&self) -> (usize, usize) {
+        let render_state = self.render_state.borrow();
         let &CellMetrics {
             line_height,
             char_width,
             ..
-        } = self.render_state.borrow().font_ctx.cell_metrics();
+        } = render_state.font_ctx.cell_metrics();
+        let padding = render_state.font_ctx.padding();
         let alloc = self.drawing_area.get_allocation();
+        let width = (f64::from(alloc.width) - padding.left - padding.right).max(0.0);
+        let height = (f64::from(alloc.height) - padding.top - padding.bottom).max(0.0);
         (
-            (alloc.width as f64 / char_width).trunc() as usize,
-            (alloc.height as f64 / line_height).trunc() as usize,
+            (width / char_width).trunc() as usize,
+            (height / line_height).trunc() as usize,
         )
     }
 
@@ -424,8 +754,11 @@ impl State {
 
     fn set_im_location(&self) {
         if let Some((row, col)) = self.grids.current().map(|g| g.get_cursor()) {
-            let (x, y, width, height) = ModelRect::point(col, row)
-                .to_area(self.render_state.borrow().font_ctx.cell_metrics());
+            let render_state = self.render_state.borrow();
+            let (x, y, width, height) = render_state
+                .font_ctx
+                .padding()
+                .shift(ModelRect::point(col, row).to_area(render_state.font_ctx.cell_metrics()));
 
             self.im_context.set_cursor_location(&gdk::Rectangle {
                 x,
@@ -520,6 +853,10 @@ impl State {
             .set_autocmds(&mut self.nvim().unwrap());
     }
 
+    fn clear_autocmds(&self, nvim: &mut NeovimRef) {
+        self.subscriptions.borrow().clear_autocmds(nvim);
+    }
+
     pub fn notify(&self, params: Vec<Value>) -> Result<(), String> {
         self.subscriptions.borrow().notify(params)
     }
@@ -582,11 +919,13 @@ impl State {
     }
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 enum MouseCursor {
     None,
     Text,
     Default,
+    // gdk cursor name from mode mouse_shape
+    Shape(&'static str),
 }
 
 pub struct UiState {
@@ -595,6 +934,8 @@ pub struct UiState {
 
     // previous editor position (col, row)
     prev_pos: (u64, u64),
+    // grid of mouse press, drag and release go to the same grid
+    mouse_grid: Option<u64>,
 
     mouse_cursor: MouseCursor,
 }
@@ -605,6 +946,7 @@ impl UiState {
             mouse_pressed: false,
             scroll_delta: (0.0, 0.0),
             prev_pos: (0, 0),
+            mouse_grid: None,
 
             mouse_cursor: MouseCursor::None,
         }
@@ -622,6 +964,7 @@ impl UiState {
                 MouseCursor::Default => "default",
                 MouseCursor::None => "none",
                 MouseCursor::Text => "text",
+                MouseCursor::Shape(name) => name,
             };
 
             window.set_cursor(gdk::Cursor::new_from_name(&window.get_display(), cursor).as_ref());
@@ -632,10 +975,17 @@ impl UiState {
 #[derive(Clone)]
 pub struct ShellOptions {
     nvim_bin_path: Option<String>,
+    // address of running nvim to attach to
+    server: Option<String>,
+    // attach to server of current project
+    persistent: bool,
+    // embedded nvim on other host
+    remote: Option<RemoteProfile>,
     timeout: Option<Duration>,
     args_for_neovim: Vec<String>,
     input_data: Option<String>,
     cterm_colors: bool,
+    ext_messages: bool,
 }
 
 impl ShellOptions {
@@ -643,7 +993,11 @@ impl ShellOptions {
         ShellOptions {
             input_data,
             cterm_colors: matches.is_present("cterm-colors"),
+            ext_messages: !matches.is_present("no-ext-messages"),
             nvim_bin_path: matches.value_of("nvim-bin-path").map(str::to_owned),
+            server: matches.value_of("server").map(str::to_owned),
+            persistent: matches.is_present("persistent"),
+            remote: matches.value_of("remote").map(RemoteProfile::find),
             timeout: value_t!(matches.value_of("timeout"), u64)
                 .map(Duration::from_secs)
                 .ok(),
@@ -654,6 +1008,13 @@ impl ShellOptions {
         }
     }
 
+    pub fn with_server(mut self, server: String) -> Self {
+        self.server = Some(server);
+        self.persistent = false;
+        self.remote = None;
+        self
+    }
+
     // remove input data from original
     // shell option, as it need to be used only once
     pub fn take(&mut self) -> Self {
@@ -684,9 +1045,26 @@ impl Shell {
         let shell_ref = Arc::downgrade(&shell.state);
         shell.state.borrow_mut().cursor = Some(BlinkCursor::new(shell_ref));
 
+        let shell_ref = Arc::downgrade(&shell.state);
+        shell
+            .state
+            .borrow_mut()
+            .float_windows
+            .set_draw_cb(move |grid, ctx| {
+                if let Some(state) = shell_ref.upgrade() {
+                    draw_float(&state.borrow(), grid, ctx);
+                }
+            });
+
         shell
     }
 
+    /// Nvim is not started by ui, but attached to running server
+    pub fn is_attached_server(&self) -> bool {
+        let options = &self.state.borrow().options;
+        options.server.is_some() || options.persistent
+    }
+
     pub fn is_nvim_initialized(&self) -> bool {
         let state = self.state.borrow();
         state.nvim.is_initialized()
@@ -701,7 +1079,10 @@ impl Shell {
 
         state.im_context.set_use_preedit(false);
 
-        state.preview_splitter.pack1(&state.drawing_area, true, false);
+        state
+            .float_windows
+            .add_overlay(state.messages.toast_widget());
+        state.preview_splitter.pack1(&*state.float_windows, true, false);
         state.preview_splitter.pack2(&*state.preview, true, false);
         state.preview_splitter.set_position(state.preview_splitter.get_allocated_width() - 600);
 
@@ -712,12 +1093,32 @@ impl Shell {
             state_ref.borrow().preview.refresh();
         }));
 
+        // nvim does not send language mapping state, so lCursor
+        // color follows iminsert on insert start and on option change
+        let state_ref = self.state.clone();
+        let set_langmap = move |args: Vec<String>| {
+            let langmap = args.first().map_or(false, |iminsert| iminsert == "1");
+            // state is borrowed while subscription callbacks run
+            let state_ref = state_ref.clone();
+            gtk::idle_add(move || {
+                state_ref.borrow_mut().set_langmap(langmap);
+                Continue(false)
+            });
+        };
+        state.subscribe(SubscriptionKey::from("InsertEnter"), &["&iminsert"], set_langmap.clone());
+        state.subscribe(
+            SubscriptionKey::with_pattern("OptionSet", "iminsert"),
+            &["&iminsert"],
+            set_langmap,
+        );
+
         let state_ref = &self.state;
-        state.subscribe(SubscriptionKey::from("CursorMoved"), &["line('.')"], clone!(state_ref => move |args| {
+        state.subscribe(SubscriptionKey::from("CursorMoved"), &["line('.')", "&buflisted"], clone!(state_ref => move |args| {
             let mut args = args.into_iter();
             if let Some(num) = args.next() {
                 if let Ok(line_number) = num.parse::<usize>() {
-                    state_ref.borrow().preview.refresh_scroll(line_number - 1);
+                    let listed = args.next().map_or(false, |listed| listed == "1");
+                    state_ref.borrow().preview.refresh_scroll(line_number - 1, listed);
                 }
             }
         }));
@@ -738,11 +1139,27 @@ impl Shell {
                 | gdk::EventMask::BUTTON_MOTION_MASK
                 | gdk::EventMask::SCROLL_MASK
                 | gdk::EventMask::SMOOTH_SCROLL_MASK
+                | gdk::EventMask::TOUCHPAD_GESTURE_MASK
                 | gdk::EventMask::ENTER_NOTIFY_MASK
                 | gdk::EventMask::LEAVE_NOTIFY_MASK
                 | gdk::EventMask::POINTER_MOTION_MASK,
         );
 
+        let ref_state = self.state.clone();
+        let zoom_begin = Rc::new(Cell::new(0));
+        state
+            .zoom_gesture
+            .connect_begin(clone!(ref_state, zoom_begin => move |_, _| {
+                zoom_begin.set(ref_state.borrow().font_zoom());
+            }));
+        state.zoom_gesture.connect_scale_changed(move |_, scale| {
+            let mut state = ref_state.borrow_mut();
+            // pinch scale is applied to font size
+            let font_size = f64::from(state.font_desc.get_size()) / f64::from(pango::SCALE);
+            let zoom = zoom_begin.get() + (font_size * (scale - 1.0)).round() as i32;
+            state.set_font_zoom(zoom);
+        });
+
         let menu = self.create_context_menu();
         let ref_state = self.state.clone();
         let ref_ui_state = self.ui_state.clone();
@@ -830,6 +1247,15 @@ impl Shell {
             .drawing_area
             .connect_focus_out_event(move |_, _| gtk_focus_out(&mut *ref_state.borrow_mut()));
 
+        let ref_state = self.state.clone();
+        state
+            .drawing_area
+            .connect_property_scale_factor_notify(move |w| {
+                ref_state
+                    .borrow_mut()
+                    .set_scale_factor(f64::from(w.get_scale_factor()));
+            });
+
         let ref_state = self.state.clone();
         state.drawing_area.connect_realize(move |w| {
             // sometime set_client_window does not work without idle_add
@@ -840,6 +1266,10 @@ impl Shell {
                 );
                 Continue(false)
             }));
+
+            ref_state
+                .borrow_mut()
+                .set_scale_factor(f64::from(w.get_scale_factor()));
         });
 
         let ref_state = self.state.clone();
@@ -884,14 +1314,25 @@ impl Shell {
                 );
                 let state = ref_state.borrow_mut();
                 let mut nvim = state.nvim().unwrap();
+                if let Some(host) = state.remote_host() {
+                    // local files can't be opened by nvim on other host
+                    let msg = format!(
+                        "Dropped files are not opened: nvim runs on {}, copy files there first",
+                        host
+                    );
+                    nvim.err_writeln_async(&msg).cb(|r| r.report_err()).call();
+                    return;
+                }
                 nvim.command_async(&command).cb(|r| r.report_err()).call()
             });
 
+        let ref_state = self.state.clone();
         let ui_state_ref = self.ui_state.clone();
         state.drawing_area.connect_enter_notify_event(move |_, ev| {
+            let mouse_cursor = ref_state.borrow().mouse_cursor();
             ui_state_ref
                 .borrow_mut()
-                .apply_mouse_cursor(MouseCursor::Text, ev.get_window());
+                .apply_mouse_cursor(mouse_cursor, ev.get_window());
             gtk::Inhibit(false)
         });
 
@@ -944,7 +1385,13 @@ impl Shell {
 
         let nvim = state.nvim();
         if let Some(mut nvim) = nvim {
-            nvim.ui_detach().expect("Error in ui_detach");
+            // attached server keeps running, so nothing must notify closed channel
+            state.clear_autocmds(&mut nvim);
+            nvim.call_function_async("GtkGuiDetach", vec![])
+                .cb(|r| r.report_err())
+                .call();
+            // wait for detach, so server does not see closed channel before it
+            nvim.blocking().ui_detach().report_err();
         }
     }
 
@@ -994,6 +1441,27 @@ impl Shell {
         state.set_nvim_command_cb(cb);
     }
 
+    pub fn set_font_zoom_cb<F>(&self, cb: Option<F>)
+    where
+        F: FnMut(i32) + Send + 'static,
+    {
+        let mut state = self.state.borrow_mut();
+        state.set_font_zoom_cb(cb);
+    }
+
+    pub fn messages_status_widget(&self) -> gtk::Box {
+        self.state.borrow().messages.status_widget().clone()
+    }
+
+    pub fn show_messages_history(&self) {
+        let state = self.state.borrow();
+        let render_state = state.render_state.borrow();
+        state.messages.show_history(
+            &render_state.hl,
+            render_state.font_ctx.font_description(),
+        );
+    }
+
     pub fn set_completeopts(&self, options: &str) {
         self.state
             .borrow()
@@ -1038,26 +1506,60 @@ fn gtk_focus_out(state: &mut State) -> Inhibit {
     Inhibit(false)
 }
 
+/// Font with size changed by `zoom` points, size does not go below `MIN_FONT_SIZE`
+fn zoom_font_desc(font_desc: &FontDescription, zoom: i32) -> FontDescription {
+    let mut font_desc = font_desc.clone();
+    if zoom != 0 {
+        let size = (font_desc.get_size() + zoom * pango::SCALE).max(MIN_FONT_SIZE * pango::SCALE);
+        if font_desc.get_size_is_absolute() {
+            font_desc.set_absolute_size(f64::from(size));
+        } else {
+            font_desc.set_size(size);
+        }
+    }
+    font_desc
+}
+
+/// Ctrl+scroll changes font zoom
+fn gtk_zoom_scroll_event(state: &mut State, ui_state: &mut UiState, ev: &EventScroll) -> Inhibit {
+    match ev.get_direction() {
+        gdk::ScrollDirection::Up => state.zoom_in(),
+        gdk::ScrollDirection::Down => state.zoom_out(),
+        gdk::ScrollDirection::Smooth => {
+            ui_state.scroll_delta.1 += ev.as_ref().delta_y;
+            let y = ui_state.scroll_delta.1 as i32;
+            if y != 0 {
+                state.set_font_zoom(state.font_zoom() - y);
+                ui_state.scroll_delta.1 -= f64::from(y);
+            }
+
+            if ev.get_is_stop() {
+                ui_state.scroll_delta = (0.0, 0.0);
+            }
+        }
+        _ => (),
+    }
+
+    Inhibit(true)
+}
+
 fn gtk_scroll_event(state: &mut State, ui_state: &mut UiState, ev: &EventScroll) -> Inhibit {
+    if ev.get_state().contains(ModifierType::CONTROL_MASK) {
+        return gtk_zoom_scroll_event(state, ui_state, ev);
+    }
+
     if !state.mouse_enabled && !state.nvim.is_initializing() {
         return Inhibit(false);
     }
 
     state.close_popup_menu();
 
+    let pos = mouse_grid_position(state, ev.get_position(), None);
     match ev.get_direction() {
-        gdk::ScrollDirection::Right => {
-            mouse_input(state, "ScrollWheelRight", ev.get_state(), ev.get_position())
-        }
-        gdk::ScrollDirection::Left => {
-            mouse_input(state, "ScrollWheelLeft", ev.get_state(), ev.get_position())
-        }
-        gdk::ScrollDirection::Up => {
-            mouse_input(state, "ScrollWheelUp", ev.get_state(), ev.get_position())
-        }
-        gdk::ScrollDirection::Down => {
-            mouse_input(state, "ScrollWheelDown", ev.get_state(), ev.get_position())
-        }
+        gdk::ScrollDirection::Right => mouse_input(state, "wheel", "right", ev.get_state(), pos),
+        gdk::ScrollDirection::Left => mouse_input(state, "wheel", "left", ev.get_state(), pos),
+        gdk::ScrollDirection::Up => mouse_input(state, "wheel", "up", ev.get_state(), pos),
+        gdk::ScrollDirection::Down => mouse_input(state, "wheel", "down", ev.get_state(), pos),
         gdk::ScrollDirection::Smooth => {
             // Remember and accumulate scroll deltas, so slow scrolling still
             // works.
@@ -1067,20 +1569,35 @@ fn gtk_scroll_event(state: &mut State, ui_state: &mut UiState, ev: &EventScroll)
             let x = ui_state.scroll_delta.0 as isize;
             let y = ui_state.scroll_delta.1 as isize;
             for _ in 0..x {
-                mouse_input(state, "ScrollWheelRight", ev.get_state(), ev.get_position())
+                mouse_input(state, "wheel", "right", ev.get_state(), pos)
             }
             for _ in 0..-x {
-                mouse_input(state, "ScrollWheelLeft", ev.get_state(), ev.get_position())
+                mouse_input(state, "wheel", "left", ev.get_state(), pos)
             }
             for _ in 0..y {
-                mouse_input(state, "ScrollWheelDown", ev.get_state(), ev.get_position())
+                mouse_input(state, "wheel", "down", ev.get_state(), pos)
             }
             for _ in 0..-y {
-                mouse_input(state, "ScrollWheelUp", ev.get_state(), ev.get_position())
+                mouse_input(state, "wheel", "up", ev.get_state(), pos)
             }
             // Subtract performed scroll deltas.
             ui_state.scroll_delta.0 -= x as f64;
             ui_state.scroll_delta.1 -= y as f64;
+
+            // Show rest of delta as sub row shift of grid under pointer
+            if ev.get_is_stop() {
+                ui_state.scroll_delta = (0.0, 0.0);
+                state.smooth_scroll.reset_nudge();
+            } else {
+                let (col, row) = mouse_coordinates_to_nvim(state, ev.get_position());
+                if let Some(grid) = state.grids.grid_at(row as usize, col as usize) {
+                    let line_height =
+                        state.render_state.borrow().font_ctx.cell_metrics().line_height;
+                    state
+                        .smooth_scroll
+                        .set_nudge(grid, -ui_state.scroll_delta.1 * line_height);
+                }
+            }
         }
         _ => (),
     }
@@ -1098,11 +1615,16 @@ fn gtk_button_press(
     }
 
     if shell.mouse_enabled {
-        ui_state.borrow_mut().mouse_pressed = true;
+        let pos = mouse_grid_position(shell, ev.get_position(), None);
+        {
+            let mut ui_state = ui_state.borrow_mut();
+            ui_state.mouse_pressed = true;
+            ui_state.mouse_grid = Some(pos.0);
+        }
 
         match ev.get_button() {
-            1 => mouse_input(shell, "LeftMouse", ev.get_state(), ev.get_position()),
-            2 => mouse_input(shell, "MiddleMouse", ev.get_state(), ev.get_position()),
+            1 => mouse_input(shell, "left", "press", ev.get_state(), pos),
+            2 => mouse_input(shell, "middle", "press", ev.get_state(), pos),
             3 => menu.popup_at_pointer(None),
 
             _ => (),
@@ -1111,39 +1633,78 @@ fn gtk_button_press(
     Inhibit(false)
 }
 
-fn mouse_input(shell: &mut State, input: &str, state: ModifierType, position: (f64, f64)) {
+/// Send mouse event to grid, `pos` is (grid, row, col) in grid cells
+fn mouse_input(
+    shell: &mut State,
+    button: &str,
+    action: &str,
+    state: ModifierType,
+    pos: (u64, u64, u64),
+) {
     if let Some(mut nvim) = shell.try_nvim() {
-        let (col, row) = mouse_coordinates_to_nvim(shell, position);
-        let input_str = format!("{}<{},{}>", keyval_to_input_string(input, state), col, row);
-
-        nvim.input(&input_str)
-            .expect("Can't send mouse input event");
+        let (grid, row, col) = pos;
+        nvim.session
+            .call_async::<Value>(
+                "nvim_input_mouse",
+                vec![
+                    Value::from(button),
+                    Value::from(action),
+                    Value::from(input::mouse_modifiers(state)),
+                    Value::from(grid),
+                    Value::from(row),
+                    Value::from(col),
+                ],
+            )
+            .cb(|r| r.report_err())
+            .call();
     }
 }
 
+/// Grid under pointer and pointer position in its cells (grid, row, col)
+///
+/// If `grid` is given, position is relative to it, so drag keeps
+/// going to grid where mouse was pressed.
+fn mouse_grid_position(shell: &State, position: (f64, f64), grid: Option<u64>) -> (u64, u64, u64) {
+    let (col, row) = mouse_coordinates_to_nvim(shell, position);
+    let grid = grid
+        .or_else(|| shell.grids.input_grid_at(row as usize, col as usize))
+        .unwrap_or(grid::DEFAULT_GRID);
+    let (grid_row, grid_col) = shell.grids.get(grid).map_or((0, 0), |g| g.position());
+
+    (
+        grid,
+        row.saturating_sub(grid_row as u64),
+        col.saturating_sub(grid_col as u64),
+    )
+}
+
 /**
  * Translate gtk mouse event coordinates to nvim (col, row).
  */
 fn mouse_coordinates_to_nvim(shell: &State, position: (f64, f64)) -> (u64, u64) {
+    let render_state = shell.render_state.borrow();
     let &CellMetrics {
         line_height,
         char_width,
         ..
-    } = shell.render_state.borrow().font_ctx.cell_metrics();
+    } = render_state.font_ctx.cell_metrics();
+    let padding = render_state.font_ctx.padding();
     let (x, y) = position;
-    let col = (x / char_width).trunc() as u64;
-    let row = (y / line_height).trunc() as u64;
+    let col = ((x - padding.left).max(0.0) / char_width).trunc() as u64;
+    let row = ((y - padding.top).max(0.0) / line_height).trunc() as u64;
     (col, row)
 }
 
 fn gtk_button_release(shell: &mut State, ui_state: &mut UiState, ev: &EventButton) -> Inhibit {
     ui_state.mouse_pressed = false;
+    let grid = ui_state.mouse_grid.take();
 
     if shell.mouse_enabled && !shell.nvim.is_initializing() {
+        let pos = mouse_grid_position(shell, ev.get_position(), grid);
         match ev.get_button() {
-            1 => mouse_input(shell, "LeftRelease", ev.get_state(), ev.get_position()),
-            2 => mouse_input(shell, "MiddleRelease", ev.get_state(), ev.get_position()),
-            3 => mouse_input(shell, "RightRelease", ev.get_state(), ev.get_position()),
+            1 => mouse_input(shell, "left", "release", ev.get_state(), pos),
+            2 => mouse_input(shell, "middle", "release", ev.get_state(), pos),
+            3 => mouse_input(shell, "right", "release", ev.get_state(), pos),
             _ => (),
         }
     }
@@ -1159,17 +1720,51 @@ fn gtk_motion_notify(shell: &mut State, ui_state: &mut UiState, ev: &EventMotion
         // if we fire LeftDrag on the same coordinates multiple times, then
         // we get: https://github.com/daa84/neovim-gtk/issues/185
         if pos != ui_state.prev_pos {
-            mouse_input(shell, "LeftDrag", ev.get_state(), ev_pos);
+            let grid_pos = mouse_grid_position(shell, ev_pos, ui_state.mouse_grid);
+            mouse_input(shell, "left", "drag", ev.get_state(), grid_pos);
             ui_state.prev_pos = pos;
         }
     }
 
-    ui_state.apply_mouse_cursor(MouseCursor::Text, shell.drawing_area.get_window());
+    ui_state.apply_mouse_cursor(shell.mouse_cursor(), shell.drawing_area.get_window());
     Inhibit(false)
 }
 
+/// Paint background image over default background, image is placed
+/// relative to drawing area whatever current transformation is
+fn draw_background_image(state: &State, ctx: &cairo::Context, base_matrix: &cairo::Matrix) {
+    if let Some(ref image) = state.background_image {
+        let alloc = state.drawing_area.get_allocation();
+
+        ctx.save();
+        ctx.set_matrix(*base_matrix);
+        image.paint(ctx, f64::from(alloc.width), f64::from(alloc.height));
+        ctx.restore();
+    }
+}
+
+fn choose_color(window: Option<&gtk::Window>, literal: &ColorLiteral) -> Option<String> {
+    let dlg = gtk::ColorChooserDialog::new(Some("Pick Color"), window);
+    dlg.set_use_alpha(literal.notation == ColorNotation::Rgba);
+
+    let mut rgba = gdk::RGBA::from(&literal.color);
+    rgba.alpha = literal.alpha;
+    dlg.set_rgba(&rgba);
+
+    let replacement = if dlg.run() == gtk::ResponseType::Ok {
+        let rgba = dlg.get_rgba();
+        Some(literal.format(&Color(rgba.red, rgba.green, rgba.blue), rgba.alpha))
+    } else {
+        None
+    };
+    dlg.destroy();
+
+    replacement
+}
+
 fn draw_content(state: &State, ctx: &cairo::Context) {
     ctx.push_group();
+    let base_matrix = ctx.get_matrix();
 
     let render_state = state.render_state.borrow();
     render::fill_background(
@@ -1177,19 +1772,224 @@ fn draw_content(state: &State, ctx: &cairo::Context) {
         &render_state.hl,
         state.transparency_settings.background_alpha(),
     );
-    render::render(
+    draw_background_image(state, ctx, &base_matrix);
+
+    let cell_metrics = render_state.font_ctx.cell_metrics();
+    let current_grid = state.grids.current_grid_id();
+
+    // padding is filled with default background
+    let padding = render_state.font_ctx.padding();
+    ctx.translate(padding.left, padding.top);
+
+    for (idx, grid) in state.grids.visible_grids() {
+        // floats are painted by float windows
+        if grid.rows() == 0 || grid.columns() == 0 || grid.kind() == GridKind::Float {
+            continue;
+        }
+
+        let (row, col) = grid.position();
+        let (x, y, width, height) = grid.area().to_area(cell_metrics);
+
+        ctx.save();
+        ctx.rectangle(x as f64, y as f64, width as f64, height as f64);
+        ctx.clip();
+
+        let (x1, y1, x2, y2) = ctx.clip_extents();
+        if x1 < x2 && y1 < y2 {
+            // grids are painted over each other, so other grids
+            // must not be visible through default colored cells
+            if idx != grid::DEFAULT_GRID {
+                ctx.set_operator(cairo::Operator::Source);
+                render::fill_background(
+                    ctx,
+                    &render_state.hl,
+                    state.transparency_settings.background_alpha(),
+                );
+                ctx.set_operator(cairo::Operator::Over);
+                draw_background_image(state, ctx, &base_matrix);
+            }
+
+            ctx.translate(
+                col as f64 * cell_metrics.char_width,
+                row as f64 * cell_metrics.line_height,
+            );
+
+            let render_grid = |ctx: &cairo::Context| {
+                if idx == current_grid {
+                    render::render(
+                        ctx,
+                        state.cursor.as_ref().unwrap(),
+                        &render_state.font_ctx,
+                        grid.model(),
+                        &render_state.hl,
+                        state.transparency_settings.filled_alpha(),
+                    );
+                } else {
+                    render::render(
+                        ctx,
+                        &EmptyCursor::new(),
+                        &render_state.font_ctx,
+                        grid.model(),
+                        &render_state.hl,
+                        state.transparency_settings.filled_alpha(),
+                    );
+                }
+
+                if state.color_swatches {
+                    render::draw_color_swatches(
+                        ctx,
+                        &render_state.font_ctx,
+                        grid.model(),
+                        &render_state.hl,
+                    );
+                }
+            };
+
+            match state.smooth_scroll.offset(idx) {
+                Some(scroll) => {
+                    draw_scrolled(state, ctx, &base_matrix, grid, &scroll, render_grid)
+                }
+                None => render_grid(ctx),
+            }
+        }
+
+        ctx.restore();
+    }
+
+    let bell_alpha = state.visual_bell.alpha();
+    if bell_alpha > 0.0 {
+        let fg = render_state.hl.fg();
+        ctx.save();
+        ctx.set_matrix(base_matrix);
+        ctx.set_source_rgba(fg.0, fg.1, fg.2, bell_alpha);
+        ctx.paint();
+        ctx.restore();
+    }
+
+    ctx.pop_group_to_source();
+    ctx.paint();
+}
+
+/// Draw grid with scroll region shifted by pixel offset,
+/// ctx must be translated to grid position
+fn draw_scrolled<F>(
+    state: &State,
+    ctx: &cairo::Context,
+    base_matrix: &cairo::Matrix,
+    grid: &grid::Grid,
+    scroll: &ScrollOffset,
+    render_grid: F,
+) where
+    F: Fn(&cairo::Context),
+{
+    let render_state = state.render_state.borrow();
+    let cell_metrics = render_state.font_ctx.cell_metrics();
+
+    let region = scroll.region.unwrap_or_else(|| {
+        ScrollRegion::new(0, grid.rows() as u64, 0, grid.columns() as u64)
+    });
+    let (x, y, width, height) = region.to_area(cell_metrics);
+    let (_, _, grid_width, grid_height) = grid.area().to_area(cell_metrics);
+
+    // part of grid outside of scroll region
+    ctx.save();
+    ctx.set_fill_rule(cairo::FillRule::EvenOdd);
+    ctx.rectangle(0.0, 0.0, f64::from(grid_width), f64::from(grid_height));
+    ctx.rectangle(x, y, width, height);
+    ctx.clip();
+    render_grid(ctx);
+    ctx.restore();
+
+    ctx.save();
+    ctx.rectangle(x, y, width, height);
+    ctx.clip();
+
+    ctx.set_operator(cairo::Operator::Source);
+    render::fill_background(
         ctx,
-        state.cursor.as_ref().unwrap(),
-        &render_state.font_ctx,
-        state.grids.current_model().unwrap(),
         &render_state.hl,
-        state.transparency_settings.filled_alpha(),
+        state.transparency_settings.background_alpha(),
     );
+    ctx.set_operator(cairo::Operator::Over);
+    draw_background_image(state, ctx, base_matrix);
+
+    ctx.save();
+    ctx.translate(0.0, scroll.offset);
+    render_grid(ctx);
+    ctx.restore();
+
+    if let Some((ref strip, strip_y)) = scroll.strip {
+        ctx.set_source_surface(strip, x, y + strip_y);
+        ctx.paint();
+    }
+
+    ctx.restore();
+}
+
+fn draw_float(state: &State, idx: u64, ctx: &cairo::Context) {
+    let grid = match state.grids.get(idx) {
+        Some(grid) if state.nvim.is_initialized() && grid.rows() > 0 => grid,
+        _ => return,
+    };
+
+    let render_state = state.render_state.borrow();
+    let cell_metrics = render_state.font_ctx.cell_metrics();
+    let (_, _, width, height) = grid.area().to_area(cell_metrics);
+
+    ctx.push_group();
+
+    rounded_rectangle(ctx, f64::from(width), f64::from(height), float_win::FLOAT_RADIUS);
+    ctx.clip();
+
+    // winblend: cells paint own translucent background
+    // and main drawing area is visible under float
+    if !grid.model().is_blended() {
+        render::fill_background(
+            ctx,
+            &render_state.hl,
+            state.transparency_settings.background_alpha(),
+        );
+    }
+
+    if idx == state.grids.current_grid_id() {
+        render::render(
+            ctx,
+            state.cursor.as_ref().unwrap(),
+            &render_state.font_ctx,
+            grid.model(),
+            &render_state.hl,
+            state.transparency_settings.filled_alpha(),
+        );
+    } else {
+        render::render(
+            ctx,
+            &EmptyCursor::new(),
+            &render_state.font_ctx,
+            grid.model(),
+            &render_state.hl,
+            state.transparency_settings.filled_alpha(),
+        );
+    }
+
+    if state.color_swatches {
+        render::draw_color_swatches(ctx, &render_state.font_ctx, grid.model(), &render_state.hl);
+    }
 
     ctx.pop_group_to_source();
     ctx.paint();
 }
 
+fn rounded_rectangle(ctx: &cairo::Context, width: f64, height: f64, radius: f64) {
+    use std::f64::consts::PI;
+
+    ctx.new_sub_path();
+    ctx.arc(width - radius, radius, radius, -PI / 2.0, 0.0);
+    ctx.arc(width - radius, height - radius, radius, 0.0, PI / 2.0);
+    ctx.arc(radius, height - radius, radius, PI / 2.0, PI);
+    ctx.arc(radius, radius, radius, PI, 3.0 * PI / 2.0);
+    ctx.close_path();
+}
+
 fn gtk_draw(state_arc: &Arc<UiMutex<State>>, ctx: &cairo::Context) -> Inhibit {
     let state = state_arc.borrow();
     if state.nvim.is_initialized() {
@@ -1235,13 +2035,24 @@ fn init_nvim_async(
     cols: usize,
     rows: usize,
 ) {
-    // execute nvim
-    let nvim = match nvim::start(
-        nvim_handler,
-        options.nvim_bin_path.as_ref(),
-        options.timeout,
-        options.args_for_neovim,
-    ) {
+    // execute nvim or connect to running one
+    let nvim = match options.server {
+        Some(ref server) => nvim::connect(nvim_handler, server, options.timeout),
+        None if options.persistent => start_persistent(nvim_handler, &options),
+        None if options.remote.is_some() => nvim::start_remote(
+            nvim_handler,
+            options.remote.as_ref().unwrap(),
+            options.timeout,
+            options.args_for_neovim,
+        ),
+        None => nvim::start(
+            nvim_handler,
+            options.nvim_bin_path.as_ref(),
+            options.timeout,
+            options.args_for_neovim,
+        ),
+    };
+    let nvim = match nvim {
         Ok(nvim) => nvim,
         Err(err) => {
             show_nvim_start_error(&err, state_arc);
@@ -1268,13 +2079,46 @@ fn init_nvim_async(
     });
 
     // attach ui
-    if let Err(err) = nvim::post_start_init(nvim, cols as i64, rows as i64, options.input_data) {
+    if let Err(err) = nvim::post_start_init(
+        nvim,
+        cols as i64,
+        rows as i64,
+        options.input_data,
+        options.ext_messages,
+    ) {
         show_nvim_init_error(&err, state_arc.clone());
     } else {
         set_nvim_initialized(state_arc);
     }
 }
 
+/// Attach to server of current project, server is started if it is not running
+fn start_persistent(
+    nvim_handler: NvimHandler,
+    options: &ShellOptions,
+) -> Result<Neovim, nvim::NvimInitError> {
+    let project = env::current_dir().map_err(nvim::NvimInitError::new_post_init)?;
+    let socket = sessions::socket_path(&project).map_err(nvim::NvimInitError::new_post_init)?;
+
+    if !sessions::is_alive(&socket) {
+        nvim::start_server(
+            options.nvim_bin_path.as_ref(),
+            &socket,
+            options.args_for_neovim.clone(),
+            options.timeout.unwrap_or(Duration::from_secs(10)),
+        )?;
+    } else if !options.args_for_neovim.is_empty() {
+        // files are opened by ui, other nvim arguments can't be applied to running server
+        warn!(
+            "Server of {} is running, nvim arguments {:?} are ignored",
+            project.display(),
+            options.args_for_neovim
+        );
+    }
+
+    nvim::connect(nvim_handler, &socket.to_string_lossy(), options.timeout)
+}
+
 fn set_nvim_to_state(state_arc: Arc<UiMutex<State>>, nvim: Neovim) -> NeovimClientAsync {
     let pair = Arc::new((Mutex::new(None), Condvar::new()));
     let pair2 = pair.clone();
@@ -1370,32 +2214,210 @@ impl State {
         col_start: u64,
         cells: Vec<Vec<Value>>,
     ) -> RepaintMode {
-        let hl = &self.render_state.borrow().hl;
-        let repaint_area = self.grids[grid].line(row as usize, col_start as usize, cells, hl);
-        RepaintMode::Area(repaint_area)
+        let row = row as usize;
+        let had_literal = self.has_color_literal(grid, row);
+        let mut repaint_area = {
+            let hl = &self.render_state.borrow().hl;
+            self.grids[grid].line(row, col_start as usize, cells, hl)
+        };
+
+        // color swatch can be drawn outside of changed cells
+        if had_literal || self.has_color_literal(grid, row) {
+            let grid = &self.grids[grid];
+            repaint_area.join(&grid.to_global(ModelRect::new(row, row, 0, grid.columns().saturating_sub(1))));
+        }
+
+        self.grid_repaint(grid, RepaintMode::Area(repaint_area))
+    }
+
+    /// Row shows color swatch
+    fn has_color_literal(&self, grid: u64, row: usize) -> bool {
+        self.color_swatches
+            && self.grids[grid]
+                .model()
+                .model()
+                .get(row)
+                .map_or(false, render::has_color_literal)
     }
 
     pub fn grid_clear(&mut self, grid: u64) -> RepaintMode {
-        let hl = &self.render_state.borrow().hl;
-        self.grids[grid].clear(&hl.default_hl());
-        RepaintMode::All
+        {
+            let hl = &self.render_state.borrow().hl;
+            self.grids[grid].clear(&hl.default_hl());
+        }
+        self.grid_repaint(grid, RepaintMode::All)
     }
 
     pub fn grid_destroy(&mut self, grid: u64) -> RepaintMode {
         self.grids.destroy(grid);
+        self.float_windows.destroy(grid);
         RepaintMode::All
     }
 
+    /// Repaint of floating grids goes to its own float window
+    fn grid_repaint(&mut self, grid: u64, mode: RepaintMode) -> RepaintMode {
+        if self.float_windows.contains(grid) {
+            let render_state = self.render_state.borrow();
+            render::shape_dirty(
+                &render_state.font_ctx,
+                self.grids[grid].model_mut(),
+                &render_state.hl,
+            );
+            self.float_windows.queue_draw(grid);
+            RepaintMode::Nothing
+        } else {
+            mode
+        }
+    }
+
+    fn show_float(&mut self, grid: u64) {
+        if let Some(grid_model) = self.grids.get(grid) {
+            let render_state = self.render_state.borrow();
+            let font_ctx = &render_state.font_ctx;
+            let (x, y, width, height) = font_ctx
+                .padding()
+                .shift(grid_model.area().to_area(font_ctx.cell_metrics()));
+            self.float_windows.show(grid, x, y, width, height);
+        }
+    }
+
+    /// Float widgets are placed in pixels, so they must be moved
+    /// after cell metrics or padding change
+    fn show_floats(&mut self) {
+        let floats: Vec<u64> = self
+            .grids
+            .visible_grids()
+            .into_iter()
+            .filter(|&(_, g)| g.kind() == GridKind::Float)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        for grid in floats {
+            self.show_float(grid);
+        }
+    }
+
     pub fn grid_cursor_goto(&mut self, grid: u64, row: u64, column: u64) -> RepaintMode {
-        let repaint_area = self.grids[grid].cursor_goto(row as usize, column as usize);
+        let prev_cursor = self.grids.current().map(|g| g.cur_point());
+
+        let prev_grid = self.grids.current_grid_id();
+        if prev_grid != grid {
+            self.float_windows.queue_draw(prev_grid);
+        }
+        self.float_windows.queue_draw(grid);
+
+        self.grids.set_current_grid(grid);
+        let mut repaint_area = self.grids[grid].cursor_goto(row as usize, column as usize);
+        if let Some(prev_cursor) = prev_cursor {
+            repaint_area.join(&prev_cursor);
+
+            let (cur_row, cur_col) = self.grids[grid].get_cursor();
+            let &CellMetrics {
+                line_height,
+                char_width,
+                ..
+            } = self.render_state.borrow().font_ctx.cell_metrics();
+            if let Some(cursor) = self.cursor.as_mut() {
+                cursor.move_from(
+                    (prev_cursor.left as f64 - cur_col as f64) * char_width,
+                    (prev_cursor.top as f64 - cur_row as f64) * line_height,
+                );
+            }
+        }
+
         self.set_im_location();
         RepaintMode::AreaList(repaint_area)
     }
 
+    pub fn win_pos(
+        &mut self,
+        grid: u64,
+        _win: Value,
+        start_row: u64,
+        start_col: u64,
+        _width: u64,
+        _height: u64,
+    ) -> RepaintMode {
+        // float can be converted to normal window
+        self.float_windows.destroy(grid);
+        self.grids.set_position(
+            grid,
+            start_row as usize,
+            start_col as usize,
+            GridKind::Window,
+            0,
+        );
+        RepaintMode::All
+    }
+
+    pub fn win_float_pos(
+        &mut self,
+        grid: u64,
+        anchor: &str,
+        anchor_grid: u64,
+        anchor_row: f64,
+        anchor_col: f64,
+        zindex: Option<u64>,
+    ) -> RepaintMode {
+        let (rows, columns) = match self.grids.get(grid) {
+            Some(grid) => (grid.rows() as f64, grid.columns() as f64),
+            None => return RepaintMode::Nothing,
+        };
+        let (anchor_grid_row, anchor_grid_col) = self
+            .grids
+            .get(anchor_grid)
+            .map(|g| g.position())
+            .unwrap_or((0, 0));
+
+        let row = match anchor {
+            "SW" | "SE" => anchor_row - rows,
+            _ => anchor_row,
+        };
+        let col = match anchor {
+            "NE" | "SE" => anchor_col - columns,
+            _ => anchor_col,
+        };
+
+        // anchor position can be fractional, position is rounded
+        // to nearest cell and kept inside screen
+        let to_cell = |anchor_pos: usize, pos: f64| (anchor_pos as f64 + pos).round().max(0.0) as usize;
+
+        self.grids.set_position(
+            grid,
+            to_cell(anchor_grid_row, row),
+            to_cell(anchor_grid_col, col),
+            GridKind::Float,
+            zindex.unwrap_or(50),
+        );
+        self.show_float(grid);
+        RepaintMode::All
+    }
+
+    pub fn msg_set_pos(&mut self, grid: u64, row: u64, _scrolled: bool, _sep_char: String) -> RepaintMode {
+        self.grids
+            .set_position(grid, row as usize, 0, GridKind::Message, 0);
+        RepaintMode::All
+    }
+
+    pub fn win_hide(&mut self, grid: u64) -> RepaintMode {
+        self.grids.hide(grid);
+        self.float_windows.hide(grid);
+        RepaintMode::All
+    }
+
+    pub fn win_close(&mut self, grid: u64) -> RepaintMode {
+        self.grids.hide(grid);
+        self.float_windows.hide(grid);
+        RepaintMode::All
+    }
+
     pub fn grid_resize(&mut self, grid: u64, columns: u64, rows: u64) -> RepaintMode {
         debug!("on_resize {}/{}", columns, rows);
 
         self.grids.get_or_create(grid).resize(columns, rows);
+        if self.float_windows.contains(grid) {
+            self.show_float(grid);
+        }
         RepaintMode::Nothing
     }
 
@@ -1404,6 +2426,7 @@ impl State {
             RepaintMode::All => {
                 self.update_dirty_glyphs();
                 self.drawing_area.queue_draw();
+                self.float_windows.queue_draw_all();
             }
             RepaintMode::Area(ref rect) => self.queue_draw_area(&[rect]),
             RepaintMode::AreaList(ref list) => self.queue_draw_area(&list.list),
@@ -1421,16 +2444,54 @@ impl State {
         rows: i64,
         cols: i64,
     ) -> RepaintMode {
-        let hl = &self.render_state.borrow().hl;
-        RepaintMode::Area(self.grids[grid].scroll(
-            top,
-            bot,
-            left,
-            right,
-            rows,
-            cols,
-            &hl.default_hl(),
-        ))
+        let region = ScrollRegion::new(top, bot, left, right);
+        if cols == 0 && rows != 0 && rows.unsigned_abs() < bot - top {
+            self.start_smooth_scroll(grid, region, rows);
+        }
+
+        let repaint_area = {
+            let hl = &self.render_state.borrow().hl;
+            self.grids[grid].scroll(top, bot, left, right, rows, cols, &hl.default_hl())
+        };
+        self.grid_repaint(grid, RepaintMode::Area(repaint_area))
+    }
+
+    /// Keep rows that leave scroll region, so they can be
+    /// shown while scroll is animated
+    fn start_smooth_scroll(&mut self, grid: u64, region: ScrollRegion, rows: i64) {
+        // floats are painted by own drawing areas
+        if self.float_windows.contains(grid) {
+            return;
+        }
+
+        let render_state = self.render_state.borrow();
+        let cell_metrics = render_state.font_ctx.cell_metrics();
+
+        let grid_model = match self.grids.get_mut(grid) {
+            Some(g) => g.model_mut(),
+            None => return,
+        };
+        render::shape_dirty(&render_state.font_ctx, grid_model, &render_state.hl);
+
+        if let Some((strip, ctx)) = smooth_scroll::create_strip(region, rows, cell_metrics) {
+            render::fill_background(
+                &ctx,
+                &render_state.hl,
+                self.transparency_settings.background_alpha(),
+            );
+            render::render(
+                &ctx,
+                &EmptyCursor::new(),
+                &render_state.font_ctx,
+                grid_model,
+                &render_state.hl,
+                self.transparency_settings.filled_alpha(),
+            );
+            drop(ctx);
+
+            self.smooth_scroll
+                .scroll(grid, region, rows, cell_metrics, strip);
+        }
     }
 
     pub fn hl_attr_define(
@@ -1496,11 +2557,66 @@ impl State {
         self.cur_point_area()
     }
 
+    /// Mouse pointer over text, depends on mouse_shape of current mode
+    fn mouse_cursor(&self) -> MouseCursor {
+        self.render_state
+            .borrow()
+            .mode
+            .mode_info()
+            .and_then(mode::ModeInfo::mouse_cursor)
+            .map_or(MouseCursor::Text, MouseCursor::Shape)
+    }
+
+    pub fn set_langmap(&mut self, langmap: bool) {
+        if let Some(cursor) = self.cursor.as_mut() {
+            cursor.set_langmap(langmap);
+        }
+        let repaint_mode = self.cur_point_area();
+        self.on_redraw(&repaint_mode);
+    }
+
     pub fn on_mouse(&mut self, on: bool) -> RepaintMode {
         self.mouse_enabled = on;
         RepaintMode::Nothing
     }
 
+    pub fn on_bell(&mut self) -> RepaintMode {
+        if self.ui_events.bell {
+            if let Some(display) = self.drawing_area.get_display() {
+                display.beep();
+            }
+        }
+        RepaintMode::Nothing
+    }
+
+    pub fn on_visual_bell(&mut self) -> RepaintMode {
+        if self.ui_events.visual_bell {
+            self.visual_bell.flash();
+        }
+        RepaintMode::Nothing
+    }
+
+    pub fn on_suspend(&mut self) -> RepaintMode {
+        if self.ui_events.suspend {
+            if let Some(window) = self
+                .drawing_area
+                .get_toplevel()
+                .and_then(|w| w.downcast::<gtk::Window>().ok())
+            {
+                window.iconify();
+            }
+        }
+        RepaintMode::Nothing
+    }
+
+    pub fn on_chdir(&mut self, dir: String) -> RepaintMode {
+        if self.ui_events.chdir {
+            self.ui_events.chdir_received = true;
+            self.on_command(nvim::NvimCommand::Chdir(dir));
+        }
+        RepaintMode::Nothing
+    }
+
     pub fn on_busy(&mut self, busy: bool) -> RepaintMode {
         if busy {
             self.cursor.as_mut().unwrap().busy_on();
@@ -1517,10 +2633,19 @@ impl State {
         selected: i64,
         row: u64,
         col: u64,
+        grid: Option<i64>,
     ) -> RepaintMode {
-        let point = ModelRect::point(col as usize, row as usize);
+        let mut point = ModelRect::point(col as usize, row as usize);
+        // with ext_multigrid position is relative to given grid,
+        // -1 is used for external cmdline
+        if let Some(grid) = grid.filter(|&g| g > 0).and_then(|g| self.grids.get(g as u64)) {
+            point = grid.to_global(point);
+        }
         let render_state = self.render_state.borrow();
-        let (x, y, width, height) = point.to_area(render_state.font_ctx.cell_metrics());
+        let (x, y, width, height) = render_state
+            .font_ctx
+            .padding()
+            .shift(point.to_area(render_state.font_ctx.cell_metrics()));
 
         let context = popup_menu::PopupMenuContext {
             nvim: &self.nvim,
@@ -1533,6 +2658,7 @@ impl State {
             width,
             height,
             max_width: self.max_popup_width(),
+            blend: self.pumblend,
         };
 
         self.popup_menu.show(context);
@@ -1569,7 +2695,22 @@ impl State {
     }
 
     pub fn option_set(&mut self, name: String, val: Value) -> RepaintMode {
-        if let "guifont" = name.as_str() { self.set_font_from_value(val) };
+        match name.as_str() {
+            "guifont" => self.set_font_from_value(val),
+            "guifontwide" => {
+                if let Some(val) = val.as_str() {
+                    let fonts = split_at_comma(val);
+                    let font = fonts.first().map(String::as_str).unwrap_or("");
+                    self.set_font_wide(font.to_owned());
+                }
+            }
+            "pumblend" => {
+                if let Some(pumblend) = val.as_u64() {
+                    self.pumblend = pumblend.min(100) as u8;
+                }
+            }
+            _ => (),
+        }
         RepaintMode::Nothing
     }
 
@@ -1635,7 +2776,10 @@ impl State {
         {
             let cursor = self.grids.current().unwrap().cur_point();
             let render_state = self.render_state.borrow();
-            let (x, y, width, height) = cursor.to_area(render_state.font_ctx.cell_metrics());
+            let (x, y, width, height) = render_state
+                .font_ctx
+                .padding()
+                .shift(cursor.to_area(render_state.font_ctx.cell_metrics()));
             let ctx = CmdLineContext {
                 nvim: &self.nvim,
                 content,
@@ -1690,6 +2834,63 @@ impl State {
         RepaintMode::Nothing
     }
 
+    pub fn msg_show(
+        &mut self,
+        kind: String,
+        content: Vec<(u64, String)>,
+        replace_last: bool,
+    ) -> RepaintMode {
+        {
+            let render_state = self.render_state.borrow();
+            self.messages.show(
+                &kind,
+                &content,
+                replace_last,
+                &render_state.hl,
+                render_state.font_ctx.font_description(),
+            );
+        }
+
+        RepaintMode::Nothing
+    }
+
+    pub fn msg_clear(&mut self) -> RepaintMode {
+        self.messages.clear();
+        RepaintMode::Nothing
+    }
+
+    pub fn msg_showmode(&mut self, content: Vec<(u64, String)>) -> RepaintMode {
+        self.messages
+            .set_showmode(&content, &self.render_state.borrow().hl);
+        RepaintMode::Nothing
+    }
+
+    pub fn msg_showcmd(&mut self, content: Vec<(u64, String)>) -> RepaintMode {
+        self.messages
+            .set_showcmd(&content, &self.render_state.borrow().hl);
+        RepaintMode::Nothing
+    }
+
+    pub fn msg_ruler(&mut self, content: Vec<(u64, String)>) -> RepaintMode {
+        self.messages.set_ruler(&content, &self.render_state.borrow().hl);
+        RepaintMode::Nothing
+    }
+
+    pub fn msg_history_show(&mut self, entries: Vec<(String, Vec<(u64, String)>)>) -> RepaintMode {
+        let render_state = self.render_state.borrow();
+        self.messages.history_show(
+            &entries,
+            &render_state.hl,
+            render_state.font_ctx.font_description(),
+        );
+        RepaintMode::Nothing
+    }
+
+    pub fn msg_history_clear(&mut self) -> RepaintMode {
+        self.messages.history_clear();
+        RepaintMode::Nothing
+    }
+
     pub fn wildmenu_show(&self, items: Vec<String>) -> RepaintMode {
         self.cmd_line
             .show_wildmenu(items, &*self.render_state.borrow(), self.max_popup_width());
@@ -1709,8 +2910,35 @@ impl State {
 
 impl CursorRedrawCb for State {
     fn queue_redraw_cursor(&mut self) {
+        self.float_windows.queue_draw(self.grids.current_grid_id());
+
         if let Some(cur_point) = self.grids.current().map(|g| g.cur_point()) {
             self.on_redraw(&RepaintMode::Area(cur_point));
         }
     }
+
+    fn queue_redraw_move(&mut self, start_offset: (f64, f64)) {
+        self.float_windows.queue_draw(self.grids.current_grid_id());
+
+        let cur_point = match self.grids.current().map(|g| g.cur_point()) {
+            Some(cur_point) => cur_point,
+            None => return,
+        };
+        let area = {
+            let render_state = self.render_state.borrow();
+            let cell_metrics = render_state.font_ctx.cell_metrics();
+            let (x, y, width, height) = cur_point.to_area(cell_metrics);
+            let (dx, dy) = start_offset;
+            // one more cell for double width cursor
+            let x2 = f64::from(x + width) + cell_metrics.char_width;
+            ModelRect::from_area(
+                cell_metrics,
+                (f64::from(x) + dx.min(0.0)).max(0.0),
+                (f64::from(y) + dy.min(0.0)).max(0.0),
+                x2 + dx.max(0.0),
+                f64::from(y + height) + dy.max(0.0),
+            )
+        };
+        self.on_redraw(&RepaintMode::Area(area));
+    }
 }
@@ -1,31 +1,67 @@
 use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
 
 use gtk;
 use gtk::prelude::*;
 use gtk::{ButtonsType, MessageDialog, MessageType};
 
-use neovim_lib::{CallError, NeovimApi, Value};
+use neovim_lib::{CallError, NeovimApiAsync, Value};
+use crate::nvim::on_ui_thread;
 use crate::shell::Shell;
 use crate::ui::{Components, UiMutex};
 
-pub fn can_close_window(comps: &UiMutex<Components>, shell: &RefCell<Shell>) -> bool {
-    let shell = shell.borrow();
-    match get_changed_buffers(&*shell) {
-        Ok(vec) => {
-            if !vec.is_empty() {
-                show_not_saved_dlg(comps, &*shell, &vec)
-            } else {
-                true
-            }
-        }
-        Err(ref err) => {
-            error!("Error getting info from nvim: {}", err);
-            true
-        }
+// names of modified buffers
+const CHANGED_BUFFERS: &str = "map(filter(getbufinfo(), 'v:val.changed'), 'v:val.name')";
+
+/// Close window, if there are changed buffers user is asked to save them first
+///
+/// Changed buffers are requested without blocking ui,
+/// so window is closed later from request callback.
+pub fn close_window(comps: Arc<UiMutex<Components>>, shell: Rc<RefCell<Shell>>) {
+    let requested = {
+        let state = shell.borrow().state.clone();
+        let state = state.borrow();
+        let nvim = state.nvim();
+
+        let comps = comps.clone();
+        let shell = shell.clone();
+        nvim.map(|mut nvim| {
+            nvim.eval_async(CHANGED_BUFFERS)
+                .cb(on_ui_thread(move |res: Result<Value, CallError>| match res {
+                    Ok(changed_bufs) => {
+                        let changed_bufs = get_changed_buffers(&changed_bufs);
+                        if changed_bufs.is_empty() {
+                            close(&comps, &shell);
+                        } else {
+                            show_not_saved_dlg(comps, shell, &changed_bufs);
+                        }
+                    }
+                    Err(ref err) => {
+                        error!("Error getting info from nvim: {}", err);
+                        close(&comps, &shell);
+                    }
+                }))
+                .call()
+        })
+        .is_some()
+    };
+
+    if !requested {
+        close(&comps, &shell);
     }
 }
 
-fn show_not_saved_dlg(comps: &UiMutex<Components>, shell: &Shell, changed_bufs: &[String]) -> bool {
+fn close(comps: &UiMutex<Components>, shell: &RefCell<Shell>) {
+    comps.borrow().close_window();
+    shell.borrow_mut().detach_ui();
+}
+
+fn show_not_saved_dlg(
+    comps: Arc<UiMutex<Components>>,
+    shell: Rc<RefCell<Shell>>,
+    changed_bufs: &[String],
+) {
     let mut changed_files = changed_bufs
         .iter()
         .map(|n| if n.is_empty() { "<No name>" } else { n })
@@ -47,61 +83,38 @@ fn show_not_saved_dlg(comps: &UiMutex<Components>, shell: &Shell, changed_bufs:
         ("_Cancel", gtk::ResponseType::Cancel),
     ]);
 
-    let res = match dlg.run() {
+    let res = dlg.run();
+    dlg.destroy();
+
+    match res {
         gtk::ResponseType::Yes => {
-            let state = shell.state.borrow();
-            let mut nvim = state.nvim().unwrap();
-            match nvim.command("wa") {
-                Err(ref err) => {
-                    error!("Error: {}", err);
-                    false
-                }
-                _ => true,
+            let state = shell.borrow().state.clone();
+            let state = state.borrow();
+            let nvim = state.nvim();
+            if let Some(mut nvim) = nvim {
+                let shell = shell.clone();
+                nvim.command_async("wa")
+                    .cb(on_ui_thread(move |res: Result<(), CallError>| match res {
+                        Err(ref err) => error!("Error: {}", err),
+                        Ok(()) => close(&comps, &shell),
+                    }))
+                    .call();
             }
         }
-        gtk::ResponseType::No => true,
-        gtk::ResponseType::Cancel | _ => false,
-    };
-
-    dlg.destroy();
-
-    res
+        gtk::ResponseType::No => close(&comps, &shell),
+        _ => (),
+    }
 }
 
-fn get_changed_buffers(shell: &Shell) -> Result<Vec<String>, CallError> {
-    let state = shell.state.borrow();
-    let nvim = state.nvim();
-    if let Some(mut nvim) = nvim {
-        let buffers = nvim.list_bufs().unwrap();
-
-        Ok(buffers
+fn get_changed_buffers(changed_bufs: &Value) -> Vec<String> {
+    match changed_bufs.as_array() {
+        Some(bufs) => bufs
             .iter()
-            .map(|buf| {
-                (
-                    match buf.get_option(&mut nvim, "modified") {
-                        Ok(Value::Boolean(val)) => val,
-                        Ok(_) => {
-                            warn!("Value must be boolean");
-                            false
-                        }
-                        Err(ref err) => {
-                            error!("Something going wrong while getting buffer option: {}", err);
-                            false
-                        }
-                    },
-                    match buf.get_name(&mut nvim) {
-                        Ok(name) => name,
-                        Err(ref err) => {
-                            error!("Something going wrong while getting buffer name: {}", err);
-                            "<Error>".to_owned()
-                        }
-                    },
-                )
-            })
-            .filter(|e| e.0)
-            .map(|e| e.1)
-            .collect())
-    } else {
-        Ok(vec![])
+            .map(|name| name.as_str().unwrap_or("<Error>").to_owned())
+            .collect(),
+        None => {
+            warn!("Value must be array");
+            vec![]
+        }
     }
 }
@@ -0,0 +1,324 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::prelude::*;
+
+use crate::render::CellMetrics;
+
+// microseconds, same units as gdk::FrameClock::get_frame_time
+const SCROLL_DURATION: i64 = 150_000;
+
+/// Scrolled region in grid cells, same as in grid_scroll event
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ScrollRegion {
+    pub top: u64,
+    pub bot: u64,
+    pub left: u64,
+    pub right: u64,
+}
+
+impl ScrollRegion {
+    pub fn new(top: u64, bot: u64, left: u64, right: u64) -> Self {
+        ScrollRegion {
+            top,
+            bot,
+            left,
+            right,
+        }
+    }
+
+    /// Area in grid local pixels (x, y, width, height)
+    pub fn to_area(self, cell_metrics: &CellMetrics) -> (f64, f64, f64, f64) {
+        let &CellMetrics {
+            char_width,
+            line_height,
+            ..
+        } = cell_metrics;
+
+        (
+            self.left as f64 * char_width,
+            self.top as f64 * line_height,
+            (self.right - self.left) as f64 * char_width,
+            (self.bot - self.top) as f64 * line_height,
+        )
+    }
+}
+
+/// Offset that must be applied to grid content on draw
+pub struct ScrollOffset {
+    /// None in case whole grid is shifted
+    pub region: Option<ScrollRegion>,
+    /// vertical shift of model content in pixels
+    pub offset: f64,
+    /// rows that was scrolled out of region and
+    /// y position of this surface relative to region top
+    pub strip: Option<(cairo::ImageSurface, f64)>,
+}
+
+fn ease_out(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+fn anim_offset(start_offset: f64, elapsed: i64) -> f64 {
+    if elapsed >= SCROLL_DURATION {
+        0.0
+    } else {
+        let t = elapsed.max(0) as f64 / SCROLL_DURATION as f64;
+        start_offset * (1.0 - ease_out(t))
+    }
+}
+
+/// Y position of scrolled out rows relative to region top
+fn strip_y(start_offset: f64, offset: f64, region_height: f64) -> f64 {
+    if start_offset > 0.0 {
+        offset - start_offset
+    } else {
+        region_height + offset
+    }
+}
+
+struct ScrollAnim {
+    grid: u64,
+    region: ScrollRegion,
+    region_height: f64,
+    start_offset: f64,
+    offset: f64,
+    start_time: Option<i64>,
+    strip: cairo::ImageSurface,
+}
+
+impl ScrollAnim {
+    fn tick(&mut self, frame_time: i64) -> bool {
+        let start_time = *self.start_time.get_or_insert(frame_time);
+        self.offset = anim_offset(self.start_offset, frame_time - start_time);
+        self.offset != 0.0
+    }
+
+    /// Join previous scrolled out rows with new one, so
+    /// scroll during animation continues from current position
+    fn join_strip(&self, new_offset: f64, strip: &cairo::ImageSurface) -> cairo::ImageSurface {
+        let height = new_offset.abs().ceil() as i32;
+        let joined =
+            match cairo::ImageSurface::create(cairo::Format::ARgb32, strip.get_width(), height) {
+                Ok(joined) => joined,
+                Err(e) => {
+                    error!("Can't create scroll surface {:?}", e);
+                    return strip.clone();
+                }
+            };
+
+        let ctx = cairo::Context::new(&joined);
+        let prev_height = self.strip.get_height() as f64;
+        if new_offset > 0.0 {
+            ctx.set_source_surface(&self.strip, 0.0, self.offset - prev_height);
+            ctx.paint();
+            ctx.set_source_surface(strip, 0.0, self.offset);
+            ctx.paint();
+        } else {
+            ctx.set_source_surface(strip, 0.0, 0.0);
+            ctx.paint();
+            ctx.set_source_surface(&self.strip, 0.0, strip.get_height() as f64);
+            ctx.paint();
+        }
+
+        joined
+    }
+}
+
+struct State {
+    anim: Option<ScrollAnim>,
+    // sub row shift from smooth (touchpad) scroll events
+    nudge: Option<(u64, f64)>,
+    ticking: bool,
+}
+
+/// Animated pixel scroll of grid content
+///
+/// On grid_scroll rows that leave the region are kept as surface
+/// and model content is shifted back to its previous position,
+/// then shift goes to zero on each frame clock tick.
+pub struct SmoothScroll {
+    state: Rc<RefCell<State>>,
+    drawing_area: gtk::DrawingArea,
+}
+
+impl SmoothScroll {
+    pub fn new(drawing_area: &gtk::DrawingArea) -> Self {
+        SmoothScroll {
+            state: Rc::new(RefCell::new(State {
+                anim: None,
+                nudge: None,
+                ticking: false,
+            })),
+            drawing_area: drawing_area.clone(),
+        }
+    }
+
+    /// Start scroll animation, `strip` contains rows scrolled out of region
+    pub fn scroll(
+        &self,
+        grid: u64,
+        region: ScrollRegion,
+        rows: i64,
+        cell_metrics: &CellMetrics,
+        strip: cairo::ImageSurface,
+    ) {
+        let mut state = self.state.borrow_mut();
+        let delta = rows as f64 * cell_metrics.line_height;
+        let region_height = region.to_area(cell_metrics).3;
+
+        let (start_offset, strip) = match state.anim {
+            Some(ref anim)
+                if anim.grid == grid
+                    && anim.region == region
+                    && anim.offset.signum() == delta.signum() =>
+            {
+                let start_offset = anim.offset + delta;
+                (start_offset, anim.join_strip(start_offset, &strip))
+            }
+            _ => (delta, strip),
+        };
+
+        state.anim = Some(ScrollAnim {
+            grid,
+            region,
+            region_height,
+            start_offset,
+            offset: start_offset,
+            start_time: None,
+            strip,
+        });
+
+        if !state.ticking {
+            state.ticking = true;
+            self.start_tick();
+        }
+    }
+
+    fn start_tick(&self) {
+        let state = self.state.clone();
+        self.drawing_area.add_tick_callback(move |drawing_area, clock| {
+            let mut state = state.borrow_mut();
+
+            let running = match state.anim {
+                Some(ref mut anim) => anim.tick(clock.get_frame_time()),
+                None => false,
+            };
+            if !running {
+                state.anim = None;
+                state.ticking = false;
+            }
+
+            drawing_area.queue_draw();
+            Continue(running)
+        });
+    }
+
+    /// Shift whole grid by sub row amount of pixels
+    pub fn set_nudge(&self, grid: u64, offset: f64) {
+        let mut state = self.state.borrow_mut();
+        state.nudge = if offset != 0.0 {
+            Some((grid, offset))
+        } else {
+            None
+        };
+        self.drawing_area.queue_draw();
+    }
+
+    pub fn reset_nudge(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.nudge.take().is_some() {
+            self.drawing_area.queue_draw();
+        }
+    }
+
+    pub fn offset(&self, grid: u64) -> Option<ScrollOffset> {
+        let state = self.state.borrow();
+
+        let nudge = state
+            .nudge
+            .filter(|&(nudge_grid, _)| nudge_grid == grid)
+            .map(|(_, offset)| offset);
+
+        match state.anim {
+            Some(ref anim) if anim.grid == grid => {
+                let offset = anim.offset + nudge.unwrap_or(0.0);
+                Some(ScrollOffset {
+                    region: Some(anim.region),
+                    offset,
+                    strip: Some((
+                        anim.strip.clone(),
+                        strip_y(anim.start_offset, offset, anim.region_height),
+                    )),
+                })
+            }
+            _ => nudge.map(|offset| ScrollOffset {
+                region: None,
+                offset,
+                strip: None,
+            }),
+        }
+    }
+}
+
+/// Create surface for rows that will be scrolled out of region
+pub fn create_strip(
+    region: ScrollRegion,
+    rows: i64,
+    cell_metrics: &CellMetrics,
+) -> Option<(cairo::ImageSurface, cairo::Context)> {
+    let (x, y, width, height) = region.to_area(cell_metrics);
+    let strip_height = rows.abs() as f64 * cell_metrics.line_height;
+
+    let surface = cairo::ImageSurface::create(
+        cairo::Format::ARgb32,
+        width.ceil() as i32,
+        strip_height.ceil() as i32,
+    )
+    .map_err(|e| error!("Can't create scroll surface {:?}", e))
+    .ok()?;
+
+    let ctx = cairo::Context::new(&surface);
+    if rows > 0 {
+        ctx.translate(-x, -y);
+    } else {
+        ctx.translate(-x, -(y + height - strip_height));
+    }
+    ctx.rectangle(x, y, width, height);
+    ctx.clip();
+
+    Some((surface, ctx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anim_offset() {
+        assert_eq!(100.0, anim_offset(100.0, 0));
+        assert_eq!(0.0, anim_offset(100.0, SCROLL_DURATION));
+        assert_eq!(0.0, anim_offset(-100.0, SCROLL_DURATION * 2));
+
+        let half = anim_offset(100.0, SCROLL_DURATION / 2);
+        assert!(half > 0.0 && half < 50.0);
+
+        let half = anim_offset(-100.0, SCROLL_DURATION / 2);
+        assert!(half < 0.0 && half > -50.0);
+    }
+
+    #[test]
+    fn test_strip_y() {
+        // content scrolled up, strip is over the region content
+        assert_eq!(-40.0, strip_y(60.0, 20.0, 200.0));
+        // content scrolled down, strip is under the region content
+        assert_eq!(180.0, strip_y(-60.0, -20.0, 200.0));
+    }
+
+    #[test]
+    fn test_region_area() {
+        let cell_metrics = CellMetrics::new_hw(10.0, 5.0);
+        let region = ScrollRegion::new(1, 5, 2, 10);
+        assert_eq!((10.0, 10.0, 40.0, 40.0), region.to_area(&cell_metrics));
+    }
+}
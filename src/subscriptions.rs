@@ -110,6 +110,11 @@ impl Subscriptions {
     ///
     /// This function is wrapped by `shell::State`.
     pub fn set_autocmds(&self, nvim: &mut NeovimRef) {
+        // autocmds of previously attached ui are left in server nvim
+        nvim.command_async("augroup NvimGtkSubscriptions | autocmd! | augroup END")
+            .cb(|r| r.report_err())
+            .call();
+
         for (key, subscriptions) in &self.0 {
             let SubscriptionKey {
                 event_name,
@@ -121,7 +126,7 @@ impl Subscriptions {
                     .iter()
                     .fold("".to_owned(), |acc, arg| acc + ", " + &arg);
                 let autocmd = format!(
-                    "autocmd {} {} call rpcnotify(1, 'subscription', '{}', '{}', {} {})",
+                    "autocmd NvimGtkSubscriptions {} {} call rpcnotify(get(g:, 'GtkGuiChannel', 1), 'subscription', '{}', '{}', {} {})",
                     event_name, pattern, event_name, pattern, i, args,
                 );
                 nvim.command_async(&autocmd).cb(|r| r.report_err()).call();
@@ -129,6 +134,16 @@ impl Subscriptions {
         }
     }
 
+    /// Remove autocmds registered by `set_autocmds`,
+    /// server nvim keeps running after ui detach.
+    ///
+    /// This function is wrapped by `shell::State`.
+    pub fn clear_autocmds(&self, nvim: &mut NeovimRef) {
+        nvim.command_async("augroup NvimGtkSubscriptions | autocmd! | augroup END")
+            .cb(|r| r.report_err())
+            .call();
+    }
+
     /// Trigger given event.
     fn on_notify(&self, key: &SubscriptionKey, index: usize, args: Vec<String>) {
         if let Some(subscription) = self.0.get(key).and_then(|v| v.get(index)) {
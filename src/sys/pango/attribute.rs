@@ -10,3 +10,8 @@ pub fn new_features(features: &str) -> Option<pango::Attribute> {
         ))
     }
 }
+
+/// Only fields that are set in description override font of text
+pub fn new_font_desc(desc: &pango::FontDescription) -> Option<pango::Attribute> {
+    unsafe { from_glib_full(pango_sys::pango_attr_font_desc_new(desc.to_glib_none().0)) }
+}
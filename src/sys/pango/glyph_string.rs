@@ -0,0 +1,66 @@
+use pango;
+use pango_sys;
+
+use glib::translate::*;
+
+/// Return `(cluster, width, x_offset)` for each glyph
+pub fn get_glyphs(glyphs: &mut pango::GlyphString) -> Vec<(i32, i32, i32)> {
+    unsafe {
+        let glyph_string = glyphs.to_glib_none_mut().0;
+        let num_glyphs = (*glyph_string).num_glyphs as usize;
+
+        (0..num_glyphs)
+            .map(|i| {
+                let info = &*(*glyph_string).glyphs.add(i);
+                let cluster = *(*glyph_string).log_clusters.add(i);
+                (cluster, info.geometry.width, info.geometry.x_offset)
+            })
+            .collect()
+    }
+}
+
+/// Set `(width, x_offset)` for each glyph
+pub fn set_geometry(glyphs: &mut pango::GlyphString, geometry: &[(i32, i32)]) {
+    unsafe {
+        let glyph_string = glyphs.to_glib_none_mut().0;
+        let num_glyphs = (*glyph_string).num_glyphs as usize;
+        debug_assert_eq!(num_glyphs, geometry.len());
+
+        for (i, &(width, x_offset)) in geometry.iter().enumerate().take(num_glyphs) {
+            let info = &mut *(*glyph_string).glyphs.add(i);
+            info.geometry.width = width;
+            info.geometry.x_offset = x_offset;
+        }
+    }
+}
+
+/// Copy of glyphs in `start..end` range
+pub fn slice(glyphs: &mut pango::GlyphString, start: usize, end: usize) -> pango::GlyphString {
+    let mut slice = pango::GlyphString::new();
+    slice.set_size((end - start) as i32);
+
+    unsafe {
+        let glyph_string = glyphs.to_glib_none_mut().0;
+        let slice_string = slice.to_glib_none_mut().0;
+        debug_assert!(end <= (*glyph_string).num_glyphs as usize);
+
+        for i in start..end {
+            *(*slice_string).glyphs.add(i - start) = *(*glyph_string).glyphs.add(i);
+            *(*slice_string).log_clusters.add(i - start) = *(*glyph_string).log_clusters.add(i);
+        }
+    }
+
+    slice
+}
+
+/// Replace glyphs in `start..end` range with empty glyph, advance is not changed
+pub fn hide(glyphs: &mut pango::GlyphString, start: usize, end: usize) {
+    unsafe {
+        let glyph_string = glyphs.to_glib_none_mut().0;
+        debug_assert!(end <= (*glyph_string).num_glyphs as usize);
+
+        for i in start..end {
+            (*(*glyph_string).glyphs.add(i)).glyph = pango_sys::PANGO_GLYPH_EMPTY;
+        }
+    }
+}
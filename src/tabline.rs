@@ -10,7 +10,7 @@ use glib::signal;
 
 use pango;
 
-use neovim_lib::{NeovimApi, NeovimApiAsync};
+use neovim_lib::NeovimApiAsync;
 use neovim_lib::neovim_api::Tabpage;
 
 use crate::nvim;
@@ -37,7 +37,9 @@ impl State {
         let target = &self.data[idx as usize];
         if Some(target) != self.selected.as_ref() {
             if let Some(mut nvim) = self.nvim.as_ref().unwrap().nvim() {
-                nvim.set_current_tabpage(target).report_err();
+                nvim.set_current_tabpage_async(target)
+                    .cb(|r| r.report_err())
+                    .call();
             }
         }
     }
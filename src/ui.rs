@@ -15,7 +15,7 @@ use gdk::WindowExt;
 
 use toml;
 
-use neovim_lib::NeovimApi;
+use neovim_lib::{NeovimApi, NeovimApiAsync};
 
 use crate::file_browser::FileBrowserWidget;
 use crate::misc;
@@ -49,6 +49,9 @@ const DEFAULT_WIDTH: i32 = 800;
 const DEFAULT_HEIGHT: i32 = 600;
 const DEFAULT_SIDEBAR_WIDTH: i32 = 200;
 
+/// Action name, accelerators and zoom handler
+type ZoomAction = (&'static str, &'static [&'static str], fn(&mut shell::State));
+
 pub struct Ui {
     open_paths: Box<[String]>,
     initialized: bool,
@@ -215,6 +218,11 @@ impl Ui {
 
                 main.set_position(comps.window_state.sidebar_width);
 
+                self.shell
+                    .borrow()
+                    .state
+                    .borrow_mut()
+                    .set_font_zoom(comps.window_state.font_zoom);
             } else {
                 window.set_default_size(DEFAULT_WIDTH, DEFAULT_HEIGHT);
                 main.set_position(DEFAULT_SIDEBAR_WIDTH);
@@ -254,6 +262,8 @@ impl Ui {
         });
         app.add_action(&show_sidebar_action);
 
+        self.create_zoom_actions(app);
+
         let comps_ref = self.comps.clone();
         window.connect_size_allocate(clone!(main => move |window, _| {
             gtk_window_size_allocate(
@@ -307,9 +317,23 @@ impl Ui {
             move |args| set_completeopts(&*shell_ref, args),
         );
 
+        // nvim older than 0.5 doesn't send chdir ui event
+        let state_ref = shell.state.clone();
+        let file_browser_ref = self.file_browser.clone();
+        let projects = [self.projects.clone(), self.fs_projects.clone()];
+        shell.state.borrow().subscribe(
+            SubscriptionKey::from("DirChanged"),
+            &["getcwd()"],
+            move |args| {
+                if state_ref.borrow().follow_dir_changed() {
+                    Ui::set_current_dir(&file_browser_ref, &projects, &args[0]);
+                }
+            },
+        );
+
         let comps_ref = self.comps.clone();
         let shell_ref = self.shell.clone();
-        window.connect_delete_event(move |_, _| gtk_delete(&*comps_ref, &*shell_ref));
+        window.connect_delete_event(move |_, _| gtk_delete(&comps_ref, &shell_ref));
 
         shell.grab_focus();
 
@@ -339,16 +363,57 @@ impl Ui {
             );
         }));
 
+        let comps_ref = self.comps.clone();
+        shell.set_font_zoom_cb(Some(move |font_zoom| {
+            comps_ref.borrow_mut().window_state.font_zoom = font_zoom;
+        }));
+
         let sidebar_action = UiMutex::new(show_sidebar_action);
         let comps_ref = self.comps.clone();
         let file_browser = self.file_browser.clone();
+        let projects = [self.projects.clone(), self.fs_projects.clone()];
         shell.set_nvim_command_cb(Some(
             move |shell: &mut shell::State, command: NvimCommand| {
-                Ui::nvim_command(shell, command, &sidebar_action, &comps_ref, &file_browser);
+                Ui::nvim_command(
+                    shell,
+                    command,
+                    &sidebar_action,
+                    &comps_ref,
+                    &file_browser,
+                    &projects,
+                );
             },
         ));
     }
 
+    fn create_zoom_actions(&self, app: &gtk::Application) {
+        let actions: [ZoomAction; 3] = [
+            (
+                "zoom-in",
+                &["<Primary>equal", "<Primary>plus", "<Primary>KP_Add"],
+                shell::State::zoom_in,
+            ),
+            (
+                "zoom-out",
+                &["<Primary>minus", "<Primary>KP_Subtract"],
+                shell::State::zoom_out,
+            ),
+            (
+                "zoom-reset",
+                &["<Primary>0", "<Primary>KP_0"],
+                shell::State::zoom_reset,
+            ),
+        ];
+
+        for &(name, accels, zoom) in &actions {
+            let action = SimpleAction::new(name, None);
+            let state_ref = self.shell.borrow().state.clone();
+            action.connect_activate(move |_, _| zoom(&mut state_ref.borrow_mut()));
+            app.add_action(&action);
+            app.set_accels_for_action(&format!("app.{}", name), accels);
+        }
+    }
+
     fn nvim_started(
         shell: &shell::State,
         plug_manager: &UiMutex<plug_manager::Manager>,
@@ -363,15 +428,22 @@ impl Ui {
             .unwrap_or(false);
         if sync_with_gtk_theme {
             if is_dark_theme() {
-                shell.nvim().unwrap().command(":set background=dark").report_err();
+                shell
+                    .nvim()
+                    .unwrap()
+                    .command_async(":set background=dark")
+                    .cb(|r| r.report_err())
+                    .call();
             }
             let shell_ref = shell.nvim_clone();
             let update_func = move |_: &gtk::Settings| {
-                if is_dark_theme() {
-                    shell_ref.nvim().unwrap().command(":set background=dark").report_err();
-                } else {
-                    shell_ref.nvim().unwrap().command(":set background=light").report_err();
-                }
+                let background = if is_dark_theme() { "dark" } else { "light" };
+                shell_ref
+                    .nvim()
+                    .unwrap()
+                    .command_async(&format!(":set background={}", background))
+                    .cb(|r| r.report_err())
+                    .call();
             };
             let update_func_clone = update_func.clone();
             gtk::Settings::get_default().unwrap().connect_property_gtk_theme_name_notify(update_func);
@@ -408,7 +480,8 @@ impl Ui {
         command: NvimCommand,
         sidebar_action: &UiMutex<SimpleAction>,
         comps: &Arc<UiMutex<Components>>,
-        sidebar: &UiMutex<FileBrowserWidget>
+        sidebar: &UiMutex<FileBrowserWidget>,
+        projects: &[Arc<UiMutex<Projects>>],
     ) {
         match command {
             NvimCommand::ShowProjectView => {
@@ -483,6 +556,18 @@ impl Ui {
                 let sidebar = sidebar.borrow();
                 sidebar.set_show_hidden_files(! sidebar.get_show_hidden_files());
             }
+            NvimCommand::Chdir(dir) => Ui::set_current_dir(sidebar, projects, &dir),
+        }
+    }
+
+    fn set_current_dir(
+        sidebar: &UiMutex<FileBrowserWidget>,
+        projects: &[Arc<UiMutex<Projects>>],
+        dir: &str,
+    ) {
+        sidebar.borrow().set_current_dir(dir);
+        for projects in projects {
+            projects.borrow_mut().set_current_dir(dir);
         }
     }
 
@@ -498,7 +583,7 @@ impl Ui {
             .open_btn
             .connect_clicked(move |btn| {
                 if btn.get_active() {
-                    projects.borrow_mut().show()
+                    Projects::show(&projects)
                 }
             });
 
@@ -534,6 +619,18 @@ impl Ui {
         save_btn.set_can_focus(false);
         header_bar.pack_end(&save_btn);
 
+        let messages_btn = Button::new_from_icon_name(
+            Some("mail-unread-symbolic"),
+            gtk::IconSize::SmallToolbar,
+        );
+        let shell = self.shell.clone();
+        messages_btn.connect_clicked(move |_| shell.borrow().show_messages_history());
+        messages_btn.set_can_focus(false);
+        messages_btn.set_tooltip_text(Some("Show messages"));
+        header_bar.pack_end(&messages_btn);
+
+        header_bar.pack_end(&self.shell.borrow().messages_status_widget());
+
         header_bar.set_show_close_button(true);
         
         // fullscreen headerbar
@@ -544,7 +641,7 @@ impl Ui {
             .fs_open_btn
             .connect_clicked(move |btn| {
                 if btn.get_active() {
-                    projects.borrow_mut().show();
+                    Projects::show(&projects);
                 } else {
                     comps_ref.borrow().headerbar_revealer.set_reveal_child(false);
                 }
@@ -663,6 +760,12 @@ impl Ui {
         section.append_item(&MenuItem::new(Some("Sidebar"), Some("app.show-sidebar")));
         menu.append_section(None, &section);
 
+        let section = Menu::new();
+        section.append_item(&MenuItem::new(Some("Zoom In"), Some("app.zoom-in")));
+        section.append_item(&MenuItem::new(Some("Zoom Out"), Some("app.zoom-out")));
+        section.append_item(&MenuItem::new(Some("Reset Zoom"), Some("app.zoom-reset")));
+        menu.append_section(None, &section);
+
         let section = Menu::new();
         section.append_item(&MenuItem::new(Some("Plugins"), Some("app.Plugins")));
         section.append_item(&MenuItem::new(Some("About"), Some("app.HelpAbout")));
@@ -702,19 +805,21 @@ fn on_help_about(window: &gtk::ApplicationWindow) {
     about.show();
 }
 
-fn gtk_delete(comps: &UiMutex<Components>, shell: &RefCell<Shell>) -> Inhibit {
+fn gtk_delete(comps: &Arc<UiMutex<Components>>, shell: &Rc<RefCell<Shell>>) -> Inhibit {
     if !shell.borrow().is_nvim_initialized() {
         return Inhibit(false);
     }
 
-    Inhibit(if shell_dlg::can_close_window(comps, shell) {
-        let comps = comps.borrow();
-        comps.close_window();
+    // server keeps running after detach, so buffers are not lost
+    if shell.borrow().is_attached_server() {
+        comps.borrow().close_window();
         shell.borrow_mut().detach_ui();
-        false
-    } else {
-        true
-    })
+        return Inhibit(false);
+    }
+
+    // window is closed when nvim answers
+    shell_dlg::close_window(comps.clone(), shell.clone());
+    Inhibit(true)
 }
 
 fn gtk_window_size_allocate(
@@ -780,6 +885,8 @@ struct WindowState {
     is_fullscreen: bool,
     show_sidebar: bool,
     sidebar_width: i32,
+    #[serde(default)]
+    font_zoom: i32,
 }
 
 impl Default for WindowState {
@@ -791,6 +898,7 @@ impl Default for WindowState {
             is_fullscreen: false,
             show_sidebar: false,
             sidebar_width: DEFAULT_SIDEBAR_WIDTH,
+            font_zoom: 0,
         }
     }
 }
@@ -2,11 +2,32 @@ use crate::render;
 
 use pango;
 
+/// Shaped glyphs of item
+#[derive(Clone)]
+pub struct Glyphs {
+    pub glyphs: pango::GlyphString,
+    // glyphs that are drawn separately, they are hidden in `glyphs`
+    pub boxed: Vec<BoxedGlyphs>,
+}
+
+/// Glyph cluster scaled and centered into own cells and clipped by them
+#[derive(Clone)]
+pub struct BoxedGlyphs {
+    /// first column relative to item start
+    pub col: usize,
+    pub cells: usize,
+    pub scale: f64,
+    /// offset in box after scale
+    pub x_offset: f64,
+    pub glyphs: pango::GlyphString,
+}
+
 #[derive(Clone)]
 pub struct Item {
     pub item: pango::Item,
     pub cells_count: usize,
     pub glyphs: Option<pango::GlyphString>,
+    pub boxed_glyphs: Vec<BoxedGlyphs>,
     pub ink_overflow: Option<InkOverflow>,
     font: pango::Font,
 }
@@ -20,6 +41,7 @@ impl Item {
             item,
             cells_count,
             glyphs: None,
+            boxed_glyphs: Vec::new(),
             ink_overflow: None,
         }
     }
@@ -28,14 +50,17 @@ impl Item {
         self.font = item.analysis().font();
         self.item = item;
         self.glyphs = None;
+        self.boxed_glyphs.clear();
         self.ink_overflow = None;
     }
 
-    pub fn set_glyphs(&mut self, ctx: &render::Context, glyphs: pango::GlyphString) {
-        let mut glyphs = glyphs;
+    pub fn set_glyphs(&mut self, ctx: &render::Context, glyphs: Glyphs) {
+        let Glyphs { mut glyphs, boxed } = glyphs;
+        // boxed glyphs are clipped, so they never overflow
         let (ink_rect, _) = glyphs.extents(&self.font);
         self.ink_overflow = InkOverflow::from(ctx, &ink_rect, self.cells_count as i32);
         self.glyphs = Some(glyphs);
+        self.boxed_glyphs = boxed;
     }
 
     pub fn font(&self) -> &pango::Font {
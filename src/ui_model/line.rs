@@ -7,6 +7,7 @@ use super::cell::Cell;
 use super::item::Item;
 use crate::color;
 use crate::render;
+use crate::sys::pango as sys_pango;
 use crate::highlight::{HighlightMap, Highlight};
 
 pub struct Line {
@@ -245,6 +246,8 @@ pub struct StyledLine {
     pub line_str: String,
     cell_to_byte: Box<[usize]>,
     pub attr_list: pango::AttrList,
+    // byte ranges of cells with same highlight
+    runs: Vec<(usize, usize)>,
 }
 
 impl StyledLine {
@@ -260,12 +263,20 @@ impl StyledLine {
         let attr_list = pango::AttrList::new();
         let mut byte_offset = 0;
         let mut style_attr = StyleAttr::new();
+        let mut runs = Vec::new();
+
+        font_features.insert_fallback_into(&attr_list);
 
         for (cell_idx, cell) in line.line.iter().enumerate() {
             if cell.double_width {
                 continue;
             }
 
+            let wide = line
+                .line
+                .get(cell_idx + 1)
+                .map_or(false, |next| next.double_width);
+
             if !cell.ch.is_empty() {
                 line_str.push_str(&cell.ch);
             } else {
@@ -277,29 +288,52 @@ impl StyledLine {
                 cell_to_byte.push(cell_idx);
             }
 
-            let next = style_attr.next(byte_offset, byte_offset + len, cell, hl);
+            let next = style_attr.next(byte_offset, byte_offset + len, cell, wide, hl);
             if let Some(next) = next {
-                style_attr.insert_into(&attr_list);
+                style_attr.insert_into(&attr_list, font_features);
+                style_attr.push_run(&mut runs);
                 style_attr = next;
             }
 
             byte_offset += len;
         }
 
-        style_attr.insert_into(&attr_list);
+        style_attr.insert_into(&attr_list, font_features);
+        style_attr.push_run(&mut runs);
         font_features.insert_into(&attr_list);
 
         StyledLine {
             line_str,
             cell_to_byte: cell_to_byte.into_boxed_slice(),
             attr_list,
+            runs,
         }
     }
+
+    /// Cell index of given byte of line_str
+    #[inline]
+    pub fn cell_for_byte(&self, byte_idx: usize) -> usize {
+        self.cell_to_byte[byte_idx]
+    }
+
+    /// (offset, length) in bytes of highlight runs that contain
+    /// something except whitespace, this runs must be shaped
+    pub fn runs<'a>(&'a self) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.runs
+            .iter()
+            .filter(move |&&(start, end)| {
+                self.line_str[start..end]
+                    .chars()
+                    .any(|ch| !ch.is_whitespace())
+            })
+            .map(|&(start, end)| (start, end - start))
+    }
 }
 
 struct StyleAttr<'c> {
     italic: bool,
     bold: bool,
+    wide: bool,
     foreground: Option<&'c color::Color>,
     background: Option<&'c color::Color>,
     empty: bool,
@@ -314,6 +348,7 @@ impl<'c> StyleAttr<'c> {
         StyleAttr {
             italic: false,
             bold: false,
+            wide: false,
             foreground: None,
             background: None,
             empty: true,
@@ -328,11 +363,13 @@ impl<'c> StyleAttr<'c> {
         start_idx: usize,
         end_idx: usize,
         cell: &'c Cell,
+        wide: bool,
         hl: &'c HighlightMap,
     ) -> Self {
         StyleAttr {
             italic: cell.hl.italic,
             bold: cell.hl.bold,
+            wide,
             foreground: hl.cell_fg(cell),
             background: hl.cell_bg(cell),
             empty: false,
@@ -348,6 +385,7 @@ impl<'c> StyleAttr<'c> {
         start_idx: usize,
         end_idx: usize,
         cell: &'c Cell,
+        wide: bool,
         hl: &'c HighlightMap,
     ) -> Option<StyleAttr<'c>> {
         // don't check attr for space
@@ -357,7 +395,7 @@ impl<'c> StyleAttr<'c> {
         }
 
 
-        let style_attr = Self::from(start_idx, end_idx, cell, hl);
+        let style_attr = Self::from(start_idx, end_idx, cell, wide, hl);
 
         if self != &style_attr {
             Some(style_attr)
@@ -367,7 +405,7 @@ impl<'c> StyleAttr<'c> {
         }
     }
 
-    fn insert_into(&self, attr_list: &pango::AttrList) {
+    fn insert_into(&self, attr_list: &pango::AttrList, font_features: &render::FontFeatures) {
         if self.empty {
             return;
         }
@@ -386,6 +424,21 @@ impl<'c> StyleAttr<'c> {
             );
         }
 
+        // explicit face goes after weight and style, so it overrides them
+        let face = render::FontFace::of(self.bold, self.italic);
+        if let Some(attr) = font_features
+            .face(face)
+            .and_then(sys_pango::attribute::new_font_desc)
+        {
+            self.insert_attr(attr_list, attr);
+        }
+
+        if self.wide {
+            if let Some(attr) = font_features.wide_attr() {
+                self.insert_attr(attr_list, attr.clone());
+            }
+        }
+
         if let Some(fg) = self.foreground {
             let (r, g, b) = fg.to_u16();
             self.insert_attr(
@@ -403,6 +456,12 @@ impl<'c> StyleAttr<'c> {
         }
     }
 
+    fn push_run(&self, runs: &mut Vec<(usize, usize)>) {
+        if !self.empty && self.start_idx < self.end_idx {
+            runs.push((self.start_idx, self.end_idx));
+        }
+    }
+
     #[inline]
     fn insert_attr(&self, attr_list: &pango::AttrList, mut attr: pango::Attribute) {
         attr.set_start_index(self.start_idx as u32);
@@ -415,6 +474,7 @@ impl<'c> PartialEq for StyleAttr<'c> {
     fn eq(&self, other: &Self) -> bool {
         self.italic == other.italic
             && self.bold == other.bold
+            && self.wide == other.wide
             && self.foreground == other.foreground
             && self.empty == other.empty
             && self.background == other.background
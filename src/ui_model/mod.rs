@@ -9,6 +9,7 @@ mod model_rect;
 
 pub use self::cell::Cell;
 pub use self::item::Item;
+pub use self::item::{BoxedGlyphs, Glyphs};
 pub use self::line::{Line, StyledLine};
 pub use self::model_layout::ModelLayout;
 pub use self::model_rect::{ModelRect, ModelRectVec};
@@ -175,6 +176,14 @@ impl UiModel {
             row.clear_glyphs();
         }
     }
+
+    /// Window content is blended with winblend, nvim sets blend
+    /// for every cell of such window. Cells without blend would not
+    /// paint default background, so all cells are checked.
+    pub fn is_blended(&self) -> bool {
+        let mut cells = self.model.iter().flat_map(|line| line.line.iter()).peekable();
+        cells.peek().is_some() && cells.all(|cell| cell.hl.blend > 0)
+    }
 }
 
 #[cfg(test)]
@@ -273,4 +282,25 @@ mod tests {
         assert_eq!(5, rect.bot);
         assert_eq!(5, rect.right);
     }
+
+    #[test]
+    fn test_is_blended() {
+        let mut model = UiModel::new(2, 3);
+        assert!(!model.is_blended());
+
+        let mut hl = Highlight::new();
+        hl.blend = 30;
+        let hl = Rc::new(hl);
+        for line in model.model_mut() {
+            for cell in line.line.iter_mut() {
+                cell.hl = hl.clone();
+            }
+        }
+        assert!(model.is_blended());
+
+        model.model_mut()[1].line[2].hl = Rc::new(Highlight::new());
+        assert!(!model.is_blended());
+
+        assert!(!UiModel::empty().is_blended());
+    }
 }
@@ -127,7 +127,7 @@ impl ModelLayout {
 
                     self.model.put_one(row_idx, col_idx, ch, false, hl.clone());
                     if ch_width > 1 {
-                        self.model.put_one(row_idx, col_idx, "", true, hl.clone());
+                        self.model.put_one(row_idx, col_idx + 1, "", true, hl.clone());
                     }
 
                     if max_col_idx < col_idx {
@@ -172,6 +172,12 @@ impl ModelLayout {
 mod tests {
     use super::*;
 
+    use pango::prelude::*;
+
+    use crate::highlight::HighlightMap;
+    use crate::render;
+    use crate::ui_model::StyledLine;
+
     #[test]
     fn test_count_lines() {
         let lines = vec![vec![(Rc::new(Highlight::new()), vec!["a".to_owned(); 5])]];
@@ -259,4 +265,117 @@ mod tests {
         assert_eq!(1, rows);
         assert_eq!(6, cols);
     }
+
+    fn styled_line(model: &ModelLayout) -> StyledLine {
+        StyledLine::from(
+            &model.model.model()[0],
+            &HighlightMap::new(),
+            &render::FontFeatures::new(),
+        )
+    }
+
+    fn font_ctx() -> render::Context {
+        let font_map = pangocairo::FontMap::get_default().unwrap();
+        let pango_context = font_map.create_context().unwrap();
+        pango_context.set_font_description(&pango::FontDescription::from_string("Monospace 12"));
+        render::Context::new(pango_context)
+    }
+
+    #[test]
+    fn test_runs_by_highlight() {
+        let mut bold = Highlight::new();
+        bold.bold = true;
+
+        let lines = vec![vec![
+            (
+                Rc::new(bold),
+                vec!["a", "-", ">", " ", "b"]
+                    .into_iter()
+                    .map(|c| c.to_owned())
+                    .collect(),
+            ),
+            (Rc::new(Highlight::new()), vec!["c".to_owned()]),
+        ]];
+        let mut model = ModelLayout::new(7);
+        model.layout(lines);
+
+        let styled_line = styled_line(&model);
+        assert_eq!("a-> bc ", styled_line.line_str);
+        // space inside highlight run is not a split point
+        assert_eq!(vec![(0, 5), (5, 2)], styled_line.runs().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_whitespace_run_skipped() {
+        let lines = vec![vec![(Rc::new(Highlight::new()), vec![" ".to_owned(); 3])]];
+        let mut model = ModelLayout::new(5);
+        model.layout(lines);
+
+        assert_eq!(0, styled_line(&model).runs().count());
+    }
+
+    #[test]
+    fn test_double_width_cell_for_byte() {
+        let lines = vec![vec![(
+            Rc::new(Highlight::new()),
+            vec!["a".to_owned(), "あ".to_owned(), "b".to_owned()],
+        )]];
+        let mut model = ModelLayout::new(5);
+        model.layout(lines);
+
+        assert!(model.model.model()[0][2].double_width);
+
+        let styled_line = styled_line(&model);
+        assert_eq!(0, styled_line.cell_for_byte(0));
+        assert_eq!(1, styled_line.cell_for_byte(1));
+        assert_eq!(1, styled_line.cell_for_byte(3));
+        assert_eq!(3, styled_line.cell_for_byte(4));
+    }
+
+    #[test]
+    fn test_align_ligature() {
+        // "->" shaped as one glyph, then space and "b"
+        let glyphs = [(0, 2048, 0), (2, 1024, 0), (3, 1024, 0)];
+        let geometry = render::align_to_columns(&glyphs, |byte| byte, 1024, 4);
+
+        assert_eq!(vec![(0, 0), (0, 2048), (4096, 3072)], geometry);
+    }
+
+    #[test]
+    fn test_align_double_width() {
+        // "aあb", あ is 3 bytes and takes 2 columns
+        let cols = [0, 1, 1, 1, 3];
+        let glyphs = [(0, 1024, 0), (1, 1800, 0), (4, 1024, 0)];
+        let geometry = render::align_to_columns(&glyphs, |byte| cols[byte], 1024, 4);
+
+        assert_eq!(vec![(0, 0), (0, 1024), (4096, 3072)], geometry);
+    }
+
+    #[test]
+    fn test_align_rtl_and_marks() {
+        // glyphs in visual order for right to left run,
+        // second cluster has base glyph and combining mark
+        let glyphs = [(3, 1024, 0), (1, 1024, 0), (1, 0, -300), (0, 1024, 0)];
+        let geometry = render::align_to_columns(&glyphs, |byte| byte, 1024, 4);
+
+        assert_eq!(
+            vec![(0, 3072), (0, 1024), (0, 1024 + 1024 - 300), (4096, 0)],
+            geometry
+        );
+    }
+
+    #[test]
+    fn test_itemize_whole_run() {
+        let lines = vec![vec![(
+            Rc::new(Highlight::new()),
+            vec!["a", " ", "b"].into_iter().map(|c| c.to_owned()).collect(),
+        )]];
+        let mut model = ModelLayout::new(3);
+        model.layout(lines);
+
+        let items = font_ctx().itemize(&styled_line(&model));
+        assert_eq!(1, items.len());
+        assert_eq!(0, items[0].offset());
+        assert_eq!(3, items[0].length());
+    }
 }
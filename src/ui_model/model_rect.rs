@@ -75,6 +75,14 @@ impl ModelRect {
         }
     }
 
+    /// Move rect by given amount of rows and columns
+    pub fn translate(&mut self, rows: usize, cols: usize) {
+        self.top += rows;
+        self.bot += rows;
+        self.left += cols;
+        self.right += cols;
+    }
+
     #[inline]
     fn in_horizontal(&self, other: &ModelRect) -> bool {
         other.left >= self.left && other.left <= self.right